@@ -0,0 +1,46 @@
+use std::sync::Arc;
+
+// Library Imports
+use anyhow::Result;
+use async_trait::async_trait;
+
+// Crate Level Imports
+use crate::utils::{command_help, App, Flag, Matches};
+
+// Super Imports
+use super::Command;
+
+/// Struct implementation for the `Check` command, which reports every
+/// diagnostic in the given files without generating any code.
+pub struct Check;
+
+#[async_trait]
+impl Command for Check {
+    const NAME: &'static str = "check";
+    const ABOUT: &'static str = "Reports the errors in the given files without compiling them";
+    const FLAGS: &'static [Flag] = &[];
+
+    fn help() -> String {
+        command_help(
+            "check <filename>...",
+            "Parses and analyses the given files without compiling them, exiting with a\nfailure status if any of them has an error.",
+            Self::FLAGS,
+        )
+    }
+
+    async fn exec(app: Arc<App>, matches: Matches) -> Result<()> {
+        app.file(&matches, Self::NAME);
+        let filenames = &matches.args;
+        // Every file is checked, so that one run reports everything.
+        let mut errors = 0;
+        for filename in filenames {
+            let (_, diagnostics, sources) = app.analyze(filename);
+            app.emit(&diagnostics, &sources);
+            errors += diagnostics.error_count();
+        }
+        if errors > 0 {
+            app.fail(errors)
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,228 @@
+use super::Command;
+use crate::utils::{command_help, App, Flag, Matches};
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+use colored::Colorize;
+use compiler::{rustcompiler::RustCompiler, Compiler};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Instant;
+use zeta_gcc::{Compile as zCompile, Emit, Options, Toolchain};
+
+/// Struct implementation for the `Compile` command, which is also what
+/// `zetac <filename>` runs.
+pub struct Compile;
+
+const USE_CLANG: Flag = Flag {
+    names: &["--useclang", "-ucg"],
+    help: "links with clang (default)",
+    value: None,
+};
+const USE_GCC: Flag = Flag {
+    names: &["--usegcc", "-ugcc"],
+    help: "links with gcc",
+    value: None,
+};
+pub(crate) const VERBOSE: Flag = Flag {
+    names: &["--verbose", "-vb"],
+    help: "prints the syntax tree and the code gccjit generates",
+    value: None,
+};
+pub(crate) const RELEASE: Flag = Flag {
+    names: &["--release", "-r"],
+    help: "builds for deployment, with every optimisation",
+    value: None,
+};
+const USE_RUST: Flag = Flag {
+    names: &["--userust"],
+    help: "same as --emit=rust",
+    value: None,
+};
+const OUTPUT: Flag = Flag {
+    names: &["-o", "--out"],
+    help: "where to write the output, named after the file by default",
+    value: Some("<path>"),
+};
+const EMIT: Flag = Flag {
+    names: &["--emit"],
+    help: "exe (default), obj, asm, dylib, gimple or rust",
+    value: Some("<kind>"),
+};
+/// What `compile` produces: Rust source, or something gccjit writes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Target {
+    Native(Emit),
+    Rust,
+}
+
+impl FromStr for Target {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "exe" => Ok(Target::Native(Emit::Executable)),
+            "obj" => Ok(Target::Native(Emit::Object)),
+            "asm" => Ok(Target::Native(Emit::Assembly)),
+            "dylib" => Ok(Target::Native(Emit::DynamicLibrary)),
+            "gimple" => Ok(Target::Native(Emit::Gimple)),
+            "rust" => Ok(Target::Rust),
+            _ => Err("expected one of exe, obj, asm, dylib, gimple or rust"),
+        }
+    }
+}
+
+/// Everything `compile` was asked to do, from its command line.
+struct Args<'m> {
+    file: &'m str,
+    output: Option<PathBuf>,
+    target: Target,
+    options: Options,
+}
+
+impl<'m> Args<'m> {
+    fn new(matches: &'m Matches) -> Result<Self> {
+        let file = matches.file(Compile::NAME)?;
+        if matches.args.len() > 1 {
+            bail!("`zetac compile` takes one file, see `zetac help compile`")
+        }
+        let toolchain = match (matches.flag(&USE_CLANG), matches.flag(&USE_GCC)) {
+            (true, true) => bail!("`--useclang` and `--usegcc` cannot be used together"),
+            (false, true) => Toolchain::Gcc,
+            _ => Toolchain::Clang,
+        };
+        let target = matches.parsed(&EMIT)?.unwrap_or(if matches.flag(&USE_RUST) {
+            Target::Rust
+        } else {
+            Target::Native(Emit::Executable)
+        });
+        Ok(Args {
+            file,
+            output: matches.parsed(&OUTPUT)?,
+            target,
+            options: Options {
+                release: matches.flag(&RELEASE),
+                verbose: matches.flag(&VERBOSE),
+                toolchain,
+            },
+        })
+    }
+}
+
+#[async_trait]
+impl Command for Compile {
+    const NAME: &'static str = "compile";
+    const ABOUT: &'static str = "Compiles the given file";
+    /// The help is generated from this list, so that what it advertises is
+    /// what is accepted.
+    const FLAGS: &'static [Flag] = &[USE_CLANG, USE_GCC, VERBOSE, RELEASE, USE_RUST, OUTPUT, EMIT];
+
+    fn help() -> String {
+        command_help(
+            "[compile] [flags] <filename>",
+            "Compiles the given file. `compile` can be left out.",
+            Self::FLAGS,
+        )
+    }
+
+    async fn exec(app: Arc<App>, matches: Matches) -> Result<()> {
+        let time = Instant::now();
+        let args = Args::new(&matches).unwrap_or_else(|e| app.error(&e.to_string()));
+        let (program, resolution, sources) = app.load(args.file);
+        match args.target {
+            // Without `-o`, the Rust goes to stdout.
+            Target::Rust => {
+                let rust = RustCompiler::new(program, resolution).compile();
+                match args.output {
+                    Some(output) => std::fs::write(&output, rust).unwrap_or_else(|e| {
+                        app.error(&format!("{}: {}", output.display(), e))
+                    }),
+                    None => println!("{}", rust),
+                }
+                return Ok(());
+            }
+            Target::Native(emit) => {
+                if args.options.verbose {
+                    println!("{:#?}", program);
+                }
+                // `dir/name.trq` builds `name`, `name.o`, ... in the current directory.
+                let file = args.file;
+                let output = args.output.unwrap_or_else(|| {
+                    let stem = Path::new(file).file_stem().unwrap_or_default();
+                    Path::new(stem).with_extension(emit.extension())
+                });
+                let gcc = zCompile::new(sources, args.options);
+                gcc.compile(program, &resolution, emit, &output)
+                    .unwrap_or_else(|e| app.error(&format!("{}: {}", output.display(), e)));
+            }
+        }
+        println!(
+            "Completed in {} seconds",
+            time.elapsed().as_secs_f64().to_string().as_str().blue()
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gccjit::OptimizationLevel;
+
+    fn parse(argv: &[&str]) -> Matches {
+        let app = App { argv: Vec::new() };
+        let argv: Vec<String> = argv.iter().map(|arg| arg.to_string()).collect();
+        app.parse(Compile::NAME, &argv, Compile::FLAGS).unwrap()
+    }
+
+    #[test]
+    fn maps_flags_onto_options() {
+        let matches = parse(&["main.trq"]);
+        let args = Args::new(&matches).unwrap();
+        assert_eq!(args.file, "main.trq");
+        assert_eq!(args.target, Target::Native(Emit::Executable));
+        let options = Options {
+            release: false,
+            verbose: false,
+            toolchain: Toolchain::Clang,
+        };
+        assert_eq!(args.options, options);
+        assert!(matches!(
+            args.options.optimization_level(),
+            OptimizationLevel::None
+        ));
+
+        let matches = parse(&["--release", "main.trq", "-vb"]);
+        let args = Args::new(&matches).unwrap();
+        assert!(args.options.release && args.options.verbose);
+        assert!(matches!(
+            args.options.optimization_level(),
+            OptimizationLevel::Aggressive
+        ));
+    }
+
+    #[test]
+    fn picks_one_toolchain() {
+        let matches = parse(&["main.trq", "--usegcc"]);
+        assert_eq!(Args::new(&matches).unwrap().options.toolchain, Toolchain::Gcc);
+        let matches = parse(&["main.trq", "-ucg"]);
+        assert_eq!(Args::new(&matches).unwrap().options.toolchain, Toolchain::Clang);
+        let matches = parse(&["main.trq", "-ucg", "--usegcc"]);
+        let error = Args::new(&matches).err().unwrap();
+        assert!(error.to_string().contains("cannot be used together"), "{}", error);
+    }
+
+    #[test]
+    fn reads_the_target_and_output() {
+        let matches = parse(&["main.trq", "--emit=obj", "-o", "out.o"]);
+        let args = Args::new(&matches).unwrap();
+        assert_eq!(args.target, Target::Native(Emit::Object));
+        assert_eq!(args.output, Some(PathBuf::from("out.o")));
+        let matches = parse(&["--userust", "main.trq"]);
+        assert_eq!(Args::new(&matches).unwrap().target, Target::Rust);
+        let matches = parse(&["main.trq", "--emit", "wasm"]);
+        assert!(Args::new(&matches).is_err());
+        let matches = parse(&["main.trq", "other.trq"]);
+        assert!(Args::new(&matches).is_err());
+    }
+}
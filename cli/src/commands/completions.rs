@@ -0,0 +1,143 @@
+use std::str::FromStr;
+use std::sync::Arc;
+
+// Library Imports
+use anyhow::Result;
+use async_trait::async_trait;
+
+// Crate Level Imports
+use crate::utils::{command_help, App, Flag, Matches, HELP};
+
+// Super Imports
+use super::{AppCommand, Command};
+
+/// A shell that `completions` can write a script for.
+#[derive(Clone, Copy)]
+enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl FromStr for Shell {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bash" => Ok(Shell::Bash),
+            "zsh" => Ok(Shell::Zsh),
+            "fish" => Ok(Shell::Fish),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Every spelling of every flag `command` accepts.
+fn flag_names(command: AppCommand) -> impl Iterator<Item = &'static str> {
+    command
+        .flags()
+        .iter()
+        .chain([&HELP])
+        .flat_map(|flag| flag.names.iter().copied())
+}
+
+fn bash() -> String {
+    let commands: Vec<&str> = AppCommand::ALL.iter().map(|c| c.name()).collect();
+    let cases: String = AppCommand::ALL
+        .iter()
+        .map(|&command| {
+            let flags: Vec<&str> = flag_names(command).collect();
+            format!("        {}) flags=\"{}\" ;;\n", command.name(), flags.join(" "))
+        })
+        .collect();
+    format!(
+        r#"_zetac() {{
+    local cur="${{COMP_WORDS[COMP_CWORD]}}" flags
+    if [ "$COMP_CWORD" -eq 1 ] && [[ "$cur" != -* ]]; then
+        COMPREPLY=($(compgen -W "{}" -- "$cur") $(compgen -f -- "$cur"))
+        return
+    fi
+    case "${{COMP_WORDS[1]}}" in
+{}        *) flags="{}" ;;
+    esac
+    if [[ "$cur" == -* ]]; then
+        COMPREPLY=($(compgen -W "$flags" -- "$cur"))
+    else
+        COMPREPLY=($(compgen -f -- "$cur"))
+    fi
+}}
+complete -F _zetac zetac
+"#,
+        commands.join(" "),
+        cases,
+        flag_names(AppCommand::Compile).collect::<Vec<_>>().join(" "),
+    )
+}
+
+fn fish() -> String {
+    let mut script = String::from(
+        "complete -c zetac -f -n __fish_use_subcommand -a '(__fish_complete_suffix .trq)'\n",
+    );
+    for &command in AppCommand::ALL {
+        script.push_str(&format!(
+            "complete -c zetac -f -n __fish_use_subcommand -a {} -d '{}'\n",
+            command.name(),
+            command.about().replace('\'', "\\'")
+        ));
+        for flag in command.flags().iter().chain([&HELP]) {
+            let names: String = flag
+                .names
+                .iter()
+                .map(|name| match name.strip_prefix("--") {
+                    Some(long) => format!(" -l {}", long),
+                    // fish calls single-dash flags with several letters old-style.
+                    None if name.len() > 2 => format!(" -o {}", &name[1..]),
+                    None => format!(" -s {}", &name[1..]),
+                })
+                .collect();
+            let value = if flag.value.is_some() { " -r" } else { "" };
+            script.push_str(&format!(
+                "complete -c zetac -n '__fish_seen_subcommand_from {}'{}{} -d '{}'\n",
+                command.name(),
+                names,
+                value,
+                flag.help.replace('\'', "\\'")
+            ));
+        }
+    }
+    script
+}
+
+/// Struct implementation for the `Completions` command.
+pub struct Completions;
+
+#[async_trait]
+impl Command for Completions {
+    const NAME: &'static str = "completions";
+    const ABOUT: &'static str = "Prints a completion script for bash, zsh or fish";
+    const FLAGS: &'static [Flag] = &[];
+
+    fn help() -> String {
+        command_help(
+            "completions <bash|zsh|fish>",
+            "Prints a script that completes zetac's commands and flags in the given shell.\nFor bash, add `source <(zetac completions bash)` to your `.bashrc`.",
+            Self::FLAGS,
+        )
+    }
+
+    async fn exec(app: Arc<App>, matches: Matches) -> Result<()> {
+        let shell = app.file(&matches, Self::NAME);
+        let script = match Shell::from_str(shell) {
+            Ok(Shell::Bash) => bash(),
+            // zsh can run bash completions once `bashcompinit` is loaded.
+            Ok(Shell::Zsh) => format!("autoload -U +X bashcompinit && bashcompinit\n{}", bash()),
+            Ok(Shell::Fish) => fish(),
+            Err(()) => app.error(&format!(
+                "cannot complete for `{}`, expected one of bash, zsh or fish",
+                shell
+            )),
+        };
+        print!("{}", script);
+        Ok(())
+    }
+}
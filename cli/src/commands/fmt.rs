@@ -0,0 +1,84 @@
+use std::fs;
+use std::sync::Arc;
+
+// Library Imports
+use anyhow::Result;
+use async_trait::async_trait;
+use colored::Colorize;
+use lexer::{diagnostics::Diagnostics, SourceMap};
+
+// Crate Level Imports
+use crate::utils::{command_help, App, Flag, Matches};
+
+// Super Imports
+use super::Command;
+
+const CHECK: Flag = Flag {
+    names: &["--check"],
+    help: "lists the files that aren't formatted instead of formatting them",
+    value: None,
+};
+
+/// Struct implementation for the `Fmt` command, which re-indents source
+/// files, see [`lexer::format`].
+pub struct Fmt;
+
+#[async_trait]
+impl Command for Fmt {
+    const NAME: &'static str = "fmt";
+    const ABOUT: &'static str = "Formats the given files in place";
+    const FLAGS: &'static [Flag] = &[CHECK];
+
+    fn help() -> String {
+        command_help(
+            "fmt [flags] <filename>...",
+            "Re-indents the given files by how deeply they are nested, and removes\ntrailing whitespace and repeated blank lines. With `--check`, exits with a\nfailure status if any of them isn't formatted.",
+            Self::FLAGS,
+        )
+    }
+
+    async fn exec(app: Arc<App>, matches: Matches) -> Result<()> {
+        app.file(&matches, Self::NAME);
+        let check = matches.flag(&CHECK);
+        let mut errors = 0;
+        let mut unformatted = 0;
+        for filename in &matches.args {
+            let source = fs::read_to_string(filename)
+                .unwrap_or_else(|e| app.error(&format!("{}: {}", filename, e)));
+            let formatted = match lexer::format(&source, filename) {
+                Ok(formatted) => formatted,
+                Err(e) => {
+                    // Spans point into file 0, so the file gets a map of its own.
+                    let mut sources = SourceMap::new();
+                    sources.add_file(filename, &source);
+                    let mut diagnostics = Diagnostics::new();
+                    diagnostics.push(e.to_diagnostic());
+                    app.emit(&diagnostics, &sources);
+                    errors += 1;
+                    continue;
+                }
+            };
+            if formatted == source {
+                continue;
+            }
+            if check {
+                println!("{} {}", "Unformatted".yellow().bold(), filename);
+                unformatted += 1;
+            } else {
+                fs::write(filename, formatted)
+                    .unwrap_or_else(|e| app.error(&format!("{}: {}", filename, e)));
+            }
+        }
+        if errors > 0 {
+            app.fail(errors)
+        }
+        if unformatted > 0 {
+            app.error(&format!(
+                "{} file{} would be reformatted by `zetac fmt`",
+                unformatted,
+                if unformatted == 1 { "" } else { "s" }
+            ))
+        }
+        Ok(())
+    }
+}
@@ -1,3 +1,4 @@
+use std::str::FromStr;
 use std::sync::Arc;
 
 // Library Imports
@@ -6,37 +7,65 @@ use async_trait::async_trait;
 use colored::Colorize;
 
 // Crate Level Imports
-use crate::utils::App;
-use crate::utils::VERSION;
+use crate::utils::{flags_help, App, Flag, Matches, HELP, VERSION};
 
 // Super Imports
-use super::Command;
+use super::{AppCommand, Command};
 
 /// Struct implementation for the `Help` command.
 pub struct Help;
 
 #[async_trait]
 impl Command for Help {
+    const NAME: &'static str = "help";
+    const ABOUT: &'static str = "Displays help information, for one command if it is given";
+    const FLAGS: &'static [Flag] = &[];
+
     fn help() -> String {
+        let width = AppCommand::ALL
+            .iter()
+            .map(|command| command.name().len())
+            .max()
+            .unwrap_or(0);
+        let commands: String = AppCommand::ALL
+            .iter()
+            .map(|command| {
+                format!(
+                    "  {} {} - {}\n",
+                    "*".bright_magenta().bold(),
+                    format!("{:width$}", command.name(), width = width).bright_blue(),
+                    command.about()
+                )
+            })
+            .collect();
         format!(
             r#"zetac {}
     
 Displays help information.
 Usage: {} {} {}
+       {} {}
 Commands:
-  {} {} - Compiles the given file 
-  "#,
+{}Flags:
+{}"#,
             VERSION.bright_green().bold(),
             "zetac".bright_green().bold(),
-            "[commands]".bright_purple(),
+            "<command>".bright_purple(),
             "[flags]".bright_purple(),
-            "*".bright_magenta().bold(),
-            "<filename>".bright_blue(),
+            "zetac".bright_green().bold(),
+            "help <command>".bright_purple(),
+            commands,
+            flags_help(&[HELP]),
         )
     }
 
-    async fn exec(_app: Arc<App>) -> Result<()> {
-        println!("{}", Self::help());
+    async fn exec(app: Arc<App>, matches: Matches) -> Result<()> {
+        match matches.args.first() {
+            Some(name) => match AppCommand::from_str(name) {
+                Ok(command) => println!("{}", command.help()),
+                Err(()) => app.error(&format!("no command `{}`, see `zetac help`", name)),
+            },
+            None => println!("{}", Self::help()),
+        }
         Ok(())
     }
 }
@@ -2,18 +2,33 @@ use std::str::FromStr;
 use std::sync::Arc;
 
 // Library Imports
-use anyhow::Result;
+use anyhow::{bail, Result};
 use async_trait::async_trait;
 
 // Crate Level Imports
-use crate::utils::App;
+use crate::utils::{App, Flag, Matches};
 
 // Modules
+pub mod check;
+pub mod compile;
+pub mod completions;
+pub mod fmt;
 pub mod help;
-pub mod unknown;
-#[derive(Debug)]
+pub mod new;
+pub mod run;
+pub mod test;
+pub mod version;
+
+#[derive(Debug, Clone, Copy)]
 pub enum AppCommand {
-    Unknown,
+    Compile,
+    Run,
+    Check,
+    Fmt,
+    New,
+    Test,
+    Completions,
+    Version,
     Help,
 }
 
@@ -21,44 +36,166 @@ impl FromStr for AppCommand {
     type Err = ();
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "compile" => Ok(Self::Unknown),
-            _ => Err(()),
-        }
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|command| command.name() == s)
+            .ok_or(())
     }
 }
 
 impl AppCommand {
-    pub fn current() -> Option<Self> {
-        if std::env::args().len() == 1 {
-            return Some(Self::Help);
+    /// Every command, in the order `zetac help` lists them.
+    pub const ALL: &'static [AppCommand] = &[
+        Self::Compile,
+        Self::Run,
+        Self::Check,
+        Self::Fmt,
+        Self::New,
+        Self::Test,
+        Self::Completions,
+        Self::Version,
+        Self::Help,
+    ];
+
+    /// Picks the command named by the first argument, and returns it with
+    /// the arguments that are its own. `zetac <filename>` and `zetac [flags]
+    /// <filename>` are short for `zetac compile`, and `--help` and
+    /// `--version` work without a command.
+    pub fn current(app: &App) -> Result<(Self, Vec<String>)> {
+        let argv = &app.argv;
+        let first = match argv.first() {
+            Some(first) => first.as_str(),
+            None => return Ok((Self::Help, vec![])),
+        };
+        Ok(match first {
+            "--help" | "-h" => (Self::Help, argv[1..].to_vec()),
+            "--version" | "-v" => (Self::Version, argv[1..].to_vec()),
+            _ => match Self::from_str(first) {
+                Ok(command) => (command, argv[1..].to_vec()),
+                Err(()) if first.starts_with('-') || first.ends_with(".trq") => {
+                    (Self::Compile, argv.clone())
+                }
+                Err(()) => bail!("no command `{}`, see `zetac help`", first),
+            },
+        })
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Compile => compile::Compile::NAME,
+            Self::Run => run::Run::NAME,
+            Self::Check => check::Check::NAME,
+            Self::Fmt => fmt::Fmt::NAME,
+            Self::New => new::New::NAME,
+            Self::Test => test::Test::NAME,
+            Self::Completions => completions::Completions::NAME,
+            Self::Version => version::Version::NAME,
+            Self::Help => help::Help::NAME,
         }
+    }
+
+    pub fn about(self) -> &'static str {
+        match self {
+            Self::Compile => compile::Compile::ABOUT,
+            Self::Run => run::Run::ABOUT,
+            Self::Check => check::Check::ABOUT,
+            Self::Fmt => fmt::Fmt::ABOUT,
+            Self::New => new::New::ABOUT,
+            Self::Test => test::Test::ABOUT,
+            Self::Completions => completions::Completions::ABOUT,
+            Self::Version => version::Version::ABOUT,
+            Self::Help => help::Help::ABOUT,
+        }
+    }
 
-        match std::env::args().nth(1) {
-            Some(cmd) => Self::from_str(cmd.as_str()).ok(),
-            None => None,
+    pub fn flags(self) -> &'static [Flag] {
+        match self {
+            Self::Compile => compile::Compile::FLAGS,
+            Self::Run => run::Run::FLAGS,
+            Self::Check => check::Check::FLAGS,
+            Self::Fmt => fmt::Fmt::FLAGS,
+            Self::New => new::New::FLAGS,
+            Self::Test => test::Test::FLAGS,
+            Self::Completions => completions::Completions::FLAGS,
+            Self::Version => version::Version::FLAGS,
+            Self::Help => help::Help::FLAGS,
         }
     }
 
-    pub fn help(&self) -> String {
+    pub fn help(self) -> String {
         match self {
-            Self::Unknown => unknown::Compile::help(),
+            Self::Compile => compile::Compile::help(),
+            Self::Run => run::Run::help(),
+            Self::Check => check::Check::help(),
+            Self::Fmt => fmt::Fmt::help(),
+            Self::New => new::New::help(),
+            Self::Test => test::Test::help(),
+            Self::Completions => completions::Completions::help(),
+            Self::Version => version::Version::help(),
             Self::Help => help::Help::help(),
         }
     }
 
-    pub async fn run(&self, app: App) -> Result<()> {
+    pub async fn run(self, app: App, matches: Matches) -> Result<()> {
         let app = Arc::new(app);
         match self {
-            Self::Unknown => unknown::Compile::exec(app).await,
-            Self::Help => help::Help::exec(app).await,
+            Self::Compile => compile::Compile::exec(app, matches).await,
+            Self::Run => run::Run::exec(app, matches).await,
+            Self::Check => check::Check::exec(app, matches).await,
+            Self::Fmt => fmt::Fmt::exec(app, matches).await,
+            Self::New => new::New::exec(app, matches).await,
+            Self::Test => test::Test::exec(app, matches).await,
+            Self::Completions => completions::Completions::exec(app, matches).await,
+            Self::Version => version::Version::exec(app, matches).await,
+            Self::Help => help::Help::exec(app, matches).await,
         }
     }
 }
 
 #[async_trait]
 pub trait Command {
+    /// What the command is called on the command line.
+    const NAME: &'static str;
+    /// What the command does, in one line, for `zetac help`.
+    const ABOUT: &'static str;
+    /// Every flag the command accepts, apart from `--help`.
+    const FLAGS: &'static [Flag];
+
     fn help() -> String;
 
-    async fn exec(app: Arc<App>) -> Result<()>;
+    async fn exec(app: Arc<App>, matches: Matches) -> Result<()>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn current(argv: &[&str]) -> Result<(AppCommand, Vec<String>)> {
+        let argv = argv.iter().map(|arg| arg.to_string()).collect();
+        AppCommand::current(&App { argv })
+    }
+
+    #[test]
+    fn picks_the_command() {
+        let (command, argv) = current(&["run", "main.trq", "--", "x"]).unwrap();
+        assert_eq!(command.name(), "run");
+        assert_eq!(argv, vec!["main.trq", "--", "x"]);
+        assert_eq!(current(&[]).unwrap().0.name(), "help");
+        assert_eq!(current(&["-v"]).unwrap().0.name(), "version");
+    }
+
+    #[test]
+    fn falls_back_to_compile() {
+        let (command, argv) = current(&["main.trq", "-o", "out"]).unwrap();
+        assert_eq!(command.name(), "compile");
+        assert_eq!(argv, vec!["main.trq", "-o", "out"]);
+        assert_eq!(current(&["--release", "main.trq"]).unwrap().0.name(), "compile");
+    }
+
+    #[test]
+    fn rejects_unknown_commands() {
+        let error = current(&["build", "main.trq"]).err().unwrap();
+        assert!(error.to_string().contains("no command `build`"), "{}", error);
+    }
 }
@@ -0,0 +1,59 @@
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+// Library Imports
+use anyhow::Result;
+use async_trait::async_trait;
+use colored::Colorize;
+
+// Crate Level Imports
+use crate::utils::{command_help, App, Flag, Matches};
+
+// Super Imports
+use super::Command;
+
+const MAIN: &str = "fn main(): int {
+    return 0
+}
+";
+
+const TEST: &str = "// `zetac test` runs every file in this directory, which passes if its
+// `main` returns 0.
+fn main(): int {
+    return 0
+}
+";
+
+/// Struct implementation for the `New` command, which starts a project.
+pub struct New;
+
+#[async_trait]
+impl Command for New {
+    const NAME: &'static str = "new";
+    const ABOUT: &'static str = "Creates a new project in the given directory";
+    const FLAGS: &'static [Flag] = &[];
+
+    fn help() -> String {
+        command_help(
+            "new <directory>",
+            "Creates a new project in the given directory, with a `main.trq` and a\n`tests` directory for `zetac test`.",
+            Self::FLAGS,
+        )
+    }
+
+    async fn exec(app: Arc<App>, matches: Matches) -> Result<()> {
+        let dir = Path::new(app.file(&matches, Self::NAME));
+        if dir.exists() {
+            app.error(&format!("{} already exists", dir.display()))
+        }
+        let created = fs::create_dir_all(dir.join("tests"))
+            .and_then(|()| fs::write(dir.join("main.trq"), MAIN))
+            .and_then(|()| fs::write(dir.join("tests").join("main.trq"), TEST));
+        if let Err(e) = created {
+            app.error(&format!("{}: {}", dir.display(), e))
+        }
+        println!("{} {}", "Created".bright_green().bold(), dir.display());
+        Ok(())
+    }
+}
@@ -0,0 +1,50 @@
+use std::process::exit;
+use std::sync::Arc;
+
+// Library Imports
+use anyhow::Result;
+use async_trait::async_trait;
+
+// Crate Level Imports
+use crate::utils::{command_help, App, Flag, Matches};
+use zeta_gcc::{Compile, Options, Toolchain};
+
+// Super Imports
+use super::compile::{RELEASE, VERBOSE};
+use super::Command;
+
+/// Struct implementation for the `Run` command, which compiles a program in
+/// memory and runs it without writing anything to disk.
+pub struct Run;
+
+#[async_trait]
+impl Command for Run {
+    const NAME: &'static str = "run";
+    const ABOUT: &'static str = "Compiles the given file in memory and runs it";
+    const FLAGS: &'static [Flag] = &[VERBOSE, RELEASE];
+
+    fn help() -> String {
+        command_help(
+            "run [flags] <filename> [-- args]",
+            "Compiles the given file in memory and runs it with `args`, exiting with its exit code.",
+            Self::FLAGS,
+        )
+    }
+
+    async fn exec(app: Arc<App>, matches: Matches) -> Result<()> {
+        let filename = app.file(&matches, Self::NAME);
+        let (program, resolution, sources) = app.load(filename);
+        let options = Options {
+            release: matches.flag(&RELEASE),
+            verbose: matches.flag(&VERBOSE),
+            // Nothing is linked, so no toolchain is needed.
+            toolchain: Toolchain::Clang,
+        };
+        // The program sees its file as `argv[0]`, like a script would.
+        let args = &matches.args;
+        let code = Compile::new(sources, options)
+            .run(program, &resolution, args)
+            .unwrap_or_else(|e| app.error(&format!("{}: {}", filename, e)));
+        exit(code)
+    }
+}
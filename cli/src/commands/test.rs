@@ -0,0 +1,82 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command as Process;
+use std::sync::Arc;
+
+// Library Imports
+use anyhow::Result;
+use async_trait::async_trait;
+use colored::Colorize;
+
+// Crate Level Imports
+use crate::utils::{command_help, App, Flag, Matches};
+
+// Super Imports
+use super::compile::RELEASE;
+use super::run::Run;
+use super::Command;
+
+/// Struct implementation for the `Test` command.
+pub struct Test;
+
+#[async_trait]
+impl Command for Test {
+    const NAME: &'static str = "test";
+    const ABOUT: &'static str = "Runs every test in a directory, `tests` by default";
+    const FLAGS: &'static [Flag] = &[RELEASE];
+
+    fn help() -> String {
+        command_help(
+            "test [flags] [directory]",
+            "Runs every `.trq` file in the directory with `zetac run`. A test passes if\nits `main` returns 0. Modules the tests share can go in subdirectories.",
+            Self::FLAGS,
+        )
+    }
+
+    async fn exec(app: Arc<App>, matches: Matches) -> Result<()> {
+        let dir = Path::new(matches.args.first().map_or("tests", |dir| dir.as_str()));
+        let mut tests: Vec<PathBuf> = fs::read_dir(dir)
+            .unwrap_or_else(|e| app.error(&format!("{}: {}", dir.display(), e)))
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.is_file() && path.extension().is_some_and(|e| e == "trq"))
+            .collect();
+        tests.sort();
+        // Every test runs in a process of its own, so that one that crashes
+        // can't take the others down with it.
+        let zetac = std::env::current_exe()?;
+        let mut failed = Vec::new();
+        for test in &tests {
+            let mut run = Process::new(&zetac);
+            run.arg(Run::NAME).arg(test);
+            if matches.flag(&RELEASE) {
+                run.arg(RELEASE.names[0]);
+            }
+            let output = run.output()?;
+            if output.status.success() {
+                println!("test {} ... {}", test.display(), "ok".bright_green());
+            } else {
+                println!("test {} ... {}", test.display(), "FAILED".red().bold());
+                failed.push((test, output));
+            }
+        }
+        for (test, output) in &failed {
+            println!("\n---- {} ({}) ----", test.display(), output.status);
+            print!("{}", String::from_utf8_lossy(&output.stdout));
+            eprint!("{}", String::from_utf8_lossy(&output.stderr));
+        }
+        println!(
+            "\ntest result: {}. {} passed; {} failed",
+            if failed.is_empty() {
+                "ok".bright_green()
+            } else {
+                "FAILED".red().bold()
+            },
+            tests.len() - failed.len(),
+            failed.len()
+        );
+        if !failed.is_empty() {
+            std::process::exit(1)
+        }
+        Ok(())
+    }
+}
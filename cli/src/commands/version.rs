@@ -0,0 +1,31 @@
+use std::sync::Arc;
+
+// Library Imports
+use anyhow::Result;
+use async_trait::async_trait;
+use colored::Colorize;
+
+// Crate Level Imports
+use crate::utils::{command_help, App, Flag, Matches, VERSION};
+
+// Super Imports
+use super::Command;
+
+/// Struct implementation for the `Version` command, which `--version` also runs.
+pub struct Version;
+
+#[async_trait]
+impl Command for Version {
+    const NAME: &'static str = "version";
+    const ABOUT: &'static str = "Prints the version of zetac";
+    const FLAGS: &'static [Flag] = &[];
+
+    fn help() -> String {
+        command_help("version", "Prints the version of zetac.", Self::FLAGS)
+    }
+
+    async fn exec(_app: Arc<App>, _matches: Matches) -> Result<()> {
+        println!("zetac v{}", VERSION.bright_green().bold());
+        Ok(())
+    }
+}
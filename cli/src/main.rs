@@ -1,28 +1,19 @@
 mod commands;
 mod utils;
-use colored::Colorize;
 use commands::AppCommand;
-use std::process::exit;
-use utils::App;
+use utils::{App, HELP};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<(), anyhow::Error> {
-    let time = std::time::Instant::now();
     let app = App::initialize();
-    let cmd = AppCommand::current().unwrap_or(AppCommand::Unknown); // Default command is help\
+    let (cmd, argv) = AppCommand::current(&app).unwrap_or_else(|e| app.error(&e.to_string()));
+    let matches = app
+        .parse(cmd.name(), &argv, cmd.flags())
+        .unwrap_or_else(|e| app.error(&e.to_string()));
 
-    if app.has_flag(&["--help", "-h"]) {
+    if matches.flag(&HELP) {
         println!("{}", cmd.help());
         return Ok(());
     }
-    if app.has_flag(&["--version", "-v"]) {
-        println!("zetac v{}", utils::VERSION.bright_green().bold());
-        exit(0);
-    }
-    cmd.run(app).await?;
-    println!(
-        "Completed in {} seconds",
-        time.elapsed().as_secs_f64().to_string().as_str().blue()
-    );
-    Ok(())
+    cmd.run(app, matches).await
 }
@@ -1,6 +1,18 @@
+use anyhow::{anyhow, bail, Result};
 use colored::Colorize;
 use lazy_static::lazy_static;
-use std::{env, path::PathBuf, process::exit};
+use lexer::{
+    diagnostics::{Diagnostic, Diagnostics},
+    Program, SourceMap,
+};
+use semantic::Resolution;
+use std::{
+    env,
+    fmt::Display,
+    path::Path,
+    process::exit,
+    str::FromStr,
+};
 
 lazy_static! {
     pub static ref ERROR_TAG: String = "error".red().bold().to_string();
@@ -8,77 +20,326 @@ lazy_static! {
 
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
+/// A flag that a command accepts, under each of its spellings.
+pub struct Flag {
+    pub names: &'static [&'static str],
+    pub help: &'static str,
+    /// What the flag's value is called in the help, for flags that take one.
+    /// The value is either the next argument or follows an `=`.
+    pub value: Option<&'static str>,
+}
+
+/// Accepted by every command, which prints the command's help instead of
+/// running it.
+pub const HELP: Flag = Flag {
+    names: &["--help", "-h"],
+    help: "prints this help",
+    value: None,
+};
+
+impl Flag {
+    fn usage(&self) -> String {
+        match self.value {
+            Some(value) => format!("{} {}", self.names.join(", "), value),
+            None => self.names.join(", "),
+        }
+    }
+}
+
+/// The `Flags:` section of a command's help, one line per flag.
+pub fn flags_help<'f, I>(flags: I) -> String
+where
+    I: IntoIterator<Item = &'f Flag> + Clone,
+{
+    let width = flags.clone().into_iter().map(|f| f.usage().len()).max().unwrap_or(0);
+    flags
+        .into_iter()
+        .map(|f| {
+            format!(
+                "  {} {} - {}\n",
+                "*".bright_magenta().bold(),
+                format!("{:width$}", f.usage(), width = width).bright_blue(),
+                f.help
+            )
+        })
+        .collect()
+}
+
+/// The help for one command: what it does, how to call it and its flags.
+pub fn command_help(usage: &str, about: &str, flags: &[Flag]) -> String {
+    format!(
+        "zetac {}\n\n{}\nUsage: {} {}\nFlags:\n{}",
+        VERSION.bright_green().bold(),
+        about,
+        "zetac".bright_green().bold(),
+        usage.bright_purple(),
+        flags_help(flags.iter().chain([&HELP])),
+    )
+}
+
+/// A command line split up using the flags a command accepts.
+#[derive(Debug, Default)]
+pub struct Matches {
+    pub args: Vec<String>,
+    /// Each flag passed, by its first name, with its value if it takes one.
+    flags: Vec<(&'static str, Option<String>)>,
+}
+
+impl Matches {
+    /// Whether `flag` was passed under any of its names.
+    pub fn flag(&self, flag: &Flag) -> bool {
+        self.flags.iter().any(|(name, _)| *name == flag.names[0])
+    }
+
+    /// The value `flag` was last given.
+    pub fn value(&self, flag: &Flag) -> Option<&str> {
+        self.flags
+            .iter()
+            .rev()
+            .find(|(name, _)| *name == flag.names[0])
+            .and_then(|(_, value)| value.as_deref())
+    }
+
+    /// The value `flag` was given, parsed as a `T`.
+    pub fn parsed<T>(&self, flag: &Flag) -> Result<Option<T>>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        match self.value(flag) {
+            Some(value) => value.parse().map(Some).map_err(|e| {
+                anyhow!("invalid value `{}` for `{}`: {}", value, flag.names[0], e)
+            }),
+            None => Ok(None),
+        }
+    }
+
+    /// The file a command works on, which must be its first argument.
+    pub fn file(&self, command: &str) -> Result<&str> {
+        self.args.first().map(String::as_str).ok_or_else(|| {
+            anyhow!(
+                "`zetac {}` needs a file, see `zetac help {}`",
+                command,
+                command
+            )
+        })
+    }
+}
+
 #[derive(Debug)]
 pub struct App {
-    pub current_dir: PathBuf,
-    pub args: Vec<String>,
-    pub flags: Vec<String>,
+    /// Every argument after the program's name, in order.
+    pub argv: Vec<String>,
 }
 
 impl App {
     pub fn initialize() -> Self {
         console::colors_enabled();
-        let current_dir = env::current_dir().unwrap();
 
-        let cli_args: Vec<_> = std::env::args().collect();
-        let mut args: Vec<String> = Vec::new();
-        let mut flags: Vec<String> = Vec::new();
+        let argv: Vec<_> = env::args().skip(1).collect();
+
+        App { argv }
+    }
+
+    /// Prints `error` and exits with a failure status.
+    pub fn error(&self, error: &str) -> ! {
+        eprint!(
+            "{}",
+            Diagnostic::error(error).render(&SourceMap::default())
+        );
+        exit(1)
+    }
 
-        for arg in cli_args.into_iter().skip(1) {
-            if arg.starts_with("--") || arg.starts_with('-') {
-                flags.push(arg);
-            } else {
-                args.push(arg);
+    /// Like [`Matches::file`], but exits if there is no file.
+    pub fn file<'m>(&self, matches: &'m Matches, command: &str) -> &'m str {
+        matches
+            .file(command)
+            .unwrap_or_else(|e| self.error(&e.to_string()))
+    }
+
+    /// Loads, parses and analyses the program rooted at `filename`. The
+    /// program and its resolution are only returned if there were no errors.
+    /// Analysis is skipped for a program that doesn't parse.
+    pub fn analyze(
+        &self,
+        filename: &str,
+    ) -> (Option<(Program, Resolution)>, Diagnostics, SourceMap) {
+        let mut sources = SourceMap::new();
+        let (mut program, errors) = lexer::load_program(Path::new(filename), &mut sources)
+            .unwrap_or_else(|e| self.error(&format!("{}: {}", filename, e)));
+        let mut diagnostics = Diagnostics::new();
+        diagnostics.extend(errors);
+        if diagnostics.has_errors() {
+            return (None, diagnostics, sources);
+        }
+        let (resolution, errors) = semantic::analyze(&mut program);
+        diagnostics.extend(errors.iter().map(|e| e.to_diagnostic()));
+        if diagnostics.has_errors() {
+            return (None, diagnostics, sources);
+        }
+        (Some((program, resolution)), diagnostics, sources)
+    }
+
+    /// Like [`App::analyze`], but prints any warnings, or aborts with the errors.
+    pub fn load(&self, filename: &str) -> (Program, Resolution, SourceMap) {
+        match self.analyze(filename) {
+            (Some((program, resolution)), diagnostics, sources) => {
+                self.emit(&diagnostics, &sources);
+                (program, resolution, sources)
             }
+            (None, diagnostics, sources) => self.abort(&diagnostics, &sources),
         }
+    }
 
-        App {
-            current_dir,
-            args,
-            flags,
+    /// Prints every diagnostic to stderr.
+    pub fn emit(&self, diagnostics: &Diagnostics, sources: &SourceMap) {
+        if !diagnostics.is_empty() {
+            eprintln!("{}", diagnostics.render(sources));
         }
     }
 
-    pub fn error(&self, error: &str) -> ! {
+    /// Prints every diagnostic to stderr along with an error count, then exits
+    /// with a failure status.
+    pub fn abort(&self, diagnostics: &Diagnostics, sources: &SourceMap) -> ! {
+        self.emit(diagnostics, sources);
+        self.fail(diagnostics.error_count())
+    }
+
+    /// Prints the count of errors already reported, then exits with a
+    /// failure status.
+    pub fn fail(&self, errors: usize) -> ! {
+        let plural = if errors == 1 { "" } else { "s" };
         eprintln!(
-            "{}: {}\n  {}",
-            "Error".red().bold(),
-            "Caused By".truecolor(190, 190, 190).italic().bold(),
-            error.truecolor(195, 195, 195).bright_white()
+            "{}: aborting due to {} previous error{}",
+            *ERROR_TAG, errors, plural
         );
-        exit(0)
+        exit(1)
     }
 
-    pub fn has_flag(&self, flags: &[&str]) -> bool {
-        self.flags
-            .iter()
-            .any(|flag| flags.iter().any(|search_flag| flag == search_flag))
+    /// Splits the arguments of `command` using the flags in `accepted` and
+    /// [`HELP`], giving each flag that takes a value the argument after it.
+    /// Everything after `--` is an argument, even if it looks like a flag.
+    /// Fails on a flag that isn't accepted, or on a value that is missing or
+    /// not wanted.
+    pub fn parse(&self, command: &str, argv: &[String], accepted: &[Flag]) -> Result<Matches> {
+        let mut matches = Matches::default();
+        let mut argv = argv.iter();
+        while let Some(arg) = argv.next() {
+            if arg == "--" {
+                matches.args.extend(argv.cloned());
+                break;
+            }
+            if !arg.starts_with('-') {
+                matches.args.push(arg.clone());
+                continue;
+            }
+            let (name, inline) = match arg.find('=') {
+                Some(eq) => (&arg[..eq], Some(arg[eq + 1..].to_string())),
+                None => (arg.as_str(), None),
+            };
+            let flag = match accepted.iter().chain([&HELP]).find(|a| a.names.contains(&name)) {
+                Some(flag) => flag,
+                None => bail!(
+                    "`zetac {}` has no flag `{}`, see `zetac help {}`",
+                    command,
+                    name,
+                    command
+                ),
+            };
+            let value = match (flag.value, inline) {
+                (Some(_), Some(value)) => Some(value),
+                (Some(value), None) => match argv.next() {
+                    Some(next) => Some(next.clone()),
+                    None => bail!("`{}` expects {} after it", name, value),
+                },
+                (None, Some(_)) => bail!("`{}` does not take a value", name),
+                (None, None) => None,
+            };
+            matches.flags.push((flag.names[0], value));
+        }
+        Ok(matches)
     }
+}
 
-    pub fn filter_flag(&self, accepted_flags_arg: &[&str]) -> Vec<String> {
-        let accepted_flags: Vec<String> = self
-            .flags
-            .iter()
-            .filter(|item| accepted_flags_arg.contains(&item.as_str()))
-            .cloned()
-            .collect();
-        if accepted_flags.len() != self.flags.len() {
-            let s: Vec<String> = self
-                .flags
-                .iter()
-                .filter(|item| !accepted_flags_arg.contains(&item.as_str()))
-                .cloned()
-                .collect();
-            let mut i: String = String::new();
-            for (l, val) in s.iter().enumerate() {
-                if l != 0 {
-                    i = i + ", " + val;
-                } else {
-                    i = val.clone();
-                }
-            }
-            println!("{}: Not a valid flag {}", "Warning".yellow().bold(), i);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{fs, path::PathBuf};
+
+    /// Writes `src` to a file of its own in the temporary directory.
+    fn source(name: &str, src: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!("zetac{}{}", std::process::id(), name));
+        fs::write(&path, src).unwrap();
+        path
+    }
+
+    const VALUE: Flag = Flag {
+        names: &["--value", "-v"],
+        help: "takes a value",
+        value: Some("<value>"),
+    };
+    const SWITCH: Flag = Flag {
+        names: &["--switch"],
+        help: "takes no value",
+        value: None,
+    };
+
+    fn parse(argv: &[&str]) -> Result<Matches> {
+        let argv: Vec<String> = argv.iter().map(|arg| arg.to_string()).collect();
+        App { argv: Vec::new() }.parse("test", &argv, &[VALUE, SWITCH])
+    }
+
+    #[test]
+    fn parses_flags_and_values() {
+        let matches = parse(&["a", "--switch", "--value=1", "b", "-v", "2"]).unwrap();
+        assert_eq!(matches.args, vec!["a", "b"]);
+        assert!(matches.flag(&SWITCH) && !matches.flag(&HELP));
+        // The last value wins.
+        assert_eq!(matches.value(&VALUE), Some("2"));
+        let matches = parse(&["--value", "--switch"]).unwrap();
+        assert_eq!(matches.value(&VALUE), Some("--switch"));
+        assert!(!matches.flag(&SWITCH));
+        assert!(parse(&["-h"]).unwrap().flag(&HELP));
+    }
+
+    #[test]
+    fn passes_everything_after_a_double_dash_through() {
+        let matches = parse(&["a", "--", "--switch", "--", "-x"]).unwrap();
+        assert_eq!(matches.args, vec!["a", "--switch", "--", "-x"]);
+        assert!(!matches.flag(&SWITCH));
+    }
+
+    #[test]
+    fn rejects_malformed_flags() {
+        let error = |argv: &[&str]| parse(argv).err().unwrap().to_string();
+        assert!(error(&["--other"]).contains("has no flag `--other`"));
+        assert!(error(&["a", "--value"]).contains("expects <value> after it"));
+        assert!(error(&["--switch=yes"]).contains("does not take a value"));
+    }
+
+    #[test]
+    fn analyze_only_returns_programs_without_errors() {
+        let app = App { argv: Vec::new() };
+        let clean = source("clean.trq", "fn main(): int {\n    return 0\n}\n");
+        let (program, diagnostics, _) = app.analyze(clean.to_str().unwrap());
+        assert!(program.is_some());
+        assert_eq!(diagnostics.error_count(), 0);
+
+        let mistyped = source(
+            "mistyped.trq",
+            "fn main(): int {\n    int x = true\n    return x\n}\n",
+        );
+        let (program, diagnostics, _) = app.analyze(mistyped.to_str().unwrap());
+        assert!(program.is_none());
+        assert_eq!(diagnostics.error_count(), 1);
+
+        let unparsed = source("unparsed.trq", "fn main(): int {\n    return (\n}\n");
+        let (program, diagnostics, _) = app.analyze(unparsed.to_str().unwrap());
+        assert!(program.is_none());
+        assert!(diagnostics.error_count() > 0);
+
+        for path in [clean, mistyped, unparsed].iter() {
+            let _ = fs::remove_file(path);
         }
-        accepted_flags
     }
 }
@@ -1,7 +1,8 @@
 pub mod rustcompiler;
 use lexer::Program;
+use semantic::Resolution;
 pub trait Compiler {
-    fn new(program: Program) -> Self;
+    fn new(program: Program, resolution: Resolution) -> Self;
     fn compile(&self) -> String;
 }
 
@@ -16,7 +17,9 @@ mod tests {
     fn it_works() {
         let tokens = tokenize("const x = 23", "").unwrap();
         let mut parser = Parser::new(tokens, "".into());
-        let token = parser.parse().unwrap();
-        let compiler = RustCompiler::new(token.unwrap().0);
+        let (mut program, _) = parser.parse();
+        let (resolution, _) = semantic::analyze(&mut program);
+        let code = RustCompiler::new(program, resolution).compile();
+        assert!(code.contains("const x: i64 = 23;"), "{}", code);
     }
 }
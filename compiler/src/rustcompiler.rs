@@ -1,141 +1,452 @@
 use super::Compiler;
 use codegen::{Function as CodegenFunc, Scope};
-use lexer::{Expression, Program, Statement, Type, Variable};
+use lexer::{
+    BinOp, Expression, ExpressionKind, Function, MatchArm, PatternKind, Program, Statement,
+    StatementKind, Type, UnOp,
+};
+use semantic::{ConstValue, Resolution, SymbolKind};
 pub struct RustCompiler {
     program: Program,
+    resolution: Resolution,
+}
+
+/// Zeta strings are never freed, so every string is a `&'static str`, which
+/// parameters, locals and struct fields alike can hold.
+fn rust_type(t: &Type) -> String {
+    match t {
+        Type::Bool => "bool".to_string(),
+        Type::Int => "i64".to_string(),
+        Type::Float => "f64".to_string(),
+        Type::Str | Type::Mlstr | Type::Char => "&'static str".to_string(),
+        Type::Void => "()".to_string(),
+        Type::Array(element, len) => format!("[{}; {}]", rust_type(element), len),
+        Type::Named(name) => semantic::mangle(name),
+    }
+}
+
+/// A Rust literal for the value of a `const`.
+fn rust_const(value: &ConstValue) -> String {
+    match value {
+        // `-9223372036854775808` would be a literal out of range for `i64`.
+        ConstValue::Int(i64::MIN) => "i64::MIN".to_string(),
+        ConstValue::Int(i) => i.to_string(),
+        // Consts can divide by zero, which Rust has no literal for.
+        ConstValue::Float(x) if x.0.is_nan() => "f64::NAN".to_string(),
+        ConstValue::Float(x) if x.0.is_infinite() => {
+            format!("{}f64::INFINITY", if x.0 < 0.0 { "-" } else { "" })
+        }
+        ConstValue::Float(x) => x.to_string(),
+        ConstValue::Bool(b) => b.to_string(),
+        ConstValue::Str(s) => format!("{:?}", s),
+        ConstValue::Array(elements) => format!(
+            "[{}]",
+            elements.iter().map(rust_const).collect::<Vec<_>>().join(", ")
+        ),
+    }
+}
+
+/// Zeta's `main` returns the exit code, which a Rust `main` cannot, so it is
+/// renamed and called from the entry point. Zeta names cannot contain `_`.
+fn rust_fn_name(name: &str) -> String {
+    match name {
+        "main" => "zeta_main".to_string(),
+        name => semantic::mangle(name),
+    }
+}
+
+/// The Rust `main`, which runs Zeta's `main` and exits with the code it
+/// returns.
+fn entry_point(main: &Function) -> CodegenFunc {
+    let mut f = CodegenFunc::new("main");
+    if main.return_type == Type::Void {
+        f.line("zeta_main();");
+    } else {
+        f.line("std::process::exit(zeta_main() as i32);");
+    }
+    f
 }
 
 impl RustCompiler {
-    fn compile_expr(&self, exp: &Expression, var_type: &str) -> String {
-        match exp {
-            Expression::FunctionCall(varname, fnargs) => {
-                let mut s = String::new();
-                for (i, _exp) in fnargs.iter().enumerate() {
-                    match &fnargs[i] {
-                        Expression::Variable(name) => {
-                            if i == 0 {
-                                s = s + "" + &name;
-                            } else {
-                                s = s + "," + &name;
-                            }
-                        }
-                        Expression::Bool(name) => {
-                            if i == 0 {
-                                s = s + "" + &name.to_string();
-                            } else {
-                                s = s + "," + &name.to_string();
-                            }
-                        }
-                        Expression::MLStr(name) => {
-                            if i == 0 {
-                                s = "\"".to_owned() + &s + "" + &name + "\"";
-                            } else {
-                                s = "\"".to_owned() + &s + "," + &name + "\"";
-                            }
-                        }
-                        Expression::Char(name) => {
-                            if i == 0 {
-                                s = "\"".to_owned() + &s + "" + &name + "\"";
-                            } else {
-                                s = "\"".to_owned() + &s + "," + &name + "\"";
-                            }
-                        }
-                        _ => unimplemented!(),
-                    };
-                }
-                format!("{}({})", varname, s)
+    /// The name of the symbol that `name`, used by `exp`, refers to. Items
+    /// of modules are known by their path, see `semantic::analyze`.
+    fn name(&self, name: &str, exp: &Expression) -> String {
+        match self.resolution.resolve(exp.span) {
+            Some(id) => self.resolution.symbol(id).name.clone(),
+            None => name.to_string(),
+        }
+    }
+
+    /// Lowers a call to one of `semantic::BUILTINS` onto the Rust standard library.
+    fn compile_builtin(&self, name: &str, args: &[Expression]) -> String {
+        match (name, args) {
+            ("argc", _) => "(std::env::args().len() as i64)".to_string(),
+            // Leaked like a concatenation, so that it is a `&'static str`.
+            ("argv", [index]) => format!(
+                "&*std::env::args().nth({} as usize).unwrap_or_default().leak()",
+                self.compile_expr(index)
+            ),
+            ("len", [s]) => format!("({}.len() as i64)", self.compile_expr(s)),
+            _ => unreachable!("the type checker checks calls to builtins"),
+        }
+    }
+
+    fn compile_expr(&self, exp: &Expression) -> String {
+        let builtin = self
+            .resolution
+            .resolve(exp.span)
+            .is_some_and(|id| self.resolution.symbol(id).kind == SymbolKind::Builtin);
+        match &exp.kind {
+            ExpressionKind::FunctionCall(name, args) if builtin => self.compile_builtin(name, args),
+            ExpressionKind::FunctionCall(varname, fnargs) => {
+                let args: Vec<String> = fnargs.iter().map(|a| self.compile_expr(a)).collect();
+                format!("{}({})", rust_fn_name(&self.name(varname, exp)), args.join(", "))
             }
-            Expression::Variable(n) => {
-                if var_type == "str" {
-                    format!("\"{}\"", n)
-                } else {
-                    n.to_string()
-                }
+            // Zeta has no references: `&x` has the type of `x` and reads its
+            // value, as it does in zeta_gcc.
+            ExpressionKind::Variable(n) | ExpressionKind::VariableRef(n) => {
+                semantic::mangle(&self.name(n, exp))
             }
-            Expression::Bool(b) => {
+            ExpressionKind::Bool(b) => {
                 format!("{}", b)
             }
-            Expression::Int(num) => {
+            ExpressionKind::Int(num) => {
                 format!("{}", num)
             }
-            Expression::MLStr(name) => "\"".to_owned() + &name + "\"",
-            Expression::Char(name) => "\"".to_owned() + &name + "\"",
-
-            other => unimplemented!(),
+            ExpressionKind::Float(num) => num.to_string(),
+            ExpressionKind::Cast(value, t) => {
+                format!("({} as {})", self.compile_expr(value), rust_type(t))
+            }
+            ExpressionKind::MLStr(name) => "\"".to_owned() + name + "\"",
+            ExpressionKind::Char(name) => "\"".to_owned() + name + "\"",
+            // `a, b` evaluates `a` for its effects, then is `b`.
+            ExpressionKind::BinOp(BinOp::Comma, lhs, rhs) => format!(
+                "{{ {}; {} }}",
+                self.compile_expr(lhs),
+                self.compile_expr(rhs)
+            ),
+            // Rust cannot add two `&str`s. The joined string is leaked so that
+            // it is a `&'static str` like every other Zeta string.
+            ExpressionKind::BinOp(BinOp::Addition, lhs, rhs)
+                if self.resolution.is_concatenation(exp.span) =>
+            {
+                format!(
+                    "&*format!(\"{{}}{{}}\", {}, {}).leak()",
+                    self.compile_expr(lhs),
+                    self.compile_expr(rhs)
+                )
+            }
+            ExpressionKind::BinOp(op, lhs, rhs) => format!(
+                "({} {} {})",
+                self.compile_expr(lhs),
+                op,
+                self.compile_expr(rhs)
+            ),
+            ExpressionKind::UnOp(op, operand) => {
+                let op = match op {
+                    UnOp::Negation => "-",
+                    UnOp::BitComp | UnOp::LogicalNeg => "!",
+                };
+                format!("({}{})", op, self.compile_expr(operand))
+            }
+            ExpressionKind::Assign(name, value) => format!(
+                "{} = {}",
+                semantic::mangle(&self.name(name, exp)),
+                self.compile_expr(value)
+            ),
+            ExpressionKind::Array(elements) => {
+                let elements: Vec<String> =
+                    elements.iter().map(|e| self.compile_expr(e)).collect();
+                format!("[{}]", elements.join(", "))
+            }
+            // Rust checks the bounds, and a negative index becomes a huge `usize`.
+            ExpressionKind::Index(array, index) => format!(
+                "{}[({}) as usize]",
+                self.compile_expr(array),
+                self.compile_expr(index)
+            ),
+            ExpressionKind::AssignIndex(array, index, value) => format!(
+                "{}[({}) as usize] = {}",
+                self.compile_expr(array),
+                self.compile_expr(index),
+                self.compile_expr(value)
+            ),
+            ExpressionKind::StructLiteral(name, fields) => {
+                let fields: Vec<String> = fields
+                    .iter()
+                    .map(|(field, value)| format!("{}: {}", field, self.compile_expr(value)))
+                    .collect();
+                format!("{} {{ {} }}", semantic::mangle(name), fields.join(", "))
+            }
+            ExpressionKind::Field(value, name) => format!("{}.{}", self.compile_expr(value), name),
+            ExpressionKind::AssignField(target, name, value) => format!(
+                "{}.{} = {}",
+                self.compile_expr(target),
+                name,
+                self.compile_expr(value)
+            ),
+            ExpressionKind::Variant(name, variant, values) if values.is_empty() => {
+                format!("{}::{}", semantic::mangle(name), variant)
+            }
+            ExpressionKind::Variant(name, variant, values) => {
+                let values: Vec<String> = values.iter().map(|v| self.compile_expr(v)).collect();
+                format!("{}::{}({})", semantic::mangle(name), variant, values.join(", "))
+            }
+            ExpressionKind::Match(value, arms) => self.compile_match(value, arms),
+            // `x++` evaluates to `x` as it was before the assignment.
+            ExpressionKind::AssignPostfix(name, value) => format!(
+                "{{ let postfix = {0}; {0} = {1}; postfix }}",
+                semantic::mangle(&self.name(name, exp)),
+                self.compile_expr(value)
+            ),
+            ExpressionKind::Ternary(condition, then, otherwise) => format!(
+                "(if {} {{ {} }} else {{ {} }})",
+                self.compile_expr(condition),
+                self.compile_expr(then),
+                self.compile_expr(otherwise)
+            ),
         }
     }
 
+    fn compile_match(&self, value: &Expression, arms: &[MatchArm]) -> String {
+        let value = self.compile_expr(value);
+        let arms: Vec<String> = arms
+            .iter()
+            .map(|arm| {
+                let pattern = match &arm.pattern.kind {
+                    PatternKind::Wildcard => "_".to_string(),
+                    PatternKind::Int(i) => i.to_string(),
+                    PatternKind::Bool(b) => b.to_string(),
+                    PatternKind::Str(s) => format!("{:?}", s),
+                    PatternKind::Variant(name, variant, bindings) if bindings.is_empty() => {
+                        format!("{}::{}", semantic::mangle(name), variant)
+                    }
+                    PatternKind::Variant(name, variant, bindings) => {
+                        let bindings: Vec<&str> = bindings
+                            .iter()
+                            .map(|b| b.as_ref().map_or("_", |b| b.name.as_str()))
+                            .collect();
+                        format!(
+                            "{}::{}({})",
+                            semantic::mangle(name),
+                            variant,
+                            bindings.join(", ")
+                        )
+                    }
+                };
+                format!("{} => {},", pattern, self.compile_expr(&arm.value))
+            })
+            .collect();
+        format!("match {} {{\n{}\n}}", value, arms.join("\n"))
+    }
+
     fn compile_statement(&self, statement: &Statement) -> String {
-        match statement {
-            Statement::Declare(var, Some(exp)) => {
-                // panic!("{:#?}", exp);
-                format!("let {} = {};", var.name, self.compile_expr(exp, &var.t))
+        match &statement.kind {
+            StatementKind::Declare(var, Some(exp)) => {
+                let t = self
+                    .resolution
+                    .resolve(var.span)
+                    .and_then(|id| self.resolution.symbol(id).t.as_ref());
+                match t {
+                    Some(t) => format!(
+                        "let mut {}: {} = {};",
+                        var.name,
+                        rust_type(t),
+                        self.compile_expr(exp)
+                    ),
+                    None => format!("let mut {} = {};", var.name, self.compile_expr(exp)),
+                }
+            }
+            StatementKind::Exp(exp) => format!("{};", self.compile_expr(exp)),
+            StatementKind::Compound(statements) => self.compile_block(statements),
+            // rustc checks that every path assigns the variable before reading it.
+            StatementKind::Declare(var, None) => {
+                let t = self
+                    .resolution
+                    .resolve(var.span)
+                    .and_then(|id| self.resolution.symbol(id).t.as_ref());
+                match t {
+                    Some(t) => format!("let mut {}: {};", var.name, rust_type(t)),
+                    None => format!("let mut {};", var.name),
+                }
+            }
+            StatementKind::Return(exp) => format!("return {};", self.compile_expr(exp)),
+            StatementKind::If(condition, then, otherwise) => {
+                let mut s = format!(
+                    "if {} {{\n{}\n}}",
+                    self.compile_expr(condition),
+                    self.compile_statement(then)
+                );
+                if let Some(otherwise) = otherwise {
+                    s += &format!(" else {{\n{}\n}}", self.compile_statement(otherwise));
+                }
+                s
+            }
+            StatementKind::While(..) | StatementKind::For(..) | StatementKind::ForRange(..) => {
+                self.compile_loop(statement, None)
+            }
+            StatementKind::Labeled(label, inner) => self.compile_loop(inner, Some(label)),
+            StatementKind::Break(label) => match label {
+                Some(label) => format!("break '{};", label),
+                None => "break;".to_string(),
+            },
+            StatementKind::Continue(label) => match label {
+                Some(label) => format!("continue '{};", label),
+                None => "continue;".to_string(),
+            },
+        }
+    }
+
+    /// Lowers a loop, giving it the Rust label `'label` if it has one.
+    fn compile_loop(&self, statement: &Statement, label: Option<&String>) -> String {
+        let label = label.map(|l| format!("'{}: ", l)).unwrap_or_default();
+        match &statement.kind {
+            StatementKind::While(condition, body) => format!(
+                "{}while {} {{\n{}\n}}",
+                label,
+                self.compile_expr(condition),
+                self.compile_statement(body)
+            ),
+            // The step runs at the start of every iteration but the first, so
+            // that `continue` still runs it. Zeta names cannot contain `_`, so
+            // `zeta_first` cannot clash with a user's variable.
+            StatementKind::For(init, condition, step, body) => {
+                let init = init
+                    .as_ref()
+                    .map(|init| self.compile_statement(init))
+                    .unwrap_or_default();
+                let condition = condition
+                    .as_ref()
+                    .map_or_else(|| "true".to_string(), |c| self.compile_expr(c));
+                let step = step
+                    .as_ref()
+                    .map(|step| format!("{};", self.compile_expr(step)))
+                    .unwrap_or_default();
+                format!(
+                    "{{\n{}\nlet mut zeta_first = true;\n{}while {{\nif !zeta_first {{\n{}\n}}\nzeta_first = false;\n{}\n}} {{\n{}\n}}\n}}",
+                    init,
+                    label,
+                    step,
+                    condition,
+                    self.compile_statement(body)
+                )
             }
-            _ => unimplemented!(),
+            StatementKind::ForRange(var, from, to, body) => format!(
+                "{}for {} in ({})..({}) {{\n{}\n}}",
+                label,
+                var.name,
+                self.compile_expr(from),
+                self.compile_expr(to),
+                self.compile_statement(body)
+            ),
+            // The parser only allows loops to be labelled.
+            _ => self.compile_statement(statement),
         }
     }
+
+    fn compile_block(&self, statements: &[Statement]) -> String {
+        let statements: Vec<String> = statements
+            .iter()
+            .map(|s| self.compile_statement(s))
+            .collect();
+        format!("{{\n{}\n}}", statements.join("\n"))
+    }
 }
 
 impl Compiler for RustCompiler {
-    fn new(program: Program) -> Self {
-        Self { program }
+    fn new(program: Program, resolution: Resolution) -> Self {
+        Self {
+            program,
+            resolution,
+        }
     }
 
     fn compile(&self) -> String {
         let Program {
             globals,
-            imports,
+            imports: _,
             func,
+            structs,
+            enums,
         } = &self.program;
 
         let mut scope = Scope::new();
 
-        for statement in globals.iter() {
-            if let Statement::Declare(Variable { name, .. }, Some(expr)) = statement {
-                scope.raw(
-                    format!(
-                        "const {}: &str = {:#?};", /* TODO don't hardcode &str */
-                        name,
-                        match expr {
-                            Expression::Variable(value) => {
-                                value
-                            }
-                            _ => unimplemented!(),
-                        }
-                    )
-                    .as_ref(),
-                );
+        // Zeta structs are values, so they are copied like they are in zeta_gcc.
+        for declaration in structs.iter() {
+            let s = scope.new_struct(&semantic::mangle(&declaration.name));
+            if declaration.is_pub {
+                s.vis("pub");
+            }
+            for derive in ["Clone", "Copy", "Debug", "PartialEq"].iter() {
+                s.derive(derive);
+            }
+            for field in declaration.fields.iter() {
+                s.field(&field.name, field.t.as_ref().map_or("()".to_string(), rust_type));
             }
         }
-        for function in func.iter() {
-            let mut t = "";
-            let mut f = CodegenFunc::new(&function.name);
-            if function.return_type == Type::Bool {
-                t = "bool";
-            } else if function.return_type == Type::Char {
-                t = "str";
-            } else if function.return_type == Type::Int {
-                t = "int";
-            } else if function.return_type == Type::Mlstr || function.return_type == Type::Str {
-                t = "str";
-            }
-            if !t.is_empty() {
-                f.ret(t);
-            }
-            for arg in 0..function.arguments.len() {
-                let a = &function.arguments[arg];
-                if a.t == "str" {
-                    f.arg(&a.name, "&".to_owned() + &function.arguments[arg].t);
-                } else {
-                    f.arg(&a.name, &function.arguments[arg].t);
+        for declaration in enums.iter() {
+            let e = scope.new_enum(&semantic::mangle(&declaration.name));
+            if declaration.is_pub {
+                e.vis("pub");
+            }
+            for derive in ["Clone", "Copy", "Debug", "PartialEq"].iter() {
+                e.derive(derive);
+            }
+            for variant in declaration.variants.iter() {
+                let v = e.new_variant(&variant.name);
+                for t in variant.fields.iter() {
+                    v.tuple(&rust_type(t));
                 }
             }
+        }
+
+        for global in globals.iter() {
+            let id = self.resolution.resolve(global.var.span);
+            let value = id.and_then(|id| self.resolution.initial_value(id));
+            let t = id.and_then(|id| self.resolution.symbol(id).t.as_ref());
+            // The semantic checks have evaluated every global.
+            if let (Some(value), Some(t)) = (value, t) {
+                scope.raw(&format!(
+                    "{}{} {}: {} = {};",
+                    if global.is_pub { "pub " } else { "" },
+                    if global.is_const { "const" } else { "static mut" },
+                    semantic::mangle(&global.var.name),
+                    rust_type(t),
+                    rust_const(value)
+                ));
+            }
+        }
+        let mutable_globals = globals.iter().any(|g| !g.is_const);
+        for function in func.iter() {
+            let mut f = CodegenFunc::new(&rust_fn_name(&function.name));
+            if function.is_pub {
+                f.vis("pub");
+            }
+            if function.return_type != Type::Void {
+                f.ret(rust_type(&function.return_type));
+            }
+            for a in function.arguments.iter() {
+                // Parameters can be assigned to like any other variable.
+                f.arg(
+                    &format!("mut {}", a.name),
+                    a.t.as_ref().map_or("_".to_string(), rust_type),
+                );
+            }
+            // Top-level `let`s are `static mut`, which Rust only lets unsafe code touch.
+            if mutable_globals {
+                f.line("unsafe {");
+            }
             for i in function.statements.iter() {
                 f.line(self.compile_statement(i));
             }
-            f.set_async(function.is_async);
+            if mutable_globals {
+                f.line("}");
+            }
             scope.push_fn(f);
         }
+        if let Some(main) = func.iter().find(|f| f.name == "main") {
+            scope.push_fn(entry_point(main));
+        }
         scope.to_string()
     }
 }
@@ -143,12 +454,205 @@ impl Compiler for RustCompiler {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use lexer::{tokenize, Parser};
+    use lexer::{tokenize, tokenize_file, Parser};
+
+    /// Builds `code` with rustc and runs it with `args`, returning its exit code.
+    fn run_rust(name: &str, code: &str, args: &[&str]) -> i32 {
+        let dir = std::env::temp_dir().join(format!("zeta-rust-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let source = dir.join("main.rs");
+        let binary = dir.join("main");
+        std::fs::write(&source, code).unwrap();
+        let output = std::process::Command::new("rustc")
+            .args(["--edition", "2018", "-o"])
+            .arg(&binary)
+            .arg(&source)
+            .output()
+            .unwrap();
+        let errors = String::from_utf8_lossy(&output.stderr);
+        assert!(output.status.success(), "{}\n{}", code, errors);
+        let status = std::process::Command::new(&binary).args(args).status().unwrap();
+        status.code().unwrap()
+    }
 
     #[test]
     fn it_works() {
         let tokens = tokenize("const x = 23", "").unwrap();
         let mut parser = Parser::new(tokens, "".into());
-        let compiler = RustCompiler::new(parser.parse().unwrap().unwrap().0);
+        let mut program = parser.parse().0;
+        let (resolution, _) = semantic::analyze(&mut program);
+        let code = RustCompiler::new(program, resolution).compile();
+        assert!(code.contains("const x: i64 = 23;"), "{}", code);
+    }
+
+    #[test]
+    fn lowers_consts_and_pub_items() {
+        let src = "pub const sizes: [int; 2] = [limit, limit * 2]\nconst limit = 1 << 4\nconst greeting = \"hi \" + \"there\"\npub fn f(): int {\n    return sizes[1]\n}";
+        let mut program = Parser::new(tokenize(src, "").unwrap(), "".into()).parse().0;
+        let (resolution, errors) = semantic::analyze(&mut program);
+        assert!(errors.is_empty(), "{:?}", errors);
+        let code = RustCompiler::new(program, resolution).compile();
+        assert!(code.contains("pub const sizes: [i64; 2] = [16, 32];"), "{}", code);
+        assert!(code.contains("const limit: i64 = 16;"), "{}", code);
+        assert!(code.contains("const greeting: &'static str = \"hi there\";"), "{}", code);
+        assert!(code.contains("pub fn f() -> i64"), "{}", code);
+    }
+
+    #[test]
+    fn concatenates_strings_with_format() {
+        let src = "fn greet(n: str): str {\n    let s = \"hi \" + n\n    return s + \"!\"\n}\nfn main(): int {\n    return len(greet(\"zeta\"))\n}";
+        let mut program = Parser::new(tokenize(src, "").unwrap(), "".into()).parse().0;
+        let (resolution, errors) = semantic::analyze(&mut program);
+        assert!(errors.is_empty(), "{:?}", errors);
+        let code = RustCompiler::new(program, resolution).compile();
+        assert!(code.contains("let mut s: &'static str = &*format!(\"{}{}\", \"hi \", n).leak();"), "{}", code);
+        assert!(code.contains("return &*format!(\"{}{}\", s, \"!\").leak();"), "{}", code);
+    }
+
+    #[test]
+    fn lowers_mutable_globals_to_statics() {
+        let src = "let count = 0\nfn bump(): int {\n    count += 1\n    return count\n}";
+        let mut program = Parser::new(tokenize(src, "").unwrap(), "".into()).parse().0;
+        let (resolution, errors) = semantic::analyze(&mut program);
+        assert!(errors.is_empty(), "{:?}", errors);
+        let code = RustCompiler::new(program, resolution).compile();
+        assert!(code.contains("static mut count: i64 = 0;"), "{}", code);
+        assert!(code.contains("fn bump() -> i64 {\n    unsafe {\n"), "{}", code);
+    }
+
+    #[test]
+    fn lowers_floats() {
+        let src = "const scale = 1.0 / 0.0\nfn f(n: int): float {\n    float x = n as float * 2.5e3\n    return -x\n}";
+        let mut program = Parser::new(tokenize(src, "").unwrap(), "".into()).parse().0;
+        let (resolution, errors) = semantic::analyze(&mut program);
+        assert!(errors.is_empty(), "{:?}", errors);
+        let code = RustCompiler::new(program, resolution).compile();
+        assert!(code.contains("const scale: f64 = f64::INFINITY;"), "{}", code);
+        assert!(code.contains("fn f(mut n: i64) -> f64"), "{}", code);
+        assert!(code.contains("let mut x: f64 = ((n as f64) * 2500.0);"), "{}", code);
+    }
+
+    #[test]
+    fn lowers_for_loops() {
+        let src = "fn f(n: int) {\n    int total = 0\n    for (int i = 0; i < n; i++) { total += i }\n    for i in 0..n { total += i }\n}";
+        let mut program = Parser::new(tokenize(src, "").unwrap(), "".into()).parse().0;
+        let (resolution, _) = semantic::analyze(&mut program);
+        let code = RustCompiler::new(program, resolution).compile();
+        assert!(code.contains("if !zeta_first {"), "{}", code);
+        assert!(code.contains("(i < n)"), "{}", code);
+        assert!(code.contains("for i in (0)..(n) {"), "{}", code);
+    }
+
+    #[test]
+    fn lowers_arrays() {
+        let src = "fn sum(a: [int; 3]): int {\n    return a[0] + a[2]\n}\nfn f(): int {\n    let grid = [[1, 2], [3, 4]]\n    grid[1][0] = sum([1, 2, 3])\n    return grid[1][0]\n}";
+        let mut program = Parser::new(tokenize(src, "").unwrap(), "".into()).parse().0;
+        let (resolution, _) = semantic::analyze(&mut program);
+        let code = RustCompiler::new(program, resolution).compile();
+        assert!(code.contains("fn sum(mut a: [i64; 3]) -> i64"), "{}", code);
+        assert!(code.contains("let mut grid: [[i64; 2]; 2] = [[1, 2], [3, 4]];"), "{}", code);
+        assert!(code.contains("grid[(1) as usize][(0) as usize] = sum([1, 2, 3]);"), "{}", code);
+    }
+
+    #[test]
+    fn lowers_structs() {
+        let src = "struct Point { x: int, y: int }\nstruct Named { name: str, at: Point }\nfn f(p: Point): Named {\n    let n = Named { name: \"origin\", at: p }\n    n.at.x = p.y\n    return n\n}";
+        let mut program = Parser::new(tokenize(src, "").unwrap(), "".into()).parse().0;
+        let (resolution, _) = semantic::analyze(&mut program);
+        let code = RustCompiler::new(program, resolution).compile();
+        assert!(code.contains("#[derive(Clone, Copy, Debug, PartialEq)]\nstruct Point {"), "{}", code);
+        assert!(code.contains("name: &'static str,"), "{}", code);
+        assert!(code.contains("fn f(mut p: Point) -> Named"), "{}", code);
+        assert!(code.contains("let mut n: Named = Named { name: \"origin\", at: p };"), "{}", code);
+        assert!(code.contains("n.at.x = p.y;"), "{}", code);
+    }
+
+    #[test]
+    fn lowers_enums_and_match() {
+        let src = "enum Command {\n    Help,\n    Add(int, int)\n}\nfn run(c: Command, name: str): int {\n    let d = Command::Add(1, 2)\n    return match c {\n        Command::Add(a, _) => a\n        Command::Help => match name { \"x\" => 1, _ => 0 }\n    }\n}";
+        let mut program = Parser::new(tokenize(src, "").unwrap(), "".into()).parse().0;
+        let (resolution, _) = semantic::analyze(&mut program);
+        let code = RustCompiler::new(program, resolution).compile();
+        assert!(code.contains("enum Command {\n    Help,\n    Add(i64, i64),\n}"), "{}", code);
+        assert!(code.contains("let mut d: Command = Command::Add(1, 2);"), "{}", code);
+        assert!(code.contains("Command::Add(a, _) => a,"), "{}", code);
+        assert!(code.contains("match name {"), "{}", code);
+    }
+
+    #[test]
+    fn lowers_labels_break_and_continue() {
+        let src = "fn f() {\n    outer: while (true) {\n        for i in 0..3 {\n            if (i == 1) { continue outer }\n            break\n        }\n    }\n}";
+        let mut program = Parser::new(tokenize(src, "").unwrap(), "".into()).parse().0;
+        let (resolution, _) = semantic::analyze(&mut program);
+        let code = RustCompiler::new(program, resolution).compile();
+        assert!(code.contains("'outer: while true {"), "{}", code);
+        assert!(code.contains("continue 'outer;"), "{}", code);
+        assert!(code.contains("break;"), "{}", code);
+    }
+
+    #[test]
+    fn mangles_the_items_of_modules() {
+        let main = "use util\nstruct Point { x: int }\nfn helper(): int {\n    return 1\n}\nfn main(): int {\n    let p = Point { x: helper() }\n    return p.x + origin().y + limit\n}";
+        let util = "pub const limit = 2\npub struct Point { y: int }\npub fn origin(): Point {\n    return Point { y: helper() }\n}\nfn helper(): int {\n    return limit\n}";
+        // Linked the way `lexer::load_program` would.
+        let mut program = Program::default();
+        for (file_id, src) in [main, util].iter().enumerate() {
+            let tokens = tokenize_file(src, "", file_id).unwrap();
+            let parsed = Parser::new(tokens, "".into()).parse().0;
+            program.imports.extend(parsed.imports);
+            program.func.extend(parsed.func);
+            program.globals.extend(parsed.globals);
+            program.structs.extend(parsed.structs);
+        }
+        program.imports[0].module = Some(1);
+        let (resolution, errors) = semantic::analyze(&mut program);
+        assert_eq!(errors, vec![]);
+        let code = RustCompiler::new(program, resolution).compile();
+        for expected in [
+            "struct Point",
+            "struct util_Point",
+            "const util_limit: i64 = 2;",
+            "fn helper() -> i64",
+            "fn util_helper() -> i64",
+            "fn util_origin() -> util_Point",
+            "util_Point { y: util_helper() }",
+            "((p.x + util_origin().y) + util_limit)",
+        ]
+        .iter()
+        {
+            assert!(code.contains(expected), "{} in {}", expected, code);
+        }
+    }
+
+    #[test]
+    fn reads_argv_as_str() {
+        let src = "fn size(s: str): int {\n    return len(s)\n}\nfn main(): int {\n    str name = argv(1)\n    return size(name) + argc()\n}";
+        let mut program = Parser::new(tokenize(src, "").unwrap(), "".into()).parse().0;
+        let (resolution, errors) = semantic::analyze(&mut program);
+        assert_eq!(errors, vec![]);
+        let code = RustCompiler::new(program, resolution).compile();
+        assert_eq!(run_rust("argv", &code, &["four"]), 6);
+    }
+
+    #[test]
+    fn lowers_ternaries_references_and_commas() {
+        let src = "fn sign(x: int): int {\n    return x > 0 ? 1 : -1\n}\nfn main(): int {\n    int total = sign(3)\n    int x = 4\n    int y = (sign(-1), &x)\n    return total + y\n}";
+        let (mut program, errors) = Parser::new(tokenize(src, "").unwrap(), "".into()).parse();
+        assert!(errors.is_empty(), "{:?}", errors);
+        let (resolution, errors) = semantic::analyze(&mut program);
+        assert_eq!(errors, vec![]);
+        let code = RustCompiler::new(program, resolution).compile();
+        assert_eq!(run_rust("ternary", &code, &[]), 5);
+    }
+
+    #[test]
+    fn stores_str_parameters_in_structs() {
+        let src = "struct Named { name: str }\nfn named(n: str): Named {\n    let s = Named { name: n }\n    return s\n}\nfn main(): int {\n    let s = named(\"zeta\")\n    return len(s.name)\n}";
+        let mut program = Parser::new(tokenize(src, "").unwrap(), "".into()).parse().0;
+        let (resolution, errors) = semantic::analyze(&mut program);
+        assert_eq!(errors, vec![]);
+        let code = RustCompiler::new(program, resolution).compile();
+        assert!(code.contains("fn named(mut n: &'static str) -> Named"), "{}", code);
+        assert_eq!(run_rust("struct_str", &code, &[]), 4);
     }
 }
@@ -356,6 +356,10 @@ extern {
                                       kind: gcc_jit_global_kind,
                                       ty: *mut gcc_jit_type,
                                       name: *const c_char) -> *mut gcc_jit_lvalue;
+    // Added in libgccjit 10 (LIBGCCJIT_ABI_14).
+    pub fn gcc_jit_global_set_initializer(global: *mut gcc_jit_lvalue,
+                                          blob: *const c_void,
+                                          num_bytes: usize) -> *mut gcc_jit_lvalue;
     pub fn gcc_jit_lvalue_as_object(lvalue: *mut gcc_jit_lvalue) -> *mut gcc_jit_object;
     pub fn gcc_jit_lvalue_as_rvalue(lvalue: *mut gcc_jit_lvalue) -> *mut gcc_jit_rvalue;
     pub fn gcc_jit_rvalue_as_object(rvalue: *mut gcc_jit_rvalue) -> *mut gcc_jit_object;
@@ -52,6 +52,21 @@ pub enum OutputKind {
     Executable,
 }
 
+/// GlobalKind informs gccjit what sort of global a new global will be,
+/// much like FunctionType does for functions.
+#[repr(C)]
+pub enum GlobalKind {
+    /// Defines a global that is "exported" by the JIT and can be accessed
+    /// from Rust through CompileResult::get_global.
+    Exported,
+    /// Defines a global that is internal to the JIT and can only be used
+    /// by jitted code, like a static global in C.
+    Internal,
+    /// Refers to a global defined outside of this context, like an extern
+    /// global declared in a C header.
+    Imported,
+}
+
 /// Represents a successful compilation of a context. This type
 /// provides the means to access compiled functions and globals.
 /// JIT compiled functions are exposted to Rust as an extern "C" function
@@ -430,6 +445,39 @@ impl<'ctx> Context<'ctx> {
         }
     }
 
+    /// Creates a new global variable with the given kind, type and name. The
+    /// global is zero initialized, and is an LValue that any function in the
+    /// context can read or assign to.
+    pub fn new_global<'a, S: AsRef<str>>(
+        &'a self,
+        loc: Option<Location<'a>>,
+        kind: GlobalKind,
+        ty: types::Type<'a>,
+        name: S,
+    ) -> LValue<'a> {
+        let name_ref = name.as_ref();
+        let loc_ptr = match loc {
+            Some(loc) => unsafe { location::get_ptr(&loc) },
+            None => ptr::null_mut(),
+        };
+        let kind = match kind {
+            GlobalKind::Exported => gccjit_sys::gcc_jit_global_kind::GCC_JIT_GLOBAL_EXPORTED,
+            GlobalKind::Internal => gccjit_sys::gcc_jit_global_kind::GCC_JIT_GLOBAL_INTERNAL,
+            GlobalKind::Imported => gccjit_sys::gcc_jit_global_kind::GCC_JIT_GLOBAL_IMPORTED,
+        };
+        unsafe {
+            let cstr = CString::new(name_ref).unwrap();
+            let ptr = gccjit_sys::gcc_jit_context_new_global(
+                self.ptr,
+                loc_ptr,
+                kind,
+                types::get_ptr(&ty),
+                cstr.as_ptr(),
+            );
+            lvalue::from_ptr(ptr)
+        }
+    }
+
     /// Creates a new binary operation between two RValues and produces a new RValue.
     pub fn new_binary_op<'a, L: ToRValue<'a>, R: ToRValue<'a>>(
         &'a self,
@@ -705,6 +753,21 @@ impl<'ctx> Context<'ctx> {
         }
     }
 
+    /// Dumps a C-like representation of everything in this context to the
+    /// path. With `update_locations`, locations are also changed to point
+    /// into the dump, so that debuggers step through it instead of the source.
+    pub fn dump_to_file<S: AsRef<str>>(&self, path: S, update_locations: bool) {
+        unsafe {
+            let path_ref = path.as_ref();
+            let cstr = CString::new(path_ref).unwrap();
+            gccjit_sys::gcc_jit_context_dump_to_file(
+                self.ptr,
+                cstr.as_ptr(),
+                update_locations as i32,
+            );
+        }
+    }
+
     /// Dumps a small C file to the path that can be used to reproduce a series
     /// of API calls. You should only ever need to call this if you are debugging
     /// an issue in gccjit itself or this library.
@@ -831,6 +894,49 @@ mod tests {
         }
     }
 
+    #[test]
+    fn exported_global() {
+        let context = Context::default();
+        let int_ty = context.new_type::<i32>();
+        let counter = context.new_global(None, GlobalKind::Exported, int_ty, "counter");
+        let fun = context.new_function(
+            None,
+            FunctionType::Exported,
+            int_ty,
+            &[],
+            "bump",
+            false,
+        );
+        let block = fun.new_block("main_block");
+        block.add_assignment_op(None, counter, BinaryOp::Plus, context.new_rvalue_one(int_ty));
+        block.end_with_return(None, counter.to_rvalue());
+
+        let result = context.compile();
+        unsafe {
+            let global_ptr = result.get_global("counter");
+            assert!(!global_ptr.is_null());
+            let func: extern "C" fn() -> i32 = mem::transmute(result.get_function("bump"));
+            assert_eq!(func(), 1);
+            assert_eq!(func(), 2);
+            assert_eq!(*(global_ptr as *mut i32), 2);
+        }
+    }
+
+    #[test]
+    fn dumps_to_file() {
+        let context = Context::default();
+        let int_ty = context.new_type::<i32>();
+        let fun = context.new_function(None, FunctionType::Exported, int_ty, &[], "answer", false);
+        fun.new_block("main_block")
+            .end_with_return(None, context.new_rvalue_from_int(int_ty, 42));
+
+        let path = std::env::temp_dir().join("gccjit_dumps_to_file.c");
+        context.dump_to_file(path.to_str().unwrap(), false);
+        let dump = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(dump.contains("answer"), "{}", dump);
+    }
+
     /* Uncomment these tests periodically to remind yourself of
      * 1) why rust is awesome and 2) make sure that you've set up
      * lifetimes correctly so that these invariant violations are
@@ -34,6 +34,7 @@ pub use context::Context;
 pub use context::OptimizationLevel;
 pub use context::CompileResult;
 pub use context::OutputKind;
+pub use context::GlobalKind;
 pub use location::Location;
 pub use object::Object;
 pub use object::ToObject;
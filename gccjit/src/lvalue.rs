@@ -82,6 +82,19 @@ impl<'ctx> LValue<'ctx> {
             rvalue::from_ptr(ptr)
         }
     }
+
+    /// Sets the initial value of a global, created with Context::new_global,
+    /// to a copy of `blob`. The blob is the value's bytes as laid out in
+    /// memory, and must be exactly as large as the global's type.
+    pub fn global_set_initializer(&self, blob: &[u8]) {
+        unsafe {
+            gccjit_sys::gcc_jit_global_set_initializer(
+                self.ptr,
+                blob.as_ptr() as *const _,
+                blob.len(),
+            );
+        }
+    }
 }
 
 pub unsafe fn from_ptr<'ctx>(ptr: *mut gccjit_sys::gcc_jit_lvalue) -> LValue<'ctx> {
@@ -35,6 +35,14 @@ impl<'ctx> fmt::Debug for Type<'ctx> {
     }
 }
 
+/// Two types are equal if they are the same gccjit type object, e.g. the
+/// same struct or the same primitive type.
+impl<'ctx> PartialEq for Type<'ctx> {
+    fn eq(&self, other: &Type<'ctx>) -> bool {
+        self.ptr == other.ptr
+    }
+}
+
 impl<'ctx> Type<'ctx> {
     /// Given a type T, creates a type to *T, a pointer to T.
     pub fn make_pointer(self) -> Type<'ctx> {
@@ -0,0 +1,10 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(src) = std::str::from_utf8(data) {
+        if let Ok(tokens) = lexer::tokenize(src, "fuzz.trq") {
+            let _ = lexer::Parser::new(tokens, "fuzz.trq".into()).parse();
+        }
+    }
+});
@@ -0,0 +1,298 @@
+use crate::{SourceMap, Span};
+use colored::{Color, Colorize};
+use std::fmt::Write;
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+impl Severity {
+    fn tag(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Note => "note",
+        }
+    }
+
+    fn color(&self) -> Color {
+        match self {
+            Severity::Error => Color::Red,
+            Severity::Warning => Color::Yellow,
+            Severity::Note => Color::Cyan,
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+    pub primary: bool,
+}
+
+/// A single message for the user, pointing at one or more places in the source.
+///
+/// The first primary label decides the `-->` location printed under the header.
+/// Primary labels are underlined with `^`, secondary ones with `-`.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: Option<&'static str>,
+    pub message: String,
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+    pub help: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn new<S: Into<String>>(severity: Severity, message: S) -> Self {
+        Self {
+            severity,
+            code: None,
+            message: message.into(),
+            labels: Vec::new(),
+            notes: Vec::new(),
+            help: Vec::new(),
+        }
+    }
+
+    pub fn error<S: Into<String>>(message: S) -> Self {
+        Self::new(Severity::Error, message)
+    }
+
+    pub fn warning<S: Into<String>>(message: S) -> Self {
+        Self::new(Severity::Warning, message)
+    }
+
+    pub fn with_code(mut self, code: &'static str) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    pub fn with_label<S: Into<String>>(mut self, span: Span, message: S) -> Self {
+        self.labels.push(Label {
+            span,
+            message: message.into(),
+            primary: true,
+        });
+        self
+    }
+
+    pub fn with_secondary_label<S: Into<String>>(mut self, span: Span, message: S) -> Self {
+        self.labels.push(Label {
+            span,
+            message: message.into(),
+            primary: false,
+        });
+        self
+    }
+
+    pub fn with_note<S: Into<String>>(mut self, note: S) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn with_help<S: Into<String>>(mut self, help: S) -> Self {
+        self.help.push(help.into());
+        self
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+    /// Renders the diagnostic in the style of rustc, quoting the offending
+    /// source lines out of `sources`.
+    pub fn render(&self, sources: &SourceMap) -> String {
+        let mut out = String::new();
+        let color = self.severity.color();
+        let tag = match self.code {
+            Some(code) => format!("{}[{}]", self.severity.tag(), code),
+            None => self.severity.tag().to_string(),
+        };
+        let _ = writeln!(
+            out,
+            "{}{} {}",
+            tag.color(color).bold(),
+            ":".bold(),
+            self.message.bold()
+        );
+
+        let mut labels: Vec<&Label> = self.labels.iter().collect();
+        labels.sort_by_key(|l| (l.span.file_id, l.span.line, !l.primary, l.span.col));
+        let width = labels
+            .iter()
+            .map(|l| l.span.line.to_string().len())
+            .max()
+            .unwrap_or(0);
+        let gutter = " ".repeat(width);
+        let bar = "|".blue().bold();
+
+        let primary = self
+            .labels
+            .iter()
+            .find(|l| l.primary)
+            .or_else(|| self.labels.first());
+        let mut current_file = None;
+        for label in labels.iter() {
+            if current_file != Some(label.span.file_id) {
+                let arrow = if Some(label) == primary.as_ref() || current_file.is_none() {
+                    "-->"
+                } else {
+                    ":::"
+                };
+                let _ = writeln!(
+                    out,
+                    "{}{} {}:{}",
+                    gutter,
+                    arrow.blue().bold(),
+                    sources.name(label.span.file_id),
+                    label.span
+                );
+                let _ = writeln!(out, "{} {}", gutter, bar);
+                current_file = Some(label.span.file_id);
+            }
+            let file = match sources.get(label.span.file_id) {
+                Some(file) => file,
+                None => continue,
+            };
+            let range = file.line_range(label.span.line).unwrap_or(0..0);
+            let line = &file.src[range.clone()];
+            let _ = writeln!(
+                out,
+                "{} {} {}",
+                format!("{:>width$}", label.span.line, width = width)
+                    .blue()
+                    .bold(),
+                bar,
+                line
+            );
+
+            let prefix: String = line
+                .chars()
+                .take(label.span.col.saturating_sub(1))
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+            let end = label.span.end.min(range.end).max(label.span.start);
+            let length = file
+                .src
+                .get(label.span.start..end)
+                .map(|s| s.chars().count())
+                .unwrap_or(0)
+                .max(1);
+            let (marker, marker_color) = if label.primary {
+                ("^", color)
+            } else {
+                ("-", Color::Blue)
+            };
+            let underline = format!("{} {}", marker.repeat(length), label.message);
+            let _ = writeln!(
+                out,
+                "{} {} {}{}",
+                gutter,
+                bar,
+                prefix,
+                underline.trim_end().color(marker_color).bold()
+            );
+        }
+
+        if !self.notes.is_empty() || !self.help.is_empty() {
+            if !labels.is_empty() {
+                let _ = writeln!(out, "{} {}", gutter, bar);
+            }
+            for note in self.notes.iter() {
+                let _ = writeln!(out, "{} {} note: {}", gutter, "=".blue().bold(), note);
+            }
+            for help in self.help.iter() {
+                let _ = writeln!(out, "{} {} help: {}", gutter, "=".blue().bold(), help);
+            }
+        }
+        out
+    }
+}
+
+/// Collects every diagnostic produced during a run so they can be reported together.
+#[derive(Debug, Default, Clone)]
+pub struct Diagnostics {
+    items: Vec<Diagnostic>,
+}
+
+impl Diagnostics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, diagnostic: Diagnostic) {
+        self.items.push(diagnostic);
+    }
+
+    pub fn extend<I: IntoIterator<Item = Diagnostic>>(&mut self, diagnostics: I) {
+        self.items.extend(diagnostics);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn error_count(&self) -> usize {
+        self.items.iter().filter(|d| d.is_error()).count()
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.error_count() > 0
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Diagnostic> {
+        self.items.iter()
+    }
+
+    /// Renders every diagnostic, separated by blank lines.
+    pub fn render(&self, sources: &SourceMap) -> String {
+        self.items
+            .iter()
+            .map(|d| d.render(sources))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+impl IntoIterator for Diagnostics {
+    type Item = Diagnostic;
+    type IntoIter = std::vec::IntoIter<Diagnostic>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.items.into_iter()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn renders_snippet_with_caret() {
+        colored::control::set_override(false);
+        let mut sources = SourceMap::new();
+        let src = "fn main() {\n    let x = 1 $ 2\n}";
+        let file_id = sources.add_file("main.trq", src);
+        let start = src.find('$').unwrap();
+        let diagnostic = Diagnostic::error("invalid token `$`")
+            .with_code("Z0001")
+            .with_label(Span::new(file_id, start, start + 1, 2, 15), "not valid here")
+            .with_help("remove the `$`");
+        assert_eq!(
+            diagnostic.render(&sources),
+            "error[Z0001]: invalid token `$`\n \
+             --> main.trq:2:15\n  \
+             |\n\
+             2 |     let x = 1 $ 2\n  \
+             |               ^ not valid here\n  \
+             |\n  \
+             = help: remove the `$`\n"
+        );
+    }
+}
@@ -1,51 +1,79 @@
 use thiserror::Error;
 
-use crate::{ParserDescriptor, Token, Type};
+use crate::diagnostics::Diagnostic;
+use crate::{ParserDescriptor, Span, Token};
 
 #[derive(Error, Debug)]
 pub enum TokenizeError {
-    #[error("invalid token {c:?}, in {filename:}:{linenum:?}")]
+    #[error("invalid token {c:?}, in {filename:}:{span:}")]
     UnknownToken{
         c: char,
-        linenum: i32,
+        span: Span,
+        filename: Box<str>
+    },
+    #[error("invalid integer, in {filename:}:{span:}")]
+    InvalidInteger {
+        source: std::num::ParseIntError,
+        span: Span,
+        filename: Box<str>
+    },
+    #[error("invalid float: {reason:}, in {filename:}:{span:}")]
+    InvalidFloat {
+        reason: Box<str>,
+        span: Span,
         filename: Box<str>
     },
-    #[error("invalid integer ")]
-    InvalidInteger(#[from] std::num::ParseIntError),
 }
 
 #[derive(Error, Debug)]
 pub enum ParseError {
-    #[error("unassigned variable, in {filename:}:{linenum:?}")]
+    #[error("unassigned variable, in {filename:}:{span:}")]
     UnassignedVariable{
-        linenum: i32,
+        span: Span,
         filename: Box<str>,
     },
-    #[error("expected {expected:?} got {received:?}: in {filename:}:{linenum:?}")]
+    #[error("expected {expected:} got {received:}: in {filename:}:{span:}")]
     UnexpectedToken {
         expected: ParserDescriptor,
         received: Token,
-        linenum: i32,
+        span: Span,
         filename: Box<str>
     },
-    #[error("expected {expected:?} got {received:?} {filename:}:{linenum:?}\n\tIn function {fnname:}")]
-    UnexpectedType { 
+    #[error("expected {expected:} got {received:} {filename:}:{span:}\n\tIn function {fnname:}")]
+    UnexpectedType {
         expected: Box<str>,
-        received: Token, 
-        fnname: Box<str>, 
-        linenum: i32,
+        received: Token,
+        fnname: Box<str>,
+        span: Span,
         filename: Box<str>
     },
     #[error("{msg:}")]
     EndOfLine {
         msg: Box<str>
     },
-    #[error("expected {expected:?} to be present")]
+    #[error("expected {expected:} to be present")]
     AbsentToken { expected: ParserDescriptor },
-    #[error("expected return type to be present in function: {fnname:?}, File: {filename:}:{linenum:}")]
+    #[error("expected return type to be present in function: {fnname:?}, File: {filename:}:{span:}")]
     AbsentReturnType{
         fnname: Box<str>,
-        linenum: i32,
+        span: Span,
+        filename: Box<str>
+    },
+    #[error("expected {expected:} but reached the end of the file: in {filename:}:{span:}")]
+    UnexpectedEof {
+        expected: ParserDescriptor,
+        span: Span,
+        filename: Box<str>
+    },
+    #[error("expression or block nested too deeply: in {filename:}:{span:}")]
+    NestingTooDeep {
+        span: Span,
+        filename: Box<str>
+    },
+    #[error("{received:} cannot be used as an operator: in {filename:}:{span:}")]
+    InvalidOperator {
+        received: Token,
+        span: Span,
         filename: Box<str>
     },
     #[error("error tokenizing")]
@@ -53,3 +81,174 @@ pub enum ParseError {
     #[error("An unkown error occured \n\t NOTE: If this continues open an issue in https://github.com/zetacli/zetac/issues")]
     Unknown,
 }
+
+/// A problem with finding or loading a module named by a `use`.
+#[derive(Error, Debug)]
+pub enum ModuleError {
+    #[error("cannot find module `{module:}` at {expected:}, in {filename:}:{span:}")]
+    NotFound {
+        module: Box<str>,
+        /// The files that were looked for.
+        expected: Box<str>,
+        span: Span,
+        filename: Box<str>,
+    },
+    #[error("cannot read module `{module:}`: {source:}, in {filename:}:{span:}")]
+    Unreadable {
+        module: Box<str>,
+        source: std::io::Error,
+        span: Span,
+        filename: Box<str>,
+    },
+    #[error("import cycle {cycle:}, in {filename:}:{span:}")]
+    Cycle {
+        cycle: Box<str>,
+        span: Span,
+        filename: Box<str>,
+    },
+}
+
+impl TokenizeError {
+    /// The stable error code shown as `error[Z....]`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            TokenizeError::UnknownToken { .. } => "Z0001",
+            TokenizeError::InvalidInteger { .. } => "Z0002",
+            TokenizeError::InvalidFloat { .. } => "Z0043",
+        }
+    }
+
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        let diagnostic = match self {
+            TokenizeError::UnknownToken { c, span, .. } => {
+                Diagnostic::error(format!("unknown character {:?}", c))
+                    .with_label(*span, "not valid in Zeta source")
+            }
+            TokenizeError::InvalidInteger { source, span, .. } => {
+                Diagnostic::error("invalid integer literal")
+                    .with_label(*span, source.to_string())
+                    .with_help("hexadecimal literals are written as `0x1f`")
+            }
+            TokenizeError::InvalidFloat { reason, span, .. } => {
+                Diagnostic::error("invalid float literal")
+                    .with_label(*span, reason.to_string())
+                    .with_help("float literals are written as `1.5`, `2e10` or `6.02e-23`")
+            }
+        };
+        diagnostic.with_code(self.code())
+    }
+}
+
+impl ParseError {
+    /// The stable error code shown as `error[Z....]`.
+    pub fn code(&self) -> Option<&'static str> {
+        Some(match self {
+            ParseError::UnassignedVariable { .. } => "Z0003",
+            ParseError::UnexpectedToken { .. } => "Z0004",
+            ParseError::UnexpectedType { .. } => "Z0005",
+            ParseError::AbsentReturnType { .. } => "Z0006",
+            ParseError::EndOfLine { .. } => "Z0007",
+            ParseError::AbsentToken { .. } => "Z0008",
+            ParseError::UnexpectedEof { .. } => "Z0009",
+            ParseError::NestingTooDeep { .. } => "Z0010",
+            ParseError::InvalidOperator { .. } => "Z0011",
+            ParseError::TokenizeError(e) => e.code(),
+            ParseError::Unknown => return None,
+        })
+    }
+
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        let diagnostic = match self {
+            ParseError::UnassignedVariable { span, .. } => {
+                Diagnostic::error("unassigned variable")
+                    .with_label(*span, "expected a value here")
+                    .with_help("variables must be given a value when declared, e.g. `let x = 0`")
+            }
+            ParseError::UnexpectedToken {
+                expected,
+                received,
+                span,
+                ..
+            } => Diagnostic::error(format!("expected {}, found {}", expected, received))
+                .with_label(*span, format!("expected {}", expected)),
+            ParseError::UnexpectedType {
+                expected,
+                received,
+                fnname,
+                span,
+                ..
+            } => Diagnostic::error(format!("expected a type, found {}", received))
+                .with_label(*span, "not a type")
+                .with_note(format!("in the parameters of function `{}`", fnname))
+                .with_help(format!("expected one of {}", expected)),
+            ParseError::AbsentReturnType { fnname, span, .. } => {
+                Diagnostic::error(format!("missing return type for function `{}`", fnname))
+                    .with_label(*span, "expected a type after `:`")
+                    .with_help(format!("write the return type, e.g. `fn {}(): int`", fnname))
+            }
+            ParseError::EndOfLine { msg } => Diagnostic::error(msg.to_string()),
+            ParseError::AbsentToken { expected } => {
+                Diagnostic::error(format!("expected {} to be present", expected))
+            }
+            ParseError::UnexpectedEof { expected, span, .. } => {
+                Diagnostic::error(format!("expected {}, found the end of the file", expected))
+                    .with_label(*span, format!("expected {}", expected))
+            }
+            ParseError::NestingTooDeep { span, .. } => {
+                Diagnostic::error("expression or block nested too deeply")
+                    .with_label(*span, "nesting limit reached here")
+                    .with_help("split the expression up using variables or functions")
+            }
+            ParseError::InvalidOperator { received, span, .. } => {
+                Diagnostic::error(format!("{} cannot be used as an operator", received))
+                    .with_label(*span, "not an operator")
+            }
+            ParseError::TokenizeError(e) => return e.to_diagnostic(),
+            ParseError::Unknown => Diagnostic::error("an unknown error occurred").with_note(
+                "if this continues open an issue in https://github.com/zetacli/zetac/issues",
+            ),
+        };
+        match self.code() {
+            Some(code) => diagnostic.with_code(code),
+            None => diagnostic,
+        }
+    }
+}
+
+impl ModuleError {
+    /// The stable error code shown as `error[Z....]`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ModuleError::NotFound { .. } => "Z0031",
+            ModuleError::Unreadable { .. } => "Z0032",
+            ModuleError::Cycle { .. } => "Z0033",
+        }
+    }
+
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        let diagnostic = match self {
+            ModuleError::NotFound {
+                module,
+                expected,
+                span,
+                ..
+            } => Diagnostic::error(format!("cannot find module `{}`", module))
+                .with_label(*span, "imported here")
+                .with_help(format!("create {}", expected)),
+            ModuleError::Unreadable {
+                module,
+                source,
+                span,
+                ..
+            } => Diagnostic::error(format!("cannot read module `{}`", module))
+                .with_label(*span, source.to_string()),
+            ModuleError::Cycle { cycle, span, .. } => {
+                Diagnostic::error("modules import each other")
+                    .with_label(*span, "this import closes the cycle")
+                    .with_note(format!("the cycle is {}", cycle))
+                    .with_help("move what both modules need into a module of its own")
+            }
+        };
+        diagnostic.with_code(self.code())
+    }
+}
@@ -0,0 +1,112 @@
+use crate::{errors::TokenizeError, tokenize, Token};
+
+const INDENT: &str = "    ";
+
+/// Formats Zeta source without reshaping it: every line is re-indented by
+/// how deeply its braces, brackets and parentheses are nested, trailing
+/// whitespace is removed, runs of blank lines are collapsed into one, and the
+/// file ends with a single newline. The contents of multi-line strings and
+/// the bodies of block comments are left alone.
+pub fn format(source: &str, fname: &str) -> Result<String, TokenizeError> {
+    let tokens = tokenize(source, fname)?;
+    let mut tokens = tokens.iter().peekable();
+    let mut lines: Vec<String> = Vec::new();
+    let mut depth: usize = 0;
+    let mut line_start = 0;
+    for line in source.split('\n') {
+        let line_end = line_start + line.len();
+        // Skip any token that spans lines, like a multi-line string, as far
+        // as the line it ends on.
+        while tokens.peek().is_some_and(|t| t.span.end <= line_start) {
+            tokens.next();
+        }
+        let inside_token = tokens
+            .peek()
+            .is_some_and(|t| t.span.start < line_start && line_start < t.span.end);
+        let content = line.trim();
+        if inside_token {
+            lines.push(line.to_string());
+        } else if content.is_empty() {
+            if lines.last().is_some_and(|last| !last.is_empty()) {
+                lines.push(String::new());
+            }
+        } else {
+            let first = line_start + (line.len() - line.trim_start().len());
+            let starts_with_token = tokens.peek().is_some_and(|t| t.span.start == first);
+            if starts_with_token || content.starts_with("//") || content.starts_with("/*") {
+                // Lines that start by closing something line up with the
+                // line that opened it.
+                let closing = tokens
+                    .clone()
+                    .take_while(|t| t.span.start < line_end && is_close(&t.token))
+                    .count();
+                let indent = INDENT.repeat(depth.saturating_sub(closing));
+                lines.push(format!("{}{}", indent, content));
+            } else {
+                // Inside a block comment.
+                lines.push(line.trim_end().to_string());
+            }
+        }
+        while let Some(token) = tokens.peek() {
+            if token.span.start >= line_end {
+                break;
+            }
+            if is_open(&token.token) {
+                depth += 1;
+            } else if is_close(&token.token) {
+                depth = depth.saturating_sub(1);
+            }
+            if token.span.end > line_end {
+                break;
+            }
+            tokens.next();
+        }
+        line_start = line_end + 1;
+    }
+    while lines.last().is_some_and(|last| last.is_empty()) {
+        lines.pop();
+    }
+    let mut formatted = lines.join("\n");
+    formatted.push('\n');
+    Ok(formatted)
+}
+
+fn is_open(token: &Token) -> bool {
+    matches!(
+        token,
+        Token::OpenBrace | Token::OpenParen | Token::OpenSquareParen
+    )
+}
+
+fn is_close(token: &Token) -> bool {
+    matches!(
+        token,
+        Token::CloseBrace | Token::CloseParen | Token::CloseSquareParen
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn indents_by_nesting() {
+        let source = "fn main(): int {  \n\n\n  int x = add(\n1,\n    2)\nif x > 2 {\n\t\treturn x\n        }\n// done\n   return 0\n}\n\n";
+        assert_eq!(
+            format(source, "main.trq").unwrap(),
+            "fn main(): int {\n\n    int x = add(\n        1,\n        2)\n    if x > 2 {\n        return x\n    }\n    // done\n    return 0\n}\n"
+        );
+    }
+
+    #[test]
+    fn leaves_strings_and_block_comments_alone() {
+        let source =
+            "fn main(): int {\n/* a\n      comment */\nmlstr s = `one\n  two  \n`\nreturn 0\n}";
+        assert_eq!(
+            format(source, "main.trq").unwrap(),
+            "fn main(): int {\n    /* a\n      comment */\n    mlstr s = `one\n  two  \n`\n    return 0\n}\n"
+        );
+        let formatted = format(source, "main.trq").unwrap();
+        assert_eq!(format(&formatted, "main.trq").unwrap(), formatted);
+    }
+}
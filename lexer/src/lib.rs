@@ -1,10 +1,17 @@
+pub mod diagnostics;
 pub mod errors;
+mod format;
+mod modules;
 pub mod ops;
 mod parser;
+mod span;
 mod tokenizer;
 mod types;
 
+pub use format::format;
+pub use modules::load_program;
 pub use ops::*;
 pub use parser::Parser;
-pub use tokenizer::tokenize;
+pub use span::*;
+pub use tokenizer::{tokenize, tokenize_file};
 pub use types::*;
@@ -0,0 +1,249 @@
+use crate::diagnostics::Diagnostic;
+use crate::errors::ModuleError;
+use crate::{tokenize_file, Import, Parser, Program, SourceMap};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Loads the program whose entry point is the file at `path`, together with
+/// every module it imports directly or through other modules, as a single
+/// program. The module `a::b` is the file `a/b.trq` or `a/b/mod.trq` in the
+/// project's root directory (see `project_root`), and is loaded once
+/// however many files import it.
+///
+/// Only a missing or unreadable entry point is an error here. Syntax errors
+/// and modules that cannot be loaded are returned as diagnostics, with the
+/// affected imports left without a `module`.
+pub fn load_program(
+    path: &Path,
+    sources: &mut SourceMap,
+) -> io::Result<(Program, Vec<Diagnostic>)> {
+    let src = fs::read_to_string(path)?;
+    let name = path
+        .file_stem()
+        .map_or_else(String::new, |s| s.to_string_lossy().into_owned());
+    let mut loader = Loader {
+        root: project_root(path),
+        sources,
+        loaded: HashMap::new(),
+        stack: Vec::new(),
+        program: Program::default(),
+        diagnostics: Vec::new(),
+    };
+    loader.load(path.to_path_buf(), name, &src);
+    Ok((loader.program, loader.diagnostics))
+}
+
+struct Loader<'s> {
+    root: PathBuf,
+    sources: &'s mut SourceMap,
+    /// The file id of every file loaded so far, by canonical path.
+    loaded: HashMap<PathBuf, usize>,
+    /// The files being loaded, each imported by the one before it, with
+    /// their module names.
+    stack: Vec<(PathBuf, String)>,
+    program: Program,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Loader<'_> {
+    /// Parses `src` into the program, then loads the modules it imports.
+    fn load(&mut self, path: PathBuf, module: String, src: &str) -> usize {
+        let filename = path.display().to_string();
+        let file_id = self.sources.add_file(&filename, src);
+        let key = canonical(&path);
+        self.loaded.insert(key.clone(), file_id);
+        self.stack.push((key, module));
+
+        let tokens = match tokenize_file(src, &filename, file_id) {
+            Ok(tokens) => tokens,
+            Err(e) => {
+                self.diagnostics.push(e.to_diagnostic());
+                self.stack.pop();
+                return file_id;
+            }
+        };
+        let (program, errors) = Parser::new(tokens, filename.as_str().into()).parse();
+        self.diagnostics
+            .extend(errors.iter().map(|e| e.to_diagnostic()));
+        let Program {
+            imports,
+            func,
+            globals,
+            structs,
+            enums,
+        } = program;
+        self.program.func.extend(func);
+        self.program.globals.extend(globals);
+        self.program.structs.extend(structs);
+        self.program.enums.extend(enums);
+        for import in imports {
+            let index = self.program.imports.len();
+            self.program.imports.push(import.clone());
+            self.program.imports[index].module = self.import(&import, &filename);
+        }
+
+        self.stack.pop();
+        file_id
+    }
+
+    /// Finds the module named by `import` and loads it if this is the first
+    /// import of it, returning its file id.
+    fn import(&mut self, import: &Import, filename: &str) -> Option<usize> {
+        let module = import.module_path().join("::");
+        let relative: PathBuf = import.module_path().iter().collect();
+        let candidates = [
+            self.root.join(&relative).with_extension("trq"),
+            self.root.join(&relative).join("mod.trq"),
+        ];
+        let path = match candidates.iter().find(|c| c.is_file()) {
+            Some(path) => path,
+            None => {
+                let expected = format!(
+                    "`{}` or `{}`",
+                    candidates[0].display(),
+                    candidates[1].display()
+                );
+                return self.error(ModuleError::NotFound {
+                    module: module.into(),
+                    expected: expected.into(),
+                    span: import.span,
+                    filename: filename.into(),
+                });
+            }
+        };
+        let key = canonical(path);
+        if let Some(start) = self.stack.iter().position(|(file, _)| *file == key) {
+            let mut cycle: Vec<String> = self.stack[start..]
+                .iter()
+                .map(|(_, name)| format!("`{}`", name))
+                .collect();
+            cycle.push(format!("`{}`", self.stack[start].1));
+            return self.error(ModuleError::Cycle {
+                cycle: cycle.join(" -> ").into(),
+                span: import.span,
+                filename: filename.into(),
+            });
+        }
+        if let Some(&file_id) = self.loaded.get(&key) {
+            return Some(file_id);
+        }
+        match fs::read_to_string(path) {
+            Ok(src) => Some(self.load(path.clone(), module, &src)),
+            Err(source) => self.error(ModuleError::Unreadable {
+                module: module.into(),
+                source,
+                span: import.span,
+                filename: filename.into(),
+            }),
+        }
+    }
+
+    fn error(&mut self, error: ModuleError) -> Option<usize> {
+        self.diagnostics.push(error.to_diagnostic());
+        None
+    }
+}
+
+/// The directory that the modules of the project holding `path` are found
+/// in: the closest one to it that has a `main.trq`, or else the directory of
+/// `path` itself. Every file of a project therefore means the same module by
+/// `use a::b`, whichever of them is compiled.
+fn project_root(path: &Path) -> PathBuf {
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+    dir.ancestors()
+        .find(|ancestor| ancestor.join("main.trq").is_file())
+        .unwrap_or(dir)
+        .to_path_buf()
+}
+
+/// The path that identifies `path` however it was spelt.
+fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Writes `files` into a new directory, returning the path of the first.
+    fn project(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("zeta-{}-{}", name, std::process::id()));
+        for (file, src) in files.iter() {
+            let path = dir.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, src).unwrap();
+        }
+        dir.join(files[0].0)
+    }
+
+    #[test]
+    fn loads_imported_modules_once() {
+        let main = project(
+            "load",
+            &[
+                (
+                    "main.trq",
+                    "use math::add\nuse util\nfn main(): int {\n    return add(1, 2)\n}",
+                ),
+                (
+                    "math.trq",
+                    "use util::twice\npub fn add(a: int, b: int): int {\n    return a + b\n}",
+                ),
+                (
+                    "util/mod.trq",
+                    "pub fn twice(a: int): int {\n    return a * 2\n}",
+                ),
+            ],
+        );
+        let mut sources = SourceMap::new();
+        let (program, diagnostics) = load_program(&main, &mut sources).unwrap();
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+        let names: Vec<&str> = program.func.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, vec!["main", "add", "twice"]);
+        assert!(program.func[1].is_pub && !program.func[0].is_pub);
+        let modules: Vec<Option<usize>> = program.imports.iter().map(|i| i.module).collect();
+        assert_eq!(modules, vec![Some(1), Some(2), Some(2)]);
+        assert_eq!(program.imports[0].item(), Some("add"));
+        assert_eq!(program.imports[2].item(), None);
+        assert_eq!(sources.files().len(), 3);
+    }
+
+    #[test]
+    fn reports_missing_modules_and_cycles() {
+        let main = project(
+            "cycle",
+            &[
+                ("main.trq", "use a::f\nuse missing::g"),
+                ("a.trq", "use b::g\npub fn f() {\n}"),
+                ("b.trq", "use a::f\npub fn g() {\n}"),
+            ],
+        );
+        let mut sources = SourceMap::new();
+        let (program, diagnostics) = load_program(&main, &mut sources).unwrap();
+        let codes: Vec<_> = diagnostics.iter().map(|d| d.code).collect();
+        assert_eq!(codes, vec![Some("Z0033"), Some("Z0031")]);
+        assert!(diagnostics[0].notes[0].contains("`a` -> `b` -> `a`"));
+        assert_eq!(program.func.len(), 2);
+    }
+
+    #[test]
+    fn resolves_modules_from_the_project_root() {
+        let main = project(
+            "root",
+            &[
+                ("main.trq", "use shapes\nfn main() {\n}"),
+                ("shapes/mod.trq", "use maths::double\npub fn f() {\n}"),
+                ("maths.trq", "pub fn double() {\n}"),
+            ],
+        );
+        // Compiling the module on its own still finds `maths` next to `main.trq`.
+        let shapes = main.parent().unwrap().join("shapes").join("mod.trq");
+        let mut sources = SourceMap::new();
+        let (program, diagnostics) = load_program(&shapes, &mut sources).unwrap();
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+        assert_eq!(program.imports[0].module, Some(1));
+        assert_eq!(project_root(&shapes), main.parent().unwrap());
+    }
+}
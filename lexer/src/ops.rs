@@ -1,5 +1,8 @@
-use crate::{errors::ParseError, Token, TokenType};
-pub type ParsingResult = Result<(Program, Vec<TokenType>), String>;
+use crate::{errors::ParseError, Span, Token};
+use std::convert::TryFrom;
+use std::fmt;
+/// A possibly partial program together with every syntax error found while parsing it.
+pub type ParsingResult = (Program, Vec<ParseError>);
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize)]
@@ -11,46 +14,132 @@ pub enum Size {
 #[derive(Debug, Eq, PartialEq, Clone, Deserialize, Serialize)]
 pub struct Variable {
     pub name: String,
-    pub t: String,
+    /// The declared type, or `None` when it is inferred from the initialiser (`let`, `const`).
+    pub t: Option<Type>,
     pub size: Size,
+    pub span: Span,
 }
 
+/// `use module::item`, or `use module` for every `pub` item of a top-level module.
 #[derive(Debug, Eq, PartialEq, Clone, Deserialize, Serialize)]
 pub struct Import {
     pub name: Vec<String>,
+    pub span: Span,
+    /// The file id of the imported module, filled in by [`crate::load_program`].
+    pub module: Option<usize>,
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+impl Import {
+    /// The path of the imported module, which is every segment but the item's.
+    pub fn module_path(&self) -> &[String] {
+        match self.name.len() {
+            1 => &self.name,
+            len => &self.name[..len - 1],
+        }
+    }
+
+    /// The imported item, or `None` when the whole module is imported.
+    pub fn item(&self) -> Option<&str> {
+        match self.name.len() {
+            1 => None,
+            _ => self.name.last().map(|s| s.as_str()),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize, Serialize, Clone)]
 pub struct Program {
     pub imports: Vec<Import>,
     pub func: Vec<Function>,
-    pub globals: Vec<Statement>,
+    pub globals: Vec<Global>,
+    pub structs: Vec<Struct>,
+    pub enums: Vec<Enum>,
 }
 
+/// `const limit: int = 10 * 2` or `let count = 0` at the top level. The
+/// initial value is worked out when compiling, and only a `let` can be
+/// assigned to afterwards.
 #[derive(Debug, Eq, PartialEq, Clone, Deserialize, Serialize)]
+pub struct Global {
+    pub is_pub: bool,
+    pub is_const: bool,
+    pub var: Variable,
+    pub value: Expression,
+    pub span: Span,
+}
+
+/// `struct Point { x: int, y: int }`
+#[derive(Debug, Eq, PartialEq, Clone, Deserialize, Serialize)]
+pub struct Struct {
+    pub is_pub: bool,
+    pub name: String,
+    /// The fields in declaration order. Their types are always given.
+    pub fields: Vec<Variable>,
+    pub span: Span,
+}
+
+/// `enum Command { Help, Add(int, int) }`
+#[derive(Debug, Eq, PartialEq, Clone, Deserialize, Serialize)]
+pub struct Enum {
+    pub is_pub: bool,
+    pub name: String,
+    pub variants: Vec<Variant>,
+    pub span: Span,
+}
+
+/// One variant of an [`Enum`], such as `Add(int, int)`, with the types of its payload.
+#[derive(Debug, Eq, PartialEq, Clone, Deserialize, Serialize)]
+pub struct Variant {
+    pub name: String,
+    pub fields: Vec<Type>,
+    pub span: Span,
+}
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Deserialize, Serialize)]
 pub enum Type {
     Bool,
     Str,
     Void,
     Int,
+    /// A 64-bit floating point number.
+    Float,
     Mlstr,
     Char,
+    /// `[element; len]`, a fixed-size array.
+    Array(Box<Type>, u64),
+    /// A type declared in the program, a `struct` or an `enum`.
+    Named(String),
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Function {
+    pub is_pub: bool,
     pub is_async: bool,
     pub name: String,
     pub return_type: Type,
     pub arguments: Vec<Variable>,
     pub statements: Vec<Statement>,
+    pub span: Span,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Deserialize, Serialize)]
+pub struct Expression {
+    pub kind: ExpressionKind,
+    pub span: Span,
+}
+
+impl Expression {
+    pub fn new(kind: ExpressionKind, span: Span) -> Self {
+        Self { kind, span }
+    }
 }
 
 #[derive(Debug, Eq, PartialEq, Clone, Deserialize, Serialize)]
-pub enum Expression {
+pub enum ExpressionKind {
     BinOp(BinOp, Box<Expression>, Box<Expression>),
     UnOp(UnOp, Box<Expression>),
     Int(u64),
+    Float(Float),
     Char(String),
     MLStr(String),
     FunctionCall(String, Vec<Expression>),
@@ -60,14 +149,104 @@ pub enum Expression {
     Assign(String, Box<Expression>),
     AssignPostfix(String, Box<Expression>),
     Ternary(Box<Expression>, Box<Expression>, Box<Expression>),
+    /// `[a, b, c]`
+    Array(Vec<Expression>),
+    /// `array[index]`
+    Index(Box<Expression>, Box<Expression>),
+    /// `array[index] = value`, where `array` is a variable or another index expression.
+    AssignIndex(Box<Expression>, Box<Expression>, Box<Expression>),
+    /// `Point { x: 1, y: 2 }`
+    StructLiteral(String, Vec<(String, Expression)>),
+    /// `value.field`
+    Field(Box<Expression>, String),
+    /// `value.field = value`, where `value` is a variable or an element or field of one.
+    AssignField(Box<Expression>, String, Box<Expression>),
+    /// `Enum::Variant` or `Enum::Variant(a, b)`
+    Variant(String, String, Vec<Expression>),
+    /// `match value { pattern => result, ... }`
+    Match(Box<Expression>, Vec<MatchArm>),
+    /// `value as type`, converting between `int` and `float`.
+    Cast(Box<Expression>, Type),
+}
+
+/// A `float` literal. Literals compare by their bits, so that the syntax
+/// trees holding them can still be `Eq`.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+pub struct Float(pub f64);
+
+impl PartialEq for Float {
+    fn eq(&self, other: &Float) -> bool {
+        self.0.to_bits() == other.0.to_bits()
+    }
+}
+
+impl Eq for Float {}
+
+impl fmt::Display for Float {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Unlike `{}`, `{:?}` always writes a `.` or an exponent, e.g. `1.0`.
+        write!(f, "{:?}", self.0)
+    }
+}
+
+/// One `pattern => value` of a `match`.
+#[derive(Debug, Eq, PartialEq, Clone, Deserialize, Serialize)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub value: Expression,
+    pub span: Span,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Deserialize, Serialize)]
+pub struct Pattern {
+    pub kind: PatternKind,
+    pub span: Span,
 }
 
 #[derive(Debug, Eq, PartialEq, Clone, Deserialize, Serialize)]
-pub enum Statement {
+pub enum PatternKind {
+    /// `_`, which matches anything.
+    Wildcard,
+    Int(u64),
+    Bool(bool),
+    Str(String),
+    /// `Enum::Variant(a, _)`, binding each payload value to a name or ignoring it with `_`.
+    Variant(String, String, Vec<Option<Variable>>),
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Deserialize, Serialize)]
+pub struct Statement {
+    pub kind: StatementKind,
+    pub span: Span,
+}
+
+impl Statement {
+    pub fn new(kind: StatementKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Deserialize, Serialize)]
+pub enum StatementKind {
     Declare(Variable, Option<Expression>),
     Return(Expression),
     If(Expression, Box<Statement>, Option<Box<Statement>>),
     While(Expression, Box<Statement>),
+    /// `for (init; condition; step) body`, where each of the three parts may be left out.
+    For(
+        Option<Box<Statement>>,
+        Option<Expression>,
+        Option<Expression>,
+        Box<Statement>,
+    ),
+    /// `for i in start..end body`, counting `i` up from `start` to `end` exclusive.
+    ForRange(Variable, Expression, Expression, Box<Statement>),
+    /// `name: loop`, giving a `while` or `for` loop a label that `break` and `continue` can name.
+    Labeled(String, Box<Statement>),
+    /// `break` or `break label`.
+    Break(Option<String>),
+    /// `continue` or `continue label`.
+    Continue(Option<String>),
     Exp(Expression),
     Compound(Vec<Statement>),
 }
@@ -102,9 +281,11 @@ pub enum BinOp {
     Comma,
 }
 
-impl From<Token> for BinOp {
-    fn from(token: Token) -> Self {
-        match token {
+impl TryFrom<Token> for BinOp {
+    type Error = Token;
+
+    fn try_from(token: Token) -> Result<Self, Self::Error> {
+        Ok(match token {
             Token::Multiplication => BinOp::Multiplication,
             Token::Division => BinOp::Division,
             Token::Modulus => BinOp::Modulus,
@@ -124,18 +305,75 @@ impl From<Token> for BinOp {
             Token::BitwiseXor => BinOp::BitwiseXor,
             Token::BitwiseOr => BinOp::BitwiseOr,
             Token::Comma => BinOp::Comma,
-            other => panic!("Token {:?} cannot be converted into a BinOp", other),
-        }
+            other => return Err(other),
+        })
     }
 }
 
-impl From<Token> for UnOp {
-    fn from(token: Token) -> Self {
-        match token {
+impl TryFrom<Token> for UnOp {
+    type Error = Token;
+
+    fn try_from(token: Token) -> Result<Self, Self::Error> {
+        Ok(match token {
             Token::Negation => UnOp::Negation,
             Token::LogicalNeg => UnOp::LogicalNeg,
             Token::BitComp => UnOp::BitComp,
-            other => panic!("Unsupported token {:?}, can only use: ! ~ -", other),
-        }
+            other => return Err(other),
+        })
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Type::Bool => "bool",
+            Type::Str => "str",
+            Type::Void => "void",
+            Type::Int => "int",
+            Type::Float => "float",
+            Type::Mlstr => "mlstr",
+            Type::Char => "char",
+            Type::Array(element, len) => return write!(f, "[{}; {}]", element, len),
+            Type::Named(name) => name,
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl fmt::Display for BinOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = match self {
+            BinOp::Addition => "+",
+            BinOp::Subtraction => "-",
+            BinOp::Multiplication => "*",
+            BinOp::Division => "/",
+            BinOp::Modulus => "%",
+            BinOp::LessThan => "<",
+            BinOp::LessThanOrEqual => "<=",
+            BinOp::GreaterThan => ">",
+            BinOp::GreaterThanOrEqual => ">=",
+            BinOp::Equal => "==",
+            BinOp::NotEqual => "!=",
+            BinOp::And => "&&",
+            BinOp::Or => "||",
+            BinOp::BitwiseLeft => "<<",
+            BinOp::BitwiseRight => ">>",
+            BinOp::BitwiseAnd => "&",
+            BinOp::BitwiseXor => "^",
+            BinOp::BitwiseOr => "|",
+            BinOp::Comma => ",",
+        };
+        write!(f, "{}", op)
+    }
+}
+
+impl fmt::Display for UnOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = match self {
+            UnOp::Negation => "-",
+            UnOp::BitComp => "~",
+            UnOp::LogicalNeg => "!",
+        };
+        write!(f, "{}", op)
     }
 }
@@ -1,62 +1,83 @@
 use crate::errors::ParseError;
 use crate::{
-    BinOp, Expression, Function, Import, Keyword, ParserDescriptor, ParsingResult, Program, Size,
-    Statement, Token, TokenType, Type, Value, Variable,
+    BinOp, Expression, ExpressionKind, Function, Global, Import, Keyword, ParserDescriptor, ParsingResult,
+    Enum, MatchArm, Pattern, PatternKind, Program, Size, Span, Statement, StatementKind, Struct,
+    Token, TokenType, Type, UnOp, Value, Variable, Variant,
 };
-use std::iter::Peekable;
-use std::process;
-use std::vec::IntoIter;
-use colored::Colorize;
+use std::convert::TryFrom;
+
+/// How deeply statements and expressions may nest before the parser gives up,
+/// so that pathological input cannot overflow the stack. Each level walks the
+/// whole precedence ladder, so this stays small enough for a 2MiB thread stack
+/// in debug builds.
+const MAX_NESTING: usize = 32;
 
 #[derive(Debug)]
 pub struct Parser {
-    tokens: Peekable<IntoIter<TokenType>>,
-    rawtokens: Vec<TokenType>,
-    peeked: Vec<TokenType>,
-    file: Box<str>
+    tokens: Vec<TokenType>,
+    pos: usize,
+    prev_span: Span,
+    file: Box<str>,
+    errors: Vec<ParseError>,
+    depth: usize,
+    /// Whether `Name {` starts a struct literal here, see [`Parser::without_struct_literals`].
+    struct_literals: bool,
+    /// Set while starting on the operand of a prefix operator, see [`Parser::parse_factor`].
+    prefix_operand: bool,
 }
 
 impl Parser {
-    pub fn new(tokens: Vec<TokenType>, file:Box<str> 
-    ) -> Parser {
-        let ntoken = tokens.clone();
+    pub fn new(tokens: Vec<TokenType>, file: Box<str>) -> Parser {
         Parser {
-            tokens: tokens.into_iter().peekable(),
-            rawtokens: ntoken,
-            peeked: Vec::new(),
-            file: file.to_owned()
+            tokens,
+            pos: 0,
+            prev_span: Span::default(),
+            file,
+            errors: Vec::new(),
+            depth: 0,
+            struct_literals: true,
+            prefix_operand: false,
         }
     }
 
-    pub fn parse(&mut self) -> Result<ParsingResult, ParseError> {
+    /// Parses the whole token stream. Syntax errors do not stop parsing: each
+    /// one is recorded, the parser skips ahead to a point where it can resume,
+    /// and whatever could be parsed is returned alongside every error.
+    pub fn parse(&mut self) -> ParsingResult {
         self.parse_program()
     }
 
     fn next(&mut self) -> Option<TokenType> {
-        if self.peeked.is_empty() {
-            self.tokens.next()
-        } else {
-            self.peeked.pop()
-        }
+        let token = self.tokens.get(self.pos).cloned()?;
+        self.pos += 1;
+        self.prev_span = token.span;
+        Some(token)
     }
 
     fn peek(&mut self) -> Option<Token> {
-        if let Some(token) = self.next() {
-            self.push(Some(token.clone()));
-            Some(token.token)
-        } else {
-            None
-        }
+        self.tokens.get(self.pos).map(|t| t.token.clone())
     }
 
     fn peek_tt(&mut self) -> Option<TokenType> {
-        if let Some(token) = self.next() {
-            self.push(Some(token.clone()));
-            Some(token)
-        } else {
-            None
+        self.tokens.get(self.pos).cloned()
+    }
+
+    /// Span of the next token, or an empty span just past the last one at end of input.
+    fn peek_span(&mut self) -> Span {
+        match self.tokens.get(self.pos) {
+            Some(token) => token.span,
+            None => Span {
+                start: self.prev_span.end,
+                ..self.prev_span
+            },
         }
     }
+
+    /// Span from `start` up to the end of the last consumed token.
+    fn span_from(&self, start: Span) -> Span {
+        start.to(self.prev_span)
+    }
+
     fn drop(&mut self, count: usize) {
         for _ in 0..count {
             self.next();
@@ -64,30 +85,59 @@ impl Parser {
     }
 
     fn push(&mut self, token: Option<TokenType>) {
-        if let Some(s) = token {
-            self.peeked.push(s);
+        if token.is_some() {
+            self.pos -= 1;
+            if self.pos > 0 {
+                self.prev_span = self.tokens[self.pos - 1].span;
+            }
         }
     }
 
-    fn has_more(&mut self) -> bool {
-        !self.peeked.is_empty() || self.tokens.peek().is_some()
+    fn next_token(&mut self, expected: ParserDescriptor) -> Result<TokenType, ParseError> {
+        match self.next() {
+            Some(token) => Ok(token),
+            None => Err(self.eof(expected)),
+        }
+    }
+
+    fn eof(&mut self, expected: ParserDescriptor) -> ParseError {
+        ParseError::UnexpectedEof {
+            expected,
+            span: self.peek_span(),
+            filename: self.file.clone(),
+        }
     }
 
-    fn next_token(&mut self) -> TokenType {
-        self.next().expect("failed to parse")
+    /// Runs `parse` one nesting level deeper, failing once `MAX_NESTING` is reached.
+    fn nested<T, F>(&mut self, parse: F) -> Result<T, ParseError>
+    where
+        F: FnOnce(&mut Parser) -> Result<T, ParseError>,
+    {
+        if self.depth >= MAX_NESTING {
+            return Err(ParseError::NestingTooDeep {
+                span: self.peek_span(),
+                filename: self.file.clone(),
+            });
+        }
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+        result
     }
 
     fn match_token(&mut self, token: Token) -> Result<Token, ParseError> {
-        let t= self.next_token();
-        let line = t.linenum;
+        let t = self.next_token(ParserDescriptor::Token(token.clone()))?;
         match t.token {
             ref t if t == &token => Ok(t.to_owned()),
-            other => Err(ParseError::UnexpectedToken {
-                expected: ParserDescriptor::Token(token),
-                filename: self.file.clone(),
-                received: other,
-                linenum: line,
-            }),
+            _ => {
+                self.push(Some(t.clone()));
+                Err(ParseError::UnexpectedToken {
+                    expected: ParserDescriptor::Token(token),
+                    filename: self.file.clone(),
+                    received: t.token,
+                    span: t.span,
+                })
+            }
         }
     }
 
@@ -101,103 +151,254 @@ impl Parser {
     }
 
     fn match_keyword(&mut self, keyword: &Keyword) -> Result<(), ParseError> {
-        let token = self.next_token();
-        let line = token.linenum;
+        let token = self.next_token(ParserDescriptor::Token(Token::Keyword(keyword.clone())))?;
         match token.token {
             Token::Keyword(ref k) if k == keyword => Ok(()),
-            other => Err(ParseError::UnexpectedToken {
-                expected: ParserDescriptor::Newline,
-                filename: self.file.clone(),
-                received: other,
-                linenum: line
-            }),
+            _ => {
+                self.push(Some(token.clone()));
+                Err(ParseError::UnexpectedToken {
+                    expected: ParserDescriptor::Token(Token::Keyword(keyword.clone())),
+                    filename: self.file.clone(),
+                    received: token.token,
+                    span: token.span,
+                })
+            }
+        }
+    }
+
+    fn is_statement_start(token: &Token) -> bool {
+        matches!(
+            token,
+            Token::Keyword(Keyword::Int)
+                | Token::Keyword(Keyword::Float)
+                | Token::Keyword(Keyword::Let)
+                | Token::Keyword(Keyword::Bool)
+                | Token::Keyword(Keyword::Const)
+                | Token::Keyword(Keyword::String)
+                | Token::Keyword(Keyword::Return)
+                | Token::Keyword(Keyword::If)
+                | Token::Keyword(Keyword::While)
+                | Token::Keyword(Keyword::For)
+                | Token::Keyword(Keyword::Break)
+                | Token::Keyword(Keyword::Continue)
+        )
+    }
+
+    fn is_item_start(token: &Token) -> bool {
+        matches!(
+            token,
+            Token::Keyword(Keyword::Func)
+                | Token::Keyword(Keyword::Use)
+                | Token::Keyword(Keyword::Const)
+                | Token::Keyword(Keyword::Let)
+                | Token::Keyword(Keyword::Struct)
+                | Token::Keyword(Keyword::Enum)
+                | Token::Keyword(Keyword::Pub)
+        )
+    }
+
+    /// Skips tokens after a syntax error inside a block until the next token
+    /// starts a new line, starts a statement or closes the block.
+    fn synchronize(&mut self, start_pos: usize) {
+        let line = self.prev_span.line;
+        let mut depth = 0;
+        while let Some(token) = self.peek_tt() {
+            let moved = self.pos > start_pos;
+            match token.token {
+                Token::CloseBrace if depth == 0 => break,
+                Token::Keyword(Keyword::Func)
+                | Token::Keyword(Keyword::Use)
+                | Token::Keyword(Keyword::Struct)
+                | Token::Keyword(Keyword::Enum)
+                | Token::Keyword(Keyword::Pub) => break,
+                ref t if depth == 0
+                    && moved
+                    && (token.span.line > line || Parser::is_statement_start(t)) =>
+                {
+                    break
+                }
+                Token::OpenBrace => depth += 1,
+                Token::CloseBrace => depth -= 1,
+                _ => {}
+            }
+            self.next();
+        }
+    }
+
+    /// Skips tokens after a syntax error at the top level until the next item.
+    fn synchronize_item(&mut self, start_pos: usize) {
+        let mut depth = 0;
+        while let Some(token) = self.peek() {
+            match token {
+                ref t if depth == 0 && self.pos > start_pos && Parser::is_item_start(t) => break,
+                Token::OpenBrace => depth += 1,
+                Token::CloseBrace if depth > 0 => depth -= 1,
+                _ => {}
+            }
+            self.next();
+        }
+    }
+
+    /// Parses statements up to, but not including, the `}` closing the current block.
+    fn parse_block_statements(&mut self) -> Vec<Statement> {
+        let mut statements = Vec::new();
+        while let Some(token) = self.peek() {
+            if token == Token::CloseBrace || token == Token::Keyword(Keyword::Func) {
+                break;
+            }
+            let start_pos = self.pos;
+            match self.parse_statement() {
+                Ok(statement) => statements.push(statement),
+                Err(e) => {
+                    self.errors.push(e);
+                    self.synchronize(start_pos);
+                }
+            }
+        }
+        statements
+    }
+
+    /// Consumes the `}` closing a block, recording an error if it is missing.
+    fn close_block(&mut self) {
+        if let Err(e) = self.match_token(Token::CloseBrace) {
+            self.errors.push(e);
         }
     }
 
     fn match_identifier(&mut self) -> Result<String, ParseError> {
-        let token = self.next_token();
-        let line = token.linenum;
+        let token = self.next_token(ParserDescriptor::AnyIdentifier)?;
         match token.token {
             Token::Identifier(n) => Ok(n),
-            other => Err(ParseError::UnexpectedToken {
-                expected: ParserDescriptor::AnyIdentifier,
-                filename: self.file.clone(),
-                received: other,
-                linenum: line,
-            }),
+            _ => {
+                self.push(Some(token.clone()));
+                Err(ParseError::UnexpectedToken {
+                    expected: ParserDescriptor::AnyIdentifier,
+                    filename: self.file.clone(),
+                    received: token.token,
+                    span: token.span,
+                })
+            }
         }
     }
 }
 
 impl Parser {
-    fn parse_program(&mut self) -> Result<ParsingResult, ParseError> {
+    fn parse_program(&mut self) -> ParsingResult {
         self.main_parser()
     }
 
-    fn main_parser(&mut self) -> Result<ParsingResult, ParseError> {
+    fn main_parser(&mut self) -> ParsingResult {
         let mut functions = Vec::new();
         let mut imports = Vec::new();
         let mut globals = Vec::new();
+        let mut structs = Vec::new();
+        let mut enums = Vec::new();
 
-        while self.has_more() {
-            if self.peek().unwrap() == Token::Keyword(Keyword::Use) {
-                imports.push(self.parse_import_statement()?);
-            } else if self.peek().unwrap() == Token::Keyword(Keyword::Func) {
-                functions.push(self.parse_function()?);
-            } else {
-                globals.push(self.parse_global_vars()?);
+        while let Some(token) = self.peek() {
+            let start_pos = self.pos;
+            let result = match token {
+                Token::Keyword(Keyword::Use) => {
+                    self.parse_import_statement().map(|i| imports.push(i))
+                }
+                Token::Keyword(Keyword::Func) => self.parse_function().map(|f| functions.push(f)),
+                Token::Keyword(Keyword::Struct) => self.parse_struct().map(|s| structs.push(s)),
+                Token::Keyword(Keyword::Enum) => self.parse_enum().map(|e| enums.push(e)),
+                // `pub` makes an item usable from the modules that import it.
+                Token::Keyword(Keyword::Pub) => {
+                    self.next();
+                    match self.peek() {
+                        Some(Token::Keyword(Keyword::Struct)) => self.parse_struct().map(|s| {
+                            structs.push(Struct { is_pub: true, ..s })
+                        }),
+                        Some(Token::Keyword(Keyword::Enum)) => self
+                            .parse_enum()
+                            .map(|e| enums.push(Enum { is_pub: true, ..e })),
+                        Some(Token::Keyword(Keyword::Const))
+                        | Some(Token::Keyword(Keyword::Let)) => self
+                            .parse_global()
+                            .map(|g| globals.push(Global { is_pub: true, ..g })),
+                        _ => self.parse_function().map(|f| {
+                            functions.push(Function { is_pub: true, ..f })
+                        }),
+                    }
+                }
+                Token::Keyword(Keyword::Const) | Token::Keyword(Keyword::Let) => {
+                    self.parse_global().map(|g| globals.push(g))
+                }
+                _ => self
+                    .next_token(ParserDescriptor::AnyItem)
+                    .and_then(|token| {
+                        Err(ParseError::UnexpectedToken {
+                            expected: ParserDescriptor::AnyItem,
+                            filename: self.file.clone(),
+                            received: token.token,
+                            span: token.span,
+                        })
+                    }),
+            };
+            if let Err(e) = result {
+                self.errors.push(e);
+                self.synchronize_item(start_pos);
             }
         }
 
-        Ok(Ok((
+        (
             Program {
                 imports,
                 func: functions,
                 globals,
+                structs,
+                enums,
             },
-            self.rawtokens.clone(),
-        )))
+            std::mem::take(&mut self.errors),
+        )
     }
 
+    /// Parses `use a::b::c`.
     fn parse_import_statement(&mut self) -> Result<Import, ParseError> {
-        if let Some(Token::Keyword(Keyword::Use)) = self.peek() {
+        let start = self.peek_span();
+        self.match_keyword(&Keyword::Use)?;
+        let mut name = vec![self.match_identifier()?];
+        while let Some(Token::DoubleColon) = self.peek() {
             self.next();
+            name.push(self.match_identifier()?);
         }
-        let mut imports = Vec::new();
-        while let Token::Identifier(name) = self.next().unwrap().token {
-            imports.push(name);
-            if let Some(Token::Keyword(Keyword::Func)) = self.peek() {
-                break;
-            }
-            if let Some(Token::Keyword(Keyword::Const)) = self.peek() {
-                break;
-            }
-            if let Some(Token::Keyword(Keyword::Use)) = self.peek() {
-                break;
-            }
-            if let Some(Token::DoubleColon) = self.peek() {
-                self.next();
-            }
-        }
-        Ok(Import { name: imports })
+        Ok(Import {
+            name,
+            span: self.span_from(start),
+            module: None,
+        })
     }
 
-    fn parse_global_vars(&mut self) -> Result<Statement, ParseError> {
-        let ne = self.next();
-        match ne {
-            Some(TokenType {
-                token: Token::Keyword(Keyword::Const),
-                val: _,
-                linenum: line,
-            }) => self.parse_declare(Size::Byte, "&str"),
-            other => {
-                self.push(other);
-                Ok(Statement::Exp(self.parse_expression()?))
+    /// Parses a top-level `const name = value` or `let name = value`,
+    /// optionally with a type: `const name: int = value`.
+    fn parse_global(&mut self) -> Result<Global, ParseError> {
+        let start = self.peek_span();
+        let is_const = match self.peek() {
+            Some(Token::Keyword(Keyword::Let)) => {
+                self.next();
+                false
             }
+            _ => {
+                self.match_keyword(&Keyword::Const)?;
+                true
+            }
+        };
+        match self.parse_declare(Size::Byte, None, start)?.kind {
+            StatementKind::Declare(var, Some(value)) => Ok(Global {
+                is_pub: false,
+                is_const,
+                var,
+                value,
+                span: self.span_from(start),
+            }),
+            // A declaration without a value is reported by `parse_declare`.
+            _ => unreachable!(),
         }
     }
 
     fn parse_function(&mut self) -> Result<Function, ParseError> {
+        let start = self.peek_span();
         self.match_keyword(&Keyword::Func)?;
 
         let is_async = match self.peek() {
@@ -215,8 +416,8 @@ impl Parser {
         };
 
         self.match_token(Token::CloseParen)?;
-        let return_type = match self.peek().unwrap() {
-            Token::Colon => {
+        let return_type = match self.peek() {
+            Some(Token::Colon) => {
                 self.match_token(Token::Colon)?;
                 self.parse_return(&name)?
             }
@@ -224,36 +425,108 @@ impl Parser {
         };
         self.match_token(Token::OpenBrace)?;
 
-        let mut statements = Vec::new();
-
-        while self.peek_token(Token::CloseBrace).is_err() {
-            let statement = self.parse_statement()?;
-            statements.push(statement);
-        }
-
-        self.match_token(Token::CloseBrace)?;
+        let statements = self.parse_block_statements();
+        self.close_block();
 
         Ok(Function {
+            is_pub: false,
             is_async,
             name,
             return_type,
             arguments,
             statements,
+            span: self.span_from(start),
+        })
+    }
+
+    /// Parses `struct Name { field: type, ... }`. The commas between fields are optional.
+    fn parse_struct(&mut self) -> Result<Struct, ParseError> {
+        let start = self.peek_span();
+        self.match_keyword(&Keyword::Struct)?;
+        let name = self.match_identifier()?;
+        self.match_token(Token::OpenBrace)?;
+        let mut fields = Vec::new();
+        while self.peek_token(Token::CloseBrace).is_err() {
+            let field_start = self.peek_span();
+            let field = self.match_identifier()?;
+            self.match_token(Token::Colon)?;
+            let t = self.parse_type()?;
+            fields.push(Variable {
+                name: field,
+                size: Size::Int,
+                t: Some(t),
+                span: self.span_from(field_start),
+            });
+            if let Some(Token::Comma) = self.peek() {
+                self.next();
+            }
+        }
+        self.match_token(Token::CloseBrace)?;
+        Ok(Struct {
+            is_pub: false,
+            name,
+            fields,
+            span: self.span_from(start),
+        })
+    }
+
+    /// Parses `enum Name { Variant, Variant(type, ...), ... }`. The commas
+    /// between variants are optional.
+    fn parse_enum(&mut self) -> Result<Enum, ParseError> {
+        let start = self.peek_span();
+        self.match_keyword(&Keyword::Enum)?;
+        let name = self.match_identifier()?;
+        self.match_token(Token::OpenBrace)?;
+        let mut variants = Vec::new();
+        while self.peek_token(Token::CloseBrace).is_err() {
+            let variant_start = self.peek_span();
+            let variant = self.match_identifier()?;
+            let mut fields = Vec::new();
+            if let Some(Token::OpenParen) = self.peek() {
+                self.next();
+                while self.peek_token(Token::CloseParen).is_err() {
+                    fields.push(self.parse_type()?);
+                    if let Some(Token::Comma) = self.peek() {
+                        self.next();
+                    }
+                }
+                self.match_token(Token::CloseParen)?;
+            }
+            variants.push(Variant {
+                name: variant,
+                fields,
+                span: self.span_from(variant_start),
+            });
+            if let Some(Token::Comma) = self.peek() {
+                self.next();
+            }
+        }
+        self.match_token(Token::CloseBrace)?;
+        Ok(Enum {
+            is_pub: false,
+            name,
+            variants,
+            span: self.span_from(start),
         })
     }
 
     fn parse_return(&mut self, fnname: &str) -> Result<Type, ParseError> {
+        if let Some(Token::OpenSquareParen) | Some(Token::Identifier(_)) = self.peek() {
+            return self.parse_type();
+        }
         let typ = match self.peek_tt() {
-            Some(TokenType{token: Token::Keyword(Keyword::Bool), val: _, linenum: _}) => Ok(Type::Bool),
-            Some(TokenType{token: Token::Keyword(Keyword::MLstr), val: _, linenum: _}) => Ok(Type::Mlstr),
-            Some(TokenType{token: Token::Keyword(Keyword::Int), val: _, linenum: _}) => Ok(Type::Int),
-            Some(TokenType{token: Token::Keyword(Keyword::String), val: _, linenum: _}) => Ok(Type::Str),
-            Some(TokenType{token: Token::Keyword(Keyword::Void), val: _, linenum: _}) => Ok(Type::Void),
-            other => Err(ParseError::AbsentReturnType{
+            Some(TokenType { token: Token::Keyword(Keyword::Bool), .. }) => Ok(Type::Bool),
+            Some(TokenType { token: Token::Keyword(Keyword::MLstr), .. }) => Ok(Type::Mlstr),
+            Some(TokenType { token: Token::Keyword(Keyword::Int), .. }) => Ok(Type::Int),
+            Some(TokenType { token: Token::Keyword(Keyword::Float), .. }) => Ok(Type::Float),
+            Some(TokenType { token: Token::Keyword(Keyword::String), .. }) => Ok(Type::Str),
+            Some(TokenType { token: Token::Keyword(Keyword::Void), .. }) => Ok(Type::Void),
+            Some(other) => Err(ParseError::AbsentReturnType {
                 filename: self.file.clone(),
                 fnname: fnname.into(),
-                linenum: other.unwrap().linenum,
+                span: other.span,
             }),
+            None => Err(self.eof(ParserDescriptor::AnyType)),
         };
 
         if typ.is_ok() {
@@ -262,73 +535,133 @@ impl Parser {
         typ
     }
 
-    fn parse_statement(&mut self) -> Result<Statement, ParseError> {
-        let ne = self.next();
-        match ne {
-            Some(TokenType {
+    /// Parses a type written out in full: a primitive such as `int`, or an
+    /// array such as `[int; 3]`.
+    fn parse_type(&mut self) -> Result<Type, ParseError> {
+        match self.next_token(ParserDescriptor::AnyType)? {
+            TokenType {
                 token: Token::Keyword(Keyword::Int),
-                val: _,
-                linenum: line,
-            }) => self.parse_declare(Size::Int, "int"),
-            Some(TokenType {
-                token: Token::Keyword(Keyword::Let),
-                val: _,
-                linenum: line,
-            }) => self.parse_declare(Size::Byte, ""),
-            Some(TokenType {
+                ..
+            } => Ok(Type::Int),
+            TokenType {
+                token: Token::Keyword(Keyword::Float),
+                ..
+            } => Ok(Type::Float),
+            TokenType {
                 token: Token::Keyword(Keyword::Bool),
-                val: _,
-                linenum: line,
-            }) => self.parse_declare(Size::Byte, "bool"),
-            Some(TokenType {
-                token: Token::Keyword(Keyword::Const),
-                val: _,
-                linenum: line,
-            }) => self.parse_declare(Size::Byte, ""),
-            Some(TokenType {
+                ..
+            } => Ok(Type::Bool),
+            TokenType {
                 token: Token::Keyword(Keyword::String),
-                val: _,
-                linenum: line,
-            }) => self.parse_declare(Size::Byte, "str"),
-            Some(TokenType {
-                token: Token::Keyword(Keyword::Return),
-                val: _,
-                linenum: line,
-            }) => Ok(Statement::Return(self.parse_expression()?)),
-            Some(TokenType {
-                token: Token::Keyword(Keyword::If),
-                val: _,
-                linenum: line,
-            }) => self.parse_if_statement(),
-            Some(TokenType {
-                token: Token::Keyword(Keyword::While),
-                val: _,
-                linenum: line,
-            }) => self.parse_while_statement(),
-            Some(TokenType {
-                token: Token::OpenBrace,
-                val: _,
-                linenum: line,
-            }) => self.parse_compond_statement(),
-            other => {
-                self.push(other);
-                Ok(Statement::Exp(self.parse_expression()?))
+                ..
+            } => Ok(Type::Str),
+            TokenType {
+                token: Token::Keyword(Keyword::MLstr),
+                ..
+            } => Ok(Type::Mlstr),
+            TokenType {
+                token: Token::Identifier(name),
+                ..
+            } => Ok(Type::Named(name)),
+            TokenType {
+                token: Token::OpenSquareParen,
+                ..
+            } => {
+                let element = self.nested(Parser::parse_type)?;
+                self.match_token(Token::Semicolon)?;
+                let len = match self.next_token(ParserDescriptor::AnyInteger)? {
+                    TokenType {
+                        token: Token::Literal(Value::Int(len)),
+                        ..
+                    } => len,
+                    received => {
+                        return Err(ParseError::UnexpectedToken {
+                            expected: ParserDescriptor::AnyInteger,
+                            filename: self.file.clone(),
+                            received: received.token,
+                            span: received.span,
+                        })
+                    }
+                };
+                self.match_token(Token::CloseSquareParen)?;
+                Ok(Type::Array(Box::new(element), len))
             }
+            received => Err(ParseError::UnexpectedToken {
+                expected: ParserDescriptor::AnyType,
+                filename: self.file.clone(),
+                received: received.token,
+                span: received.span,
+            }),
         }
     }
 
-    fn parse_compond_statement(&mut self) -> Result<Statement, ParseError> {
-        let mut statements = vec![];
-        while self.peek_token(Token::CloseBrace).is_err() {
-            statements.push(self.parse_statement()?);
+    fn parse_statement(&mut self) -> Result<Statement, ParseError> {
+        self.nested(Parser::parse_single_statement)
+    }
+
+    fn parse_single_statement(&mut self) -> Result<Statement, ParseError> {
+        let start = self.peek_span();
+        let ne = self.next();
+        let token = ne.as_ref().map(|t| &t.token);
+        match token {
+            Some(Token::Keyword(Keyword::Int)) => {
+                self.parse_declare(Size::Int, Some(Type::Int), start)
+            }
+            Some(Token::Keyword(Keyword::Float)) => {
+                self.parse_declare(Size::Int, Some(Type::Float), start)
+            }
+            Some(Token::Keyword(Keyword::Let)) => self.parse_declare(Size::Byte, None, start),
+            Some(Token::Keyword(Keyword::Bool)) => {
+                self.parse_declare(Size::Byte, Some(Type::Bool), start)
+            }
+            Some(Token::Keyword(Keyword::Const)) => self.parse_declare(Size::Byte, None, start),
+            Some(Token::Keyword(Keyword::String)) => {
+                self.parse_declare(Size::Byte, Some(Type::Str), start)
+            }
+            Some(Token::Keyword(Keyword::Return)) => {
+                let exp = self.parse_expression()?;
+                Ok(Statement::new(StatementKind::Return(exp), self.span_from(start)))
+            }
+            Some(Token::Keyword(Keyword::If)) => self.parse_if_statement(start),
+            Some(Token::Keyword(Keyword::While)) => self.parse_while_statement(start),
+            Some(Token::Keyword(Keyword::For)) => self.parse_for_statement(start),
+            Some(Token::Keyword(Keyword::Break)) => {
+                let label = self.parse_loop_label();
+                Ok(Statement::new(StatementKind::Break(label), self.span_from(start)))
+            }
+            Some(Token::Keyword(Keyword::Continue)) => {
+                let label = self.parse_loop_label();
+                Ok(Statement::new(StatementKind::Continue(label), self.span_from(start)))
+            }
+            Some(Token::Identifier(name)) if self.peek() == Some(Token::Colon) => {
+                let name = name.clone();
+                self.drop(1);
+                self.parse_labeled_statement(name, start)
+            }
+            Some(Token::OpenBrace) => self.parse_compond_statement(start),
+            _ => {
+                self.push(ne);
+                let exp = self.parse_expression()?;
+                Ok(Statement::new(StatementKind::Exp(exp), self.span_from(start)))
+            }
         }
-        self.drop(1);
-        Ok(Statement::Compound(statements))
     }
 
-    fn parse_if_statement(&mut self) -> Result<Statement, ParseError> {
-        match self.next_token() {
-            TokenType{token: Token::OpenParen,linenum: l, val: _} => {
+    fn parse_compond_statement(&mut self, start: Span) -> Result<Statement, ParseError> {
+        let statements = self.parse_block_statements();
+        self.close_block();
+        Ok(Statement::new(
+            StatementKind::Compound(statements),
+            self.span_from(start),
+        ))
+    }
+
+    fn parse_if_statement(&mut self, start: Span) -> Result<Statement, ParseError> {
+        match self.next_token(ParserDescriptor::Token(Token::OpenParen))? {
+            TokenType {
+                token: Token::OpenParen,
+                ..
+            } => {
                 let condition = self.parse_expression()?;
                 self.match_token(Token::CloseParen)?;
                 let if_body = self.parse_statement()?;
@@ -336,72 +669,240 @@ impl Parser {
                     Some(Token::Keyword(Keyword::Else)) => {
                         self.drop(1);
                         let else_body = self.parse_statement()?;
-                        Ok(Statement::If(
-                            condition,
-                            Box::new(if_body),
-                            Some(Box::new(else_body)),
+                        Ok(Statement::new(
+                            StatementKind::If(
+                                condition,
+                                Box::new(if_body),
+                                Some(Box::new(else_body)),
+                            ),
+                            self.span_from(start),
                         ))
                     }
-                    _ => Ok(Statement::If(condition, Box::new(if_body), None)),
+                    _ => Ok(Statement::new(
+                        StatementKind::If(condition, Box::new(if_body), None),
+                        self.span_from(start),
+                    )),
                 }
             }
             received => Err(ParseError::UnexpectedToken {
                 expected: ParserDescriptor::Token(Token::OpenParen),
                 received: received.token,
                 filename: self.file.clone(),
-                linenum: received.linenum 
+                span: received.span,
             }),
         }
     }
 
-    fn parse_while_statement(&mut self) -> Result<Statement, ParseError> {
-        let token = self.next_token(); 
-        match token{
-            TokenType{token: Token::OpenParen, val: _, linenum: _} => {
+    fn parse_while_statement(&mut self, start: Span) -> Result<Statement, ParseError> {
+        let token = self.next_token(ParserDescriptor::Token(Token::OpenParen))?;
+        match token {
+            TokenType {
+                token: Token::OpenParen,
+                ..
+            } => {
                 let condition = self.parse_expression()?;
                 self.match_token(Token::CloseParen)?;
-                Ok(Statement::While(
-                    condition,
-                    Box::new(self.parse_statement()?),
+                let body = self.parse_statement()?;
+                Ok(Statement::new(
+                    StatementKind::While(condition, Box::new(body)),
+                    self.span_from(start),
                 ))
             }
             received => Err(ParseError::UnexpectedToken {
                 expected: ParserDescriptor::Token(Token::OpenParen),
                 filename: self.file.clone(),
                 received: received.token,
-                linenum: received.linenum
+                span: received.span,
             }),
         }
     }
 
-    fn parse_declare(&mut self, size: Size, t: &str) -> Result<Statement, ParseError> {
-        match (self.next_token(), self.peek()) {
-            (TokenType{token: Token::Identifier(name), val: _, linenum: _}, Some(Token::Assign)) => {
+    /// Parses the loop after `name:`. Only loops can be labelled.
+    fn parse_labeled_statement(&mut self, name: String, start: Span) -> Result<Statement, ParseError> {
+        match self.peek_tt() {
+            Some(TokenType {
+                token: Token::Keyword(Keyword::While),
+                ..
+            })
+            | Some(TokenType {
+                token: Token::Keyword(Keyword::For),
+                ..
+            }) => {
+                let body = self.parse_statement()?;
+                Ok(Statement::new(
+                    StatementKind::Labeled(name, Box::new(body)),
+                    self.span_from(start),
+                ))
+            }
+            Some(received) => Err(ParseError::UnexpectedToken {
+                expected: ParserDescriptor::Token(Token::Keyword(Keyword::While)),
+                filename: self.file.clone(),
+                received: received.token,
+                span: received.span,
+            }),
+            None => Err(self.eof(ParserDescriptor::Token(Token::Keyword(Keyword::While)))),
+        }
+    }
+
+    /// The label after `break` or `continue`, which has to be on the same line.
+    fn parse_loop_label(&mut self) -> Option<String> {
+        let line = self.prev_span.line;
+        match self.peek_tt() {
+            Some(TokenType {
+                token: Token::Identifier(name),
+                span,
+                ..
+            }) if span.line == line => {
+                self.drop(1);
+                Some(name)
+            }
+            _ => None,
+        }
+    }
+
+    /// Parses either `for (init; condition; step) body` or `for i in start..end body`.
+    fn parse_for_statement(&mut self, start: Span) -> Result<Statement, ParseError> {
+        let token = self.next_token(ParserDescriptor::Token(Token::OpenParen))?;
+        match token {
+            TokenType {
+                token: Token::OpenParen,
+                ..
+            } => {
+                let init = self.parse_for_clause(Token::Semicolon, Parser::parse_statement)?;
+                let condition = self.parse_for_clause(Token::Semicolon, Parser::parse_expression)?;
+                let step = self.parse_for_clause(Token::CloseParen, Parser::parse_expression)?;
+                let body = self.parse_statement()?;
+                Ok(Statement::new(
+                    StatementKind::For(init.map(Box::new), condition, step, Box::new(body)),
+                    self.span_from(start),
+                ))
+            }
+            TokenType {
+                token: Token::Identifier(name),
+                span,
+                ..
+            } => {
+                self.match_keyword(&Keyword::In)?;
+                let from = self.without_struct_literals(Parser::parse_expression)?;
+                self.match_token(Token::DotDot)?;
+                let to = self.without_struct_literals(Parser::parse_expression)?;
+                let body = self.parse_statement()?;
+                let var = Variable {
+                    name,
+                    size: Size::Int,
+                    t: Some(Type::Int),
+                    span,
+                };
+                Ok(Statement::new(
+                    StatementKind::ForRange(var, from, to, Box::new(body)),
+                    self.span_from(start),
+                ))
+            }
+            received => Err(ParseError::UnexpectedToken {
+                expected: ParserDescriptor::Token(Token::OpenParen),
+                filename: self.file.clone(),
+                received: received.token,
+                span: received.span,
+            }),
+        }
+    }
+
+    /// Parses with `f` where a `{` after a name has to start a block rather
+    /// than a struct literal, such as the end of `for i in 0..n {`.
+    fn without_struct_literals<T>(
+        &mut self,
+        f: fn(&mut Self) -> Result<T, ParseError>,
+    ) -> Result<T, ParseError> {
+        let saved = std::mem::replace(&mut self.struct_literals, false);
+        let result = f(self);
+        self.struct_literals = saved;
+        result
+    }
+
+    /// Parses one of the optional parts of a `for (;;)` header, up to and including `end`.
+    fn parse_for_clause<T>(
+        &mut self,
+        end: Token,
+        parse: fn(&mut Self) -> Result<T, ParseError>,
+    ) -> Result<Option<T>, ParseError> {
+        if self.peek().as_ref() == Some(&end) {
+            self.drop(1);
+            return Ok(None);
+        }
+        let clause = parse(self)?;
+        self.match_token(end)?;
+        Ok(Some(clause))
+    }
+
+    fn parse_declare(
+        &mut self,
+        size: Size,
+        t: Option<Type>,
+        start: Span,
+    ) -> Result<Statement, ParseError> {
+        let name = self.next_token(ParserDescriptor::AnyIdentifier)?;
+        // `let` may spell out the type it would otherwise infer: `let a: [int; 3] = ...`.
+        let t = match (&name.token, &t, self.peek()) {
+            (Token::Identifier(_), None, Some(Token::Colon)) => {
+                self.drop(1);
+                Some(self.parse_type()?)
+            }
+            _ => t,
+        };
+        match (name, self.peek()) {
+            (
+                TokenType {
+                    token: Token::Identifier(name),
+                    span,
+                    ..
+                },
+                Some(Token::Assign),
+            ) => {
                 self.drop(1);
                 let exp = self.parse_expression()?;
-                Ok(Statement::Declare(
-                    Variable {
-                        name,
-                        size,
-                        t: t.to_string(),
-                    },
-                    Some(exp),
+                Ok(Statement::new(
+                    StatementKind::Declare(
+                        Variable {
+                            name,
+                            size,
+                            t,
+                            span,
+                        },
+                        Some(exp),
+                    ),
+                    self.span_from(start),
                 ))
             }
-            other => Err(ParseError::UnassignedVariable{
-                linenum: other.0.linenum,
-                filename: self.file.clone()
+            other => Err(ParseError::UnassignedVariable {
+                span: other.0.span,
+                filename: self.file.clone(),
             }),
         }
     }
 
-    fn parse_assign_op(&mut self, bin_op: BinOp, name: &str) -> Result<Expression, ParseError> {
-        let exp = Expression::BinOp(
-            bin_op,
-            Box::new(Expression::Variable(name.to_string())),
-            Box::new(self.parse_expression()?),
+    fn parse_assign_op(
+        &mut self,
+        bin_op: BinOp,
+        name: &str,
+        name_span: Span,
+    ) -> Result<Expression, ParseError> {
+        let rhs = self.parse_expression()?;
+        let span = self.span_from(name_span);
+        let exp = Expression::new(
+            ExpressionKind::BinOp(
+                bin_op,
+                Box::new(Expression::new(
+                    ExpressionKind::Variable(name.to_string()),
+                    name_span,
+                )),
+                Box::new(rhs),
+            ),
+            span,
         );
-        Ok(Expression::Assign(name.to_string(), Box::new(exp)))
+        Ok(Expression::new(
+            ExpressionKind::Assign(name.to_string(), Box::new(exp)),
+            span,
+        ))
     }
 
     fn parse_inc_op(
@@ -409,26 +910,42 @@ impl Parser {
         bin_op: BinOp,
         name: &str,
         postfix: bool,
+        start: Span,
     ) -> Result<Expression, ParseError> {
+        let op_span = if postfix { self.peek_span() } else { start };
         self.next();
-        let exp = Expression::BinOp(
-            bin_op,
-            Box::new(Expression::Variable(name.to_string())),
-            Box::new(Expression::Int(1)),
+        let name_span = if postfix { start } else { self.prev_span };
+        let span = self.span_from(start);
+        let exp = Expression::new(
+            ExpressionKind::BinOp(
+                bin_op,
+                Box::new(Expression::new(
+                    ExpressionKind::Variable(name.to_string()),
+                    name_span,
+                )),
+                Box::new(Expression::new(ExpressionKind::Int(1), op_span)),
+            ),
+            span,
         );
         if postfix {
-            Ok(Expression::AssignPostfix(name.to_string(), Box::new(exp)))
+            Ok(Expression::new(
+                ExpressionKind::AssignPostfix(name.to_string(), Box::new(exp)),
+                span,
+            ))
         } else {
-            Ok(Expression::Assign(name.to_string(), Box::new(exp)))
+            Ok(Expression::new(
+                ExpressionKind::Assign(name.to_string(), Box::new(exp)),
+                span,
+            ))
         }
     }
 
     fn parse_expression(&mut self) -> Result<Expression, ParseError> {
-        self.parse_comma_expression()
+        self.nested(Parser::parse_comma_expression)
     }
 
     fn parse_comma_expression(&mut self) -> Result<Expression, ParseError> {
-        self.parse_gen_experssion(&[Token::Comma], &Parser::parse_assignment_expression)
+        self.parse_gen_experssion(&[Token::Comma], Parser::parse_assignment_expression)
     }
 
     fn parse_assignment_expression(&mut self) -> Result<Expression, ParseError> {
@@ -436,182 +953,214 @@ impl Parser {
             (
                 Some(TokenType {
                     token: Token::Identifier(name),
-                    val: _,
-                    linenum: line,
+                    span,
+                    ..
                 }),
                 Some(TokenType {
                     token: Token::Assign,
-                    val: _,
-                    linenum: line1,
+                    ..
                 }),
             ) => {
                 let exp = self.parse_expression()?;
-                Ok(Expression::Assign(name, Box::new(exp)))
+                Ok(Expression::new(
+                    ExpressionKind::Assign(name, Box::new(exp)),
+                    self.span_from(span),
+                ))
             }
             (
                 Some(TokenType {
                     token: Token::Identifier(name),
-                    val: _,
-                    linenum: line,
+                    span,
+                    ..
                 }),
                 Some(TokenType {
                     token: Token::AssignAdd,
-                    val: _,
-                    linenum: line1,
+                    ..
                 }),
-            ) => self.parse_assign_op(BinOp::Addition, &name),
+            ) => self.parse_assign_op(BinOp::Addition, &name, span),
             (
                 Some(TokenType {
                     token: Token::Identifier(name),
-                    val: _,
-                    linenum: line,
+                    span,
+                    ..
                 }),
                 Some(TokenType {
                     token: Token::AssignSub,
-                    val: _,
-                    linenum: line1,
+                    ..
                 }),
-            ) => self.parse_assign_op(BinOp::Subtraction, &name),
+            ) => self.parse_assign_op(BinOp::Subtraction, &name, span),
             (
                 Some(TokenType {
                     token: Token::Identifier(name),
-                    val: _,
-                    linenum: line,
+                    span,
+                    ..
                 }),
                 Some(TokenType {
                     token: Token::AssignMul,
-                    val: _,
-                    linenum: line1,
+                    ..
                 }),
-            ) => self.parse_assign_op(BinOp::Multiplication, &name),
+            ) => self.parse_assign_op(BinOp::Multiplication, &name, span),
             (
                 Some(TokenType {
                     token: Token::Identifier(name),
-                    val: _,
-                    linenum: line,
+                    span,
+                    ..
                 }),
                 Some(TokenType {
                     token: Token::AssignDiv,
-                    val: _,
-                    linenum: line1,
+                    ..
                 }),
-            ) => self.parse_assign_op(BinOp::Division, &name),
+            ) => self.parse_assign_op(BinOp::Division, &name, span),
             (
                 Some(TokenType {
                     token: Token::Identifier(name),
-                    val: _,
-                    linenum: line,
+                    span,
+                    ..
                 }),
                 Some(TokenType {
                     token: Token::AssignMod,
-                    val: _,
-                    linenum: line1,
+                    ..
                 }),
-            ) => self.parse_assign_op(BinOp::Modulus, &name),
+            ) => self.parse_assign_op(BinOp::Modulus, &name, span),
             (
                 Some(TokenType {
                     token: Token::Identifier(name),
-                    val: _,
-                    linenum: line,
+                    span,
+                    ..
                 }),
                 Some(TokenType {
                     token: Token::AssignBitLeft,
-                    val: _,
-                    linenum: line1,
+                    ..
                 }),
-            ) => self.parse_assign_op(BinOp::BitwiseLeft, &name),
+            ) => self.parse_assign_op(BinOp::BitwiseLeft, &name, span),
             (
                 Some(TokenType {
                     token: Token::Identifier(name),
-                    val: _,
-                    linenum: line,
+                    span,
+                    ..
                 }),
                 Some(TokenType {
                     token: Token::AssignBitRight,
-                    val: _,
-                    linenum: line1,
+                    ..
                 }),
-            ) => self.parse_assign_op(BinOp::BitwiseRight, &name),
+            ) => self.parse_assign_op(BinOp::BitwiseRight, &name, span),
             (
                 Some(TokenType {
                     token: Token::Identifier(name),
-                    val: _,
-                    linenum: line,
+                    span,
+                    ..
                 }),
                 Some(TokenType {
                     token: Token::AssignAnd,
-                    val: _,
-                    linenum: line1,
+                    ..
                 }),
-            ) => self.parse_assign_op(BinOp::BitwiseAnd, &name),
+            ) => self.parse_assign_op(BinOp::BitwiseAnd, &name, span),
             (
                 Some(TokenType {
                     token: Token::Identifier(name),
-                    val: _,
-                    linenum: line,
+                    span,
+                    ..
                 }),
                 Some(TokenType {
                     token: Token::AssignOr,
-                    val: _,
-                    linenum: line1,
+                    ..
                 }),
-            ) => self.parse_assign_op(BinOp::BitwiseOr, &name),
+            ) => self.parse_assign_op(BinOp::BitwiseOr, &name, span),
             (
                 Some(TokenType {
                     token: Token::Identifier(name),
-                    val: _,
-                    linenum: line,
+                    span,
+                    ..
                 }),
                 Some(TokenType {
                     token: Token::AssignXor,
-                    val: _,
-                    linenum: line1,
+                    ..
                 }),
-            ) => self.parse_assign_op(BinOp::BitwiseXor, &name),
+            ) => self.parse_assign_op(BinOp::BitwiseXor, &name, span),
             (a, b) => {
                 self.push(b);
                 self.push(a);
-                self.parse_conditional_expression()
+                let start = self.peek_span();
+                let exp = self.parse_conditional_expression()?;
+                match (exp.kind, self.peek()) {
+                    (ExpressionKind::Index(array, index), Some(Token::Assign))
+                        if Parser::is_place(&array) =>
+                    {
+                        self.drop(1);
+                        let value = self.parse_expression()?;
+                        Ok(Expression::new(
+                            ExpressionKind::AssignIndex(array, index, Box::new(value)),
+                            self.span_from(start),
+                        ))
+                    }
+                    (ExpressionKind::Field(value, field), Some(Token::Assign))
+                        if Parser::is_place(&value) =>
+                    {
+                        self.drop(1);
+                        let rhs = self.parse_expression()?;
+                        Ok(Expression::new(
+                            ExpressionKind::AssignField(value, field, Box::new(rhs)),
+                            self.span_from(start),
+                        ))
+                    }
+                    (kind, _) => Ok(Expression::new(kind, exp.span)),
+                }
+            }
+        }
+    }
+
+    /// Whether `exp` names storage that can be assigned to: a variable, or an
+    /// element or field of one.
+    fn is_place(exp: &Expression) -> bool {
+        match &exp.kind {
+            ExpressionKind::Variable(_) => true,
+            ExpressionKind::Index(value, _) | ExpressionKind::Field(value, _) => {
+                Parser::is_place(value)
             }
+            _ => false,
         }
     }
 
     fn parse_conditional_expression(&mut self) -> Result<Expression, ParseError> {
+        let start = self.peek_span();
         let mut term = self.parse_or_expression()?;
         while let Some(Token::Question) = self.peek() {
             self.next();
             let body = self.parse_expression()?;
             self.match_token(Token::Colon)?;
             let else_body = self.parse_expression()?;
-            term = Expression::Ternary(Box::new(term), Box::new(body), Box::new(else_body))
+            term = Expression::new(
+                ExpressionKind::Ternary(Box::new(term), Box::new(body), Box::new(else_body)),
+                self.span_from(start),
+            )
         }
         Ok(term)
     }
 
     fn parse_or_expression(&mut self) -> Result<Expression, ParseError> {
-        self.parse_gen_experssion(&[Token::Or], &Parser::parse_logical_and_expression)
+        self.parse_gen_experssion(&[Token::Or], Parser::parse_logical_and_expression)
     }
 
     fn parse_logical_and_expression(&mut self) -> Result<Expression, ParseError> {
-        self.parse_gen_experssion(&[Token::And], &Parser::parse_bitwise_or_expression)
+        self.parse_gen_experssion(&[Token::And], Parser::parse_bitwise_or_expression)
     }
 
     fn parse_bitwise_or_expression(&mut self) -> Result<Expression, ParseError> {
-        self.parse_gen_experssion(&[Token::BitwiseOr], &Parser::parse_bitwise_xor_expression)
+        self.parse_gen_experssion(&[Token::BitwiseOr], Parser::parse_bitwise_xor_expression)
     }
 
     fn parse_bitwise_xor_expression(&mut self) -> Result<Expression, ParseError> {
-        self.parse_gen_experssion(&[Token::BitwiseXor], &Parser::parse_bitwise_and_expression)
+        self.parse_gen_experssion(&[Token::BitwiseXor], Parser::parse_bitwise_and_expression)
     }
 
     fn parse_bitwise_and_expression(&mut self) -> Result<Expression, ParseError> {
-        self.parse_gen_experssion(&[Token::BitwiseAnd], &Parser::parse_equality_expression)
+        self.parse_gen_experssion(&[Token::BitwiseAnd], Parser::parse_equality_expression)
     }
 
     fn parse_equality_expression(&mut self) -> Result<Expression, ParseError> {
         self.parse_gen_experssion(
             &[Token::Equal, Token::NotEqual],
-            &Parser::parse_relational_expression,
+            Parser::parse_relational_expression,
         )
     }
 
@@ -623,139 +1172,241 @@ impl Parser {
                 Token::LessThanOrEqual,
                 Token::GreaterThanOrEqual,
             ],
-            &Parser::parse_bitshift_expression,
+            Parser::parse_bitshift_expression,
         )
     }
 
     fn parse_bitshift_expression(&mut self) -> Result<Expression, ParseError> {
         self.parse_gen_experssion(
             &[Token::BitwiseLeft, Token::BitwiseRight],
-            &Parser::parse_additive_expression,
+            Parser::parse_additive_expression,
         )
     }
 
     fn parse_additive_expression(&mut self) -> Result<Expression, ParseError> {
         self.parse_gen_experssion(
             &[Token::Negation, Token::Addition],
-            &Parser::parse_multiplicative_expression,
+            Parser::parse_multiplicative_expression,
         )
     }
 
     fn parse_multiplicative_expression(&mut self) -> Result<Expression, ParseError> {
         self.parse_gen_experssion(
             &[Token::Multiplication, Token::Division, Token::Modulus],
-            &Parser::parse_factor,
+            Parser::parse_factor,
         )
     }
 
+    /// Parses a primary expression followed by any number of `[index]`s,
+    /// `.field`s and `as type`s. An index has to start on the same line, so
+    /// that a line starting with an array literal is not taken as indexing the
+    /// line before. The operand of a prefix operator takes no `as`, which is
+    /// left to the factor around it, so `-x as float` converts `-x`.
     fn parse_factor(&mut self) -> Result<Expression, ParseError> {
-        match (self.next(), self.peek()) {
+        let start = self.peek_span();
+        let casts = !std::mem::replace(&mut self.prefix_operand, false);
+        let mut exp = self.parse_primary()?;
+        loop {
+            let kind = match self.peek_tt() {
+                Some(TokenType {
+                    token: Token::OpenSquareParen,
+                    span,
+                    ..
+                }) if span.line == self.prev_span.line => {
+                    self.next();
+                    let index = self.parse_expression()?;
+                    self.match_token(Token::CloseSquareParen)?;
+                    ExpressionKind::Index(Box::new(exp), Box::new(index))
+                }
+                Some(TokenType {
+                    token: Token::Dot, ..
+                }) => {
+                    self.next();
+                    ExpressionKind::Field(Box::new(exp), self.match_identifier()?)
+                }
+                Some(TokenType {
+                    token: Token::Keyword(Keyword::As),
+                    ..
+                }) if casts => {
+                    self.next();
+                    ExpressionKind::Cast(Box::new(exp), self.parse_type()?)
+                }
+                _ => break,
+            };
+            exp = Expression::new(kind, self.span_from(start));
+        }
+        Ok(exp)
+    }
+
+    /// Parses `match value { pattern => result, ... }` after the `match`.
+    fn parse_match(&mut self) -> Result<ExpressionKind, ParseError> {
+        let value = self.without_struct_literals(Parser::parse_expression)?;
+        self.match_token(Token::OpenBrace)?;
+        let mut arms = Vec::new();
+        while self.peek_token(Token::CloseBrace).is_err() {
+            arms.push(self.parse_match_arm()?);
+            if let Some(Token::Comma) = self.peek() {
+                self.next();
+            }
+        }
+        self.match_token(Token::CloseBrace)?;
+        Ok(ExpressionKind::Match(Box::new(value), arms))
+    }
+
+    /// Parses the operand of `-`, `!` or `~`. This is kept out of
+    /// `parse_primary` to keep its stack frame, which every level of nesting
+    /// pays for, small.
+    fn parse_prefix_operator(&mut self, op: TokenType) -> Result<ExpressionKind, ParseError> {
+        self.prefix_operand = true;
+        let factor = self.nested(Parser::parse_factor)?;
+        match UnOp::try_from(op.token) {
+            Ok(unop) => Ok(ExpressionKind::UnOp(unop, Box::new(factor))),
+            Err(received) => Err(ParseError::InvalidOperator {
+                received,
+                span: op.span,
+                filename: self.file.clone(),
+            }),
+        }
+    }
+
+    fn parse_primary(&mut self) -> Result<Expression, ParseError> {
+        let start = self.peek_span();
+        let kind = match (self.next(), self.peek()) {
             (
                 Some(TokenType {
                     token: Token::Literal(Value::Char(c)),
-                    val: _,
-                    linenum: line,
+                    ..
                 }),
                 _,
-            ) => Ok(Expression::Char(c.chars().as_str().parse().unwrap())),
+            ) => ExpressionKind::Char(c),
             (
                 Some(TokenType {
                     token: Token::Keyword(Keyword::True),
-                    val: _,
-                    linenum: line,
+                    ..
                 }),
                 _,
-            ) => Ok(Expression::Bool(true)),
+            ) => ExpressionKind::Bool(true),
             (
                 Some(TokenType {
                     token: Token::Keyword(Keyword::False),
-                    val: _,
-                    linenum: line,
+                    ..
                 }),
                 _,
-            ) => Ok(Expression::Bool(false)),
+            ) => ExpressionKind::Bool(false),
             (
                 Some(TokenType {
                     token: Token::Literal(Value::Int(num)),
-                    val: _,
-                    linenum: line,
+                    ..
+                }),
+                _,
+            ) => ExpressionKind::Int(num),
+            (
+                Some(TokenType {
+                    token: Token::Literal(Value::Float(num)),
+                    ..
                 }),
                 _,
-            ) => Ok(Expression::Int(num)),
+            ) => ExpressionKind::Float(num),
             (
                 Some(TokenType {
                     token: Token::Literal(Value::MLStr(num)),
-                    val: _,
-                    linenum: line,
+                    ..
                 }),
                 _,
-            ) => Ok(Expression::MLStr(num)),
+            ) => ExpressionKind::MLStr(num),
             (
                 Some(TokenType {
                     token: Token::Identifier(name),
-                    val: _,
-                    linenum: line,
+                    ..
                 }),
                 Some(Token::Increment),
-            ) => self.parse_inc_op(BinOp::Addition, &name, true),
+            ) => return self.parse_inc_op(BinOp::Addition, &name, true, start),
             (
                 Some(TokenType {
                     token: Token::Identifier(name),
-                    val: _,
-                    linenum: line,
+                    ..
                 }),
                 Some(Token::Decrement),
-            ) => self.parse_inc_op(BinOp::Subtraction, &name, true),
+            ) => return self.parse_inc_op(BinOp::Subtraction, &name, true, start),
             (
                 Some(TokenType {
                     token: Token::Increment,
-                    val: _,
-                    linenum: line,
+                    ..
                 }),
                 Some(Token::Identifier(name)),
-            ) => self.parse_inc_op(BinOp::Addition, &name, false),
+            ) => return self.parse_inc_op(BinOp::Addition, &name, false, start),
             (
                 Some(TokenType {
                     token: Token::Decrement,
-                    val: _,
-                    linenum: line,
+                    ..
                 }),
                 Some(Token::Identifier(name)),
-            ) => self.parse_inc_op(BinOp::Subtraction, &name, false),
+            ) => return self.parse_inc_op(BinOp::Subtraction, &name, false, start),
             (
                 Some(TokenType {
                     token: Token::OpenParen,
-                    val: _,
-                    linenum: line,
+                    ..
                 }),
                 _,
             ) => {
                 let exp = self.parse_expression()?;
                 self.match_token(Token::CloseParen)?;
-                Ok(exp)
+                exp.kind
             }
+            (
+                Some(TokenType {
+                    token: Token::OpenSquareParen,
+                    ..
+                }),
+                _,
+            ) => ExpressionKind::Array(self.parse_array_elements()?),
             (
                 Some(TokenType {
                     token: Token::Identifier(name),
-                    val: _,
-                    linenum: line,
+                    ..
                 }),
                 _,
-            ) => match self.peek() {
-                Some(Token::OpenParen) => Ok(Expression::FunctionCall(
-                    name,
-                    self.parse_function_arguments()?,
-                )),
-                _ => Ok(Expression::Variable(name)),
+            ) => match self.peek_tt() {
+                Some(TokenType {
+                    token: Token::OpenParen,
+                    ..
+                }) => ExpressionKind::FunctionCall(name, self.parse_function_arguments()?),
+                Some(TokenType {
+                    token: Token::OpenBrace,
+                    span,
+                    ..
+                }) if self.struct_literals && span.line == self.prev_span.line => {
+                    self.next();
+                    ExpressionKind::StructLiteral(name, self.parse_struct_literal_fields()?)
+                }
+                Some(TokenType {
+                    token: Token::DoubleColon,
+                    ..
+                }) => {
+                    self.next();
+                    let variant = self.match_identifier()?;
+                    let arguments = match self.peek() {
+                        Some(Token::OpenParen) => self.parse_function_arguments()?,
+                        _ => Vec::new(),
+                    };
+                    ExpressionKind::Variant(name, variant, arguments)
+                }
+                _ => ExpressionKind::Variable(name),
             },
+            (
+                Some(TokenType {
+                    token: Token::Keyword(Keyword::Match),
+                    ..
+                }),
+                _,
+            ) => self.parse_match()?,
             (
                 Some(
                     op
                     @
                     TokenType {
                         token: Token::Negation,
-                        val: _,
-                        linenum: _,
+                        ..
                     },
                 ),
                 _,
@@ -766,8 +1417,7 @@ impl Parser {
                     @
                     TokenType {
                         token: Token::LogicalNeg,
-                        val: _,
-                        linenum: _,
+                        ..
                     },
                 ),
                 _,
@@ -778,45 +1428,165 @@ impl Parser {
                     @
                     TokenType {
                         token: Token::BitComp,
-                        val: _,
-                        linenum: _,
+                        ..
                     },
                 ),
                 _,
-            ) => {
-                let factor = self.parse_factor()?;
-                Ok(Expression::UnOp(op.token.into(), Box::new(factor)))
-            }
+            ) => self.parse_prefix_operator(op)?,
             (
                 Some(TokenType {
                     token: Token::BitwiseAnd,
-                    val: _,
-                    linenum: line,
+                    ..
                 }),
                 _,
             ) => match self.next() {
                 Some(TokenType {
                     token: Token::Identifier(name),
-                    val: _,
-                    linenum: line,
-                }) => Ok(Expression::VariableRef(name)),
-                Some(received) => Err(ParseError::UnexpectedToken {
-                    expected: ParserDescriptor::AnyVariable,
-                    received: received.token,
-                    filename: self.file.clone(),
-                    linenum: received.linenum
-                }),
-                _ => Err(ParseError::Unknown),
+                    ..
+                }) => ExpressionKind::VariableRef(name),
+                Some(received) => {
+                    return Err(ParseError::UnexpectedToken {
+                        expected: ParserDescriptor::AnyVariable,
+                        received: received.token,
+                        filename: self.file.clone(),
+                        span: received.span,
+                    })
+                }
+                None => return Err(self.eof(ParserDescriptor::AnyVariable)),
             },
-            other => Err(ParseError::UnassignedVariable{
-                linenum: other.0.unwrap_or_else(||{
-                    eprintln!("{} {}", "Reached End of line: \n".red().bold(),
-                        "NOTE: If you want help for this error consider opening an issue here https://github.com/zetacli/zetac/issues".blue().bold());
-                    process::exit(1);
-                }).linenum,
-                filename: self.file.clone(),
-            }),
+            (None, _) => return Err(self.eof(ParserDescriptor::AnyExpression)),
+            (Some(other), _) => {
+                // Leave the offending token in place so that a stray `}` still closes its block.
+                let span = other.span;
+                self.push(Some(other));
+                return Err(ParseError::UnassignedVariable {
+                    span,
+                    filename: self.file.clone(),
+                });
+            }
+        };
+        Ok(Expression::new(kind, self.span_from(start)))
+    }
+
+    /// Parses `pattern => value`.
+    fn parse_match_arm(&mut self) -> Result<MatchArm, ParseError> {
+        let start = self.peek_span();
+        let pattern = self.parse_pattern()?;
+        self.match_token(Token::AsignFunc)?;
+        let value = self.parse_assignment_expression()?;
+        Ok(MatchArm {
+            pattern,
+            value,
+            span: self.span_from(start),
+        })
+    }
+
+    fn parse_pattern(&mut self) -> Result<Pattern, ParseError> {
+        let start = self.peek_span();
+        let kind = match self.next_token(ParserDescriptor::AnyPattern)? {
+            TokenType {
+                token: Token::Underscore,
+                ..
+            } => PatternKind::Wildcard,
+            TokenType {
+                token: Token::Literal(Value::Int(i)),
+                ..
+            } => PatternKind::Int(i),
+            TokenType {
+                token: Token::Literal(Value::Char(s)),
+                ..
+            }
+            | TokenType {
+                token: Token::Literal(Value::MLStr(s)),
+                ..
+            } => PatternKind::Str(s),
+            TokenType {
+                token: Token::Keyword(Keyword::True),
+                ..
+            } => PatternKind::Bool(true),
+            TokenType {
+                token: Token::Keyword(Keyword::False),
+                ..
+            } => PatternKind::Bool(false),
+            TokenType {
+                token: Token::Identifier(name),
+                ..
+            } => {
+                self.match_token(Token::DoubleColon)?;
+                let variant = self.match_identifier()?;
+                let mut bindings = Vec::new();
+                if let Some(Token::OpenParen) = self.peek() {
+                    self.next();
+                    while self.peek_token(Token::CloseParen).is_err() {
+                        bindings.push(self.parse_binding()?);
+                        if let Some(Token::Comma) = self.peek() {
+                            self.next();
+                        }
+                    }
+                    self.match_token(Token::CloseParen)?;
+                }
+                PatternKind::Variant(name, variant, bindings)
+            }
+            received => {
+                self.push(Some(received.clone()));
+                return Err(ParseError::UnexpectedToken {
+                    expected: ParserDescriptor::AnyPattern,
+                    filename: self.file.clone(),
+                    received: received.token,
+                    span: received.span,
+                });
+            }
+        };
+        Ok(Pattern {
+            kind,
+            span: self.span_from(start),
+        })
+    }
+
+    /// Parses a name that a pattern binds a payload value to, or `_` to ignore it.
+    fn parse_binding(&mut self) -> Result<Option<Variable>, ParseError> {
+        let start = self.peek_span();
+        if let Some(Token::Underscore) = self.peek() {
+            self.next();
+            return Ok(None);
+        }
+        let name = self.match_identifier()?;
+        Ok(Some(Variable {
+            name,
+            t: None,
+            size: Size::Int,
+            span: self.span_from(start),
+        }))
+    }
+
+    /// Parses the `field: value`s of a struct literal after its `{`, up to and including the `}`.
+    fn parse_struct_literal_fields(&mut self) -> Result<Vec<(String, Expression)>, ParseError> {
+        let mut fields = Vec::new();
+        while self.peek() != Some(Token::CloseBrace) {
+            let name = self.match_identifier()?;
+            self.match_token(Token::Colon)?;
+            fields.push((name, self.parse_assignment_expression()?));
+            match self.peek() {
+                Some(Token::Comma) => self.drop(1),
+                _ => break,
+            }
+        }
+        self.match_token(Token::CloseBrace)?;
+        Ok(fields)
+    }
+
+    /// Parses the elements of an array literal after its `[`, up to and including the `]`.
+    fn parse_array_elements(&mut self) -> Result<Vec<Expression>, ParseError> {
+        let mut elements = Vec::new();
+        while self.peek() != Some(Token::CloseSquareParen) {
+            elements.push(self.parse_assignment_expression()?);
+            match self.peek() {
+                Some(Token::Comma) => self.drop(1),
+                _ => break,
+            }
         }
+        self.match_token(Token::CloseSquareParen)?;
+        Ok(elements)
     }
 
     fn parse_function_arguments(&mut self) -> Result<Vec<Expression>, ParseError> {
@@ -836,48 +1606,57 @@ impl Parser {
     fn parse_arguments(&mut self, fnname: &str) -> Result<Vec<Variable>, ParseError> {
         let mut arguments = Vec::new();
         while self.peek_token(Token::CloseParen).is_err() {
+            let start = self.peek_span();
             let name = self.match_identifier()?;
             self.match_token(Token::Colon)?;
-            let size = match self.peek_tt() {
-                Some(TokenType {
+            let (size, t) = match self.next_token(ParserDescriptor::AnyType)? {
+                open @ TokenType {
+                    token: Token::OpenSquareParen,
+                    ..
+                }
+                | open @ TokenType {
+                    token: Token::Identifier(_),
+                    ..
+                } => {
+                    self.push(Some(open));
+                    (Size::Int, self.parse_type()?)
+                }
+                TokenType {
                     token: Token::Keyword(Keyword::Int),
-                    val: _,
-                    linenum: _line,
-                }) => Ok(Size::Int),
-                Some(TokenType {
+                    ..
+                } => (Size::Int, Type::Int),
+                TokenType {
+                    token: Token::Keyword(Keyword::Float),
+                    ..
+                } => (Size::Int, Type::Float),
+                TokenType {
                     token: Token::Keyword(Keyword::String),
-                    val: _,
-                    linenum: _line,
-                }) => Ok(Size::Byte),
-                Some(TokenType {
+                    ..
+                } => (Size::Byte, Type::Str),
+                TokenType {
                     token: Token::Keyword(Keyword::MLstr),
-                    val: _,
-                    linenum: _line,
-                }) => Ok(Size::Byte),
-                Some(TokenType {
+                    ..
+                } => (Size::Byte, Type::Mlstr),
+                TokenType {
                     token: Token::Keyword(Keyword::Bool),
-                    val: _,
-                    linenum: _line,
-                }) => Ok(Size::Byte),
-                other => Err(ParseError::UnexpectedType {
-                    expected: "Int(int) String(str) MLStr(mlstr) or Bool(bool)".into(),
-                    received: other.clone().unwrap().token,
-                    filename: self.file.clone(),
-                    fnname: fnname.into(),
-                    linenum: other.unwrap().linenum
-                }),
-            }?;
-            let t = match self.next_token() {
-                TokenType{token: Token::Keyword(Keyword::Int), val: _, linenum: _} => "int",
-                TokenType{token: Token::Keyword(Keyword::String), val: _, linenum: _} => "str",
-                TokenType{token: Token::Keyword(Keyword::MLstr), val: _, linenum: _} => "mlstr",
-                TokenType{token: Token::Keyword(Keyword::Bool), val: _, linenum: _} => "bool",
-                other => panic!("Function {} has wrong argument type: Got {:#?}", name, other),
+                    ..
+                } => (Size::Byte, Type::Bool),
+                other => {
+                    self.push(Some(other.clone()));
+                    return Err(ParseError::UnexpectedType {
+                        expected: "Int(int) Float(float) String(str) MLStr(mlstr) or Bool(bool)".into(),
+                        received: other.token,
+                        filename: self.file.clone(),
+                        fnname: fnname.into(),
+                        span: other.span,
+                    });
+                }
             };
             arguments.push(Variable {
                 name,
                 size,
-                t: t.to_string(),
+                t: Some(t),
+                span: self.span_from(start),
             });
             if let Some(Token::Comma) = self.peek() {
                 self.next();
@@ -894,14 +1673,26 @@ impl Parser {
     where
         F: Fn(&mut Parser) -> Result<Expression, ParseError>,
     {
+        let start = self.peek_span();
         let mut term = next(self)?;
 
         loop {
-            match self.peek() {
-                Some(ref token) if matching.contains(token) => {
-                    let op = self.next().unwrap().token.into();
-                    let next_term = next(self);
-                    term = Expression::BinOp(op, Box::new(term), Box::new(next_term?))
+            match self.peek_tt() {
+                Some(token) if matching.contains(&token.token) => {
+                    self.next();
+                    let span = token.span;
+                    let op = BinOp::try_from(token.token).map_err(|received| {
+                        ParseError::InvalidOperator {
+                            received,
+                            span,
+                            filename: self.file.clone(),
+                        }
+                    })?;
+                    let next_term = next(self)?;
+                    term = Expression::new(
+                        ExpressionKind::BinOp(op, Box::new(term), Box::new(next_term)),
+                        self.span_from(start),
+                    )
                 }
                 _ => break,
             }
@@ -909,3 +1700,302 @@ impl Parser {
         Ok(term)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::tokenize;
+
+    #[test]
+    fn ast_nodes_carry_spans() {
+        let src = "fn main(): int {\n    return 1 + 22\n}";
+        let mut parser = Parser::new(tokenize(src, "").unwrap(), "".into());
+        let (program, errors) = parser.parse();
+        assert!(errors.is_empty());
+        let function = &program.func[0];
+        assert_eq!((function.span.start, function.span.end), (0, src.len()));
+
+        let statement = &function.statements[0];
+        assert_eq!(&src[statement.span.start..statement.span.end], "return 1 + 22");
+        assert_eq!((statement.span.line, statement.span.col), (2, 5));
+        match &statement.kind {
+            StatementKind::Return(exp) => {
+                assert_eq!(&src[exp.span.start..exp.span.end], "1 + 22");
+                assert_eq!((exp.span.line, exp.span.col), (2, 12));
+            }
+            other => panic!("expected a return statement, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parses_both_kinds_of_for_loop() {
+        let src = "fn f(n: int) {\n    for (int i = 0; i < n; i++) { }\n    for (;;) { }\n    for i in 0..n + 1 { }\n}";
+        let (program, errors) = parse_src(src).unwrap();
+        assert!(errors.is_empty(), "{:?}", errors);
+        let statements = &program.func[0].statements;
+        match &statements[0].kind {
+            StatementKind::For(Some(init), Some(_), Some(step), _) => {
+                assert!(matches!(init.kind, StatementKind::Declare(..)));
+                assert!(matches!(step.kind, ExpressionKind::AssignPostfix(..)));
+            }
+            other => panic!("expected a for loop, got {:?}", other),
+        }
+        assert!(matches!(
+            statements[1].kind,
+            StatementKind::For(None, None, None, _)
+        ));
+        match &statements[2].kind {
+            StatementKind::ForRange(var, from, to, _) => {
+                assert_eq!((var.name.as_str(), &var.t), ("i", &Some(Type::Int)));
+                assert_eq!(from.kind, ExpressionKind::Int(0));
+                assert!(matches!(to.kind, ExpressionKind::BinOp(BinOp::Addition, ..)));
+            }
+            other => panic!("expected a range loop, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parses_labels_break_and_continue() {
+        let src = "fn f() {\n    outer: while (true) {\n        for i in 0..3 {\n            continue outer\n        }\n        break\n        f()\n    }\n}";
+        let (program, errors) = parse_src(src).unwrap();
+        assert!(errors.is_empty(), "{:?}", errors);
+        let body = match &program.func[0].statements[0].kind {
+            StatementKind::Labeled(label, inner) => match &inner.kind {
+                StatementKind::While(_, body) => {
+                    assert_eq!(label, "outer");
+                    body
+                }
+                other => panic!("expected a while loop, got {:?}", other),
+            },
+            other => panic!("expected a labelled loop, got {:?}", other),
+        };
+        let statements = match &body.kind {
+            StatementKind::Compound(statements) => statements,
+            other => panic!("expected a block, got {:?}", other),
+        };
+        match &statements[0].kind {
+            StatementKind::ForRange(_, _, _, body) => match &body.kind {
+                StatementKind::Compound(inner) => {
+                    assert_eq!(inner[0].kind, StatementKind::Continue(Some("outer".into())))
+                }
+                other => panic!("expected a block, got {:?}", other),
+            },
+            other => panic!("expected a range loop, got {:?}", other),
+        }
+        // A label has to be on the same line as the `break`.
+        assert_eq!(statements[1].kind, StatementKind::Break(None));
+        assert!(matches!(statements[2].kind, StatementKind::Exp(_)));
+
+        let (_, errors) = parse_src("fn f() {\n    outer: f()\n}").unwrap();
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn parses_arrays_and_indexing() {
+        let src = "fn f(grid: [[int; 2]; 3]): [int; 2] {\n    let a: [int; 3] = [1, 2, 3]\n    grid[a[0]][1] = a[2]\n    return grid[0]\n}";
+        let (program, errors) = parse_src(src).unwrap();
+        assert!(errors.is_empty(), "{:?}", errors);
+        let function = &program.func[0];
+        let row = Type::Array(Box::new(Type::Int), 2);
+        assert_eq!(function.return_type, row);
+        assert_eq!(function.arguments[0].t, Some(Type::Array(Box::new(row), 3)));
+        match &function.statements[0].kind {
+            StatementKind::Declare(var, Some(value)) => {
+                assert_eq!(var.t, Some(Type::Array(Box::new(Type::Int), 3)));
+                assert!(matches!(&value.kind, ExpressionKind::Array(elements) if elements.len() == 3));
+            }
+            other => panic!("expected a declaration, got {:?}", other),
+        }
+        match &function.statements[1].kind {
+            StatementKind::Exp(Expression {
+                kind: ExpressionKind::AssignIndex(array, index, value),
+                ..
+            }) => {
+                assert!(matches!(array.kind, ExpressionKind::Index(..)));
+                assert_eq!(index.kind, ExpressionKind::Int(1));
+                assert!(matches!(value.kind, ExpressionKind::Index(..)));
+            }
+            other => panic!("expected an index assignment, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parses_pub_items_and_typed_consts() {
+        let src = "pub const limit: int = 2 * 8\nlet name = \"z\"\npub struct P { x: int }\npub fn f() {\n}\nfn g() {\n}\n1 + 2";
+        let (program, errors) = parse_src(src).unwrap();
+        let globals: Vec<_> = program
+            .globals
+            .iter()
+            .map(|g| (g.is_pub, g.is_const, g.var.name.as_str(), g.var.t.clone()))
+            .collect();
+        assert_eq!(
+            globals,
+            vec![(true, true, "limit", Some(Type::Int)), (false, false, "name", None)]
+        );
+        assert!(program.structs[0].is_pub);
+        assert!(program.func[0].is_pub && !program.func[1].is_pub);
+        match &errors[..] {
+            [ParseError::UnexpectedToken {
+                expected: ParserDescriptor::AnyItem,
+                ..
+            }] => {}
+            other => panic!("expected one error for the stray expression, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parses_structs_and_field_access() {
+        let src = "struct Point { x: int, y: int }\nfn f(p: Point): Point {\n    let q = Point { x: p.y, y: 1 }\n    q.x = 2\n    for i in 0..q.x {\n    }\n    return q\n}";
+        let (program, errors) = parse_src(src).unwrap();
+        assert!(errors.is_empty(), "{:?}", errors);
+        let point = &program.structs[0];
+        assert_eq!(point.name, "Point");
+        let fields: Vec<&str> = point.fields.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(fields, vec!["x", "y"]);
+        let function = &program.func[0];
+        assert_eq!(function.return_type, Type::Named("Point".into()));
+        assert_eq!(function.arguments[0].t, Some(Type::Named("Point".into())));
+        match &function.statements[0].kind {
+            StatementKind::Declare(_, Some(value)) => match &value.kind {
+                ExpressionKind::StructLiteral(name, fields) => {
+                    assert_eq!(name, "Point");
+                    assert!(matches!(&fields[0].1.kind, ExpressionKind::Field(_, field) if field == "y"));
+                }
+                other => panic!("expected a struct literal, got {:?}", other),
+            },
+            other => panic!("expected a declaration, got {:?}", other),
+        }
+        assert!(matches!(
+            &function.statements[1].kind,
+            StatementKind::Exp(Expression {
+                kind: ExpressionKind::AssignField(_, field, _),
+                ..
+            }) if field == "x"
+        ));
+        assert!(matches!(&function.statements[2].kind, StatementKind::ForRange(..)));
+    }
+
+    #[test]
+    fn parses_enums_and_match() {
+        let src = "enum Command {\n    Help,\n    Add(int, int)\n}\nfn run(c: Command): int {\n    return match c {\n        Command::Add(a, _) => a\n        Command::Help => 0,\n        _ => 1\n    }\n}\nfn f(): int {\n    return run(Command::Add(1, 2))\n}";
+        let (program, errors) = parse_src(src).unwrap();
+        assert!(errors.is_empty(), "{:?}", errors);
+        let command = &program.enums[0];
+        assert_eq!(command.variants[0].fields, vec![]);
+        assert_eq!(command.variants[1].fields, vec![Type::Int, Type::Int]);
+        match &program.func[0].statements[0].kind {
+            StatementKind::Return(Expression {
+                kind: ExpressionKind::Match(value, arms),
+                ..
+            }) => {
+                assert_eq!(value.kind, ExpressionKind::Variable("c".into()));
+                let patterns: Vec<&PatternKind> = arms.iter().map(|a| &a.pattern.kind).collect();
+                match &patterns[..] {
+                    [PatternKind::Variant(_, add, bindings), PatternKind::Variant(..), PatternKind::Wildcard] => {
+                        assert_eq!(add, "Add");
+                        assert!(matches!(&bindings[..], [Some(a), None] if a.name == "a"));
+                    }
+                    other => panic!("unexpected patterns {:?}", other),
+                }
+            }
+            other => panic!("expected a match, got {:?}", other),
+        }
+        match &program.func[1].statements[0].kind {
+            StatementKind::Return(Expression {
+                kind: ExpressionKind::FunctionCall(_, arguments),
+                ..
+            }) => assert!(matches!(
+                &arguments[0].kind,
+                ExpressionKind::Variant(e, v, values) if e == "Command" && v == "Add" && values.len() == 2
+            )),
+            other => panic!("expected a call, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parses_casts_tighter_than_multiplication() {
+        let (program, errors) = parse_src("fn f(a: int, b: int): float {\n    return -a * b as float\n}").unwrap();
+        assert!(errors.is_empty(), "{:?}", errors);
+        match &program.func[0].statements[0].kind {
+            StatementKind::Return(Expression {
+                kind: ExpressionKind::BinOp(BinOp::Multiplication, lhs, rhs),
+                ..
+            }) => {
+                assert!(matches!(lhs.kind, ExpressionKind::UnOp(UnOp::Negation, _)));
+                assert!(matches!(&rhs.kind, ExpressionKind::Cast(_, Type::Float)));
+            }
+            other => panic!("expected a multiplication, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn recovers_and_reports_every_error() {
+        let src = "fn a(): int {\n    int x = )\n    int y = 2\n    return y\n}\nfn b() {\n    while (1 { }\n    int z = 3\n}\nfn c() {\n    return 1\n}";
+        let mut parser = Parser::new(tokenize(src, "").unwrap(), "".into());
+        let (program, errors) = parser.parse();
+        let lines: Vec<usize> = errors
+            .iter()
+            .map(|e| match e {
+                ParseError::UnassignedVariable { span, .. }
+                | ParseError::UnexpectedToken { span, .. } => span.line,
+                other => panic!("unexpected error {:?}", other),
+            })
+            .collect();
+        assert_eq!(lines, vec![2, 7]);
+        let names: Vec<&str> = program.func.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, vec!["a", "b", "c"]);
+        assert_eq!(program.func[0].statements.len(), 2);
+        assert_eq!(program.func[1].statements.len(), 1);
+    }
+
+    fn parse_src(src: &str) -> Option<ParsingResult> {
+        let tokens = tokenize(src, "").ok()?;
+        Some(Parser::new(tokens, "".into()).parse())
+    }
+
+    #[test]
+    fn truncated_and_mutated_input_never_panics() {
+        let src = "use std::io\nconst X = \"x\"\nfn f(a: int, b: str): int {\n    if (a <= 2) { return -a } else { a += 1 }\n    while (a != 0) { a-- }\n    return f(a, b) * ~(a % 3)\n}";
+        let alphabet: Vec<char> = "(){}[],:;=+-*/%!~<>&|^ \nafnletint0x1\"`".chars().collect();
+        for end in (0..=src.len()).filter(|i| src.is_char_boundary(*i)) {
+            parse_src(&src[..end]);
+        }
+        // A fixed linear congruential generator keeps the mutations reproducible.
+        let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut rand = move |n: usize| {
+            seed = seed
+                .wrapping_mul(6_364_136_223_846_793_005)
+                .wrapping_add(1_442_695_040_888_963_407);
+            (seed >> 33) as usize % n
+        };
+        for _ in 0..2000 {
+            let mut chars: Vec<char> = src.chars().collect();
+            for _ in 0..=rand(4) {
+                let at = rand(chars.len());
+                match rand(3) {
+                    0 => chars[at] = alphabet[rand(alphabet.len())],
+                    1 => chars.insert(at, alphabet[rand(alphabet.len())]),
+                    _ => {
+                        chars.remove(at);
+                    }
+                }
+            }
+            parse_src(&chars.into_iter().collect::<String>());
+        }
+    }
+
+    #[test]
+    fn deep_nesting_is_an_error() {
+        let src = format!("fn main(): int {{\n    return {}1\n}}", "(".repeat(10_000));
+        let (_, errors) = parse_src(&src).unwrap();
+        assert!(errors
+            .iter()
+            .any(|e| matches!(e, ParseError::NestingTooDeep { .. })));
+
+        let src = format!("fn main() {}", "{".repeat(10_000));
+        let (_, errors) = parse_src(&src).unwrap();
+        assert!(errors
+            .iter()
+            .any(|e| matches!(e, ParseError::NestingTooDeep { .. })));
+    }
+}
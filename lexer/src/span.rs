@@ -0,0 +1,132 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::ops::Range;
+
+/// A region of source code. `start` and `end` are byte offsets into the file
+/// identified by `file_id`, while `line` and `col` (both 1-based) describe
+/// where `start` sits so that errors can be reported without the source text.
+#[derive(Debug, Default, Eq, PartialEq, Clone, Copy, Hash, Serialize, Deserialize)]
+pub struct Span {
+    pub file_id: usize,
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub col: usize,
+}
+
+impl Span {
+    pub fn new(file_id: usize, start: usize, end: usize, line: usize, col: usize) -> Self {
+        Self {
+            file_id,
+            start,
+            end,
+            line,
+            col,
+        }
+    }
+
+    /// Returns a span covering everything from the start of `self` to the end of `other`.
+    pub fn to(&self, other: Span) -> Span {
+        Span {
+            end: other.end.max(self.end),
+            ..*self
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.end.saturating_sub(self.start)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.col)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SourceFile {
+    pub name: String,
+    pub src: String,
+    line_starts: Vec<usize>,
+}
+
+impl SourceFile {
+    pub fn new(name: String, src: String) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(src.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Self {
+            name,
+            src,
+            line_starts,
+        }
+    }
+
+    /// Returns the 1-based line and column of a byte offset.
+    pub fn line_col(&self, offset: usize) -> (usize, usize) {
+        let line = match self.line_starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(next) => next - 1,
+        };
+        let start = self.line_starts[line];
+        let col = self
+            .src
+            .get(start..offset.min(self.src.len()))
+            .map_or(0, |s| s.chars().count())
+            + 1;
+        (line + 1, col)
+    }
+
+    /// Returns the byte range of a 1-based line, without its trailing newline.
+    pub fn line_range(&self, line: usize) -> Option<Range<usize>> {
+        let start = *self.line_starts.get(line.checked_sub(1)?)?;
+        let end = self
+            .line_starts
+            .get(line)
+            .copied()
+            .unwrap_or(self.src.len());
+        let text = self.src[start..end].trim_end_matches(&['\n', '\r'][..]);
+        Some(start..start + text.len())
+    }
+
+    /// Returns the text of a 1-based line, without its trailing newline.
+    pub fn line(&self, line: usize) -> Option<&str> {
+        self.line_range(line).map(|range| &self.src[range])
+    }
+}
+
+/// Owns every source file taking part in a compilation. The index of a file
+/// in the map is the `file_id` stored in its spans.
+#[derive(Debug, Default, Clone)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+impl SourceMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_file(&mut self, name: &str, src: &str) -> usize {
+        self.files
+            .push(SourceFile::new(name.to_string(), src.to_string()));
+        self.files.len() - 1
+    }
+
+    pub fn get(&self, file_id: usize) -> Option<&SourceFile> {
+        self.files.get(file_id)
+    }
+
+    pub fn name(&self, file_id: usize) -> &str {
+        self.get(file_id).map(|f| f.name.as_str()).unwrap_or("<unknown>")
+    }
+
+    pub fn files(&self) -> &[SourceFile] {
+        &self.files
+    }
+}
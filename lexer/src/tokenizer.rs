@@ -1,417 +1,200 @@
 use crate::{errors::TokenizeError, *};
 
 pub fn tokenize(contents: &str, fname: &str) -> Result<Vec<TokenType>, TokenizeError> {
-    let mut tokens = TokenParser::new(contents);
-    let mut linenum = 1;
+    tokenize_file(contents, fname, 0)
+}
+
+/// Tokenizes `contents`, tagging every token's span with `file_id` so that it
+/// can later be looked up in a [`SourceMap`].
+pub fn tokenize_file(
+    contents: &str,
+    fname: &str,
+    file_id: usize,
+) -> Result<Vec<TokenType>, TokenizeError> {
+    let mut tokens = TokenParser::new(contents, file_id);
     while let Some(&c) = tokens.peek() {
+        tokens.begin();
         match c {
-            '\n' => {
-                linenum += 1;
-                tokens.t_drop()
-            }
-            '{' => tokens.push(TokenType {
-                token: Token::OpenBrace,
-                val: String::from("{"),
-                linenum,
-            }),
-            '}' => tokens.push(TokenType {
-                token: Token::CloseBrace,
-                val: String::from("}"),
-                linenum,
-            }),
-            '(' => tokens.push(TokenType {
-                token: Token::OpenParen,
-                val: String::from("("),
-                linenum,
-            }),
-            ')' => tokens.push(TokenType {
-                token: Token::CloseParen,
-                val: String::from(")"),
-                linenum,
-            }),
-            '[' => tokens.push(TokenType {
-                token: Token::OpenSquareParen,
-                val: String::from("["),
-                linenum,
-            }),
-            ']' => tokens.push(TokenType {
-                token: Token::CloseSquareParen,
-                val: String::from("]"),
-                linenum,
-            }),
-            ' ' | '\t' | '\r' => tokens.t_drop(),
+            '{' => tokens.push(Token::OpenBrace, "{"),
+            '}' => tokens.push(Token::CloseBrace, "}"),
+            '(' => tokens.push(Token::OpenParen, "("),
+            ')' => tokens.push(Token::CloseParen, ")"),
+            '[' => tokens.push(Token::OpenSquareParen, "["),
+            ']' => tokens.push(Token::CloseSquareParen, "]"),
+            ' ' | '\t' | '\r' | '\n' => tokens.t_drop(),
             'a'..='z' | 'A'..='Z' => {
                 let word: &str = &tokens.get_string(|x| x.is_ascii() && x.is_alphanumeric());
-                match word {
-                    "async" => tokens.push_back(TokenType {
-                        token: Token::Keyword(Keyword::Async),
-                        val: String::from("async"),
-                        linenum,
-                    }),
-                    "true" => tokens.push_back(TokenType {
-                        token: Token::Keyword(Keyword::True),
-                        val: String::from("true"),
-                        linenum,
-                    }),
-                    "false" => tokens.push_back(TokenType {
-                        token: Token::Keyword(Keyword::False),
-                        val: String::from("async"),
-                        linenum,
-                    }),
-                    "int" => tokens.push_back(TokenType {
-                        token: Token::Keyword(Keyword::Int),
-                        val: String::from("int"),
-                        linenum,
-                    }),
-                    "str" => tokens.push_back(TokenType {
-                        token: Token::Keyword(Keyword::String),
-                        val: String::from("str"),
-                        linenum,
-                    }),
-                    "fn" => tokens.push_back(TokenType {
-                        token: Token::Keyword(Keyword::Func),
-                        val: String::from("fn"),
-                        linenum,
-                    }),
-                    "let" => tokens.push_back(TokenType {
-                        token: Token::Keyword(Keyword::Let),
-                        val: String::from("let"),
-                        linenum,
-                    }),
-                    "mlstr" => tokens.push_back(TokenType {
-                        token: Token::Keyword(Keyword::MLstr),
-                        val: String::from("mlstr"),
-                        linenum,
-                    }),
-                    "return" => tokens.push_back(TokenType {
-                        token: Token::Keyword(Keyword::Return),
-                        val: String::from("return"),
-                        linenum,
-                    }),
-                    "void" => tokens.push_back(TokenType {
-                        token: Token::Keyword(Keyword::Void),
-                        val: String::from("void"),
-                        linenum,
-                    }),
-                    "bool" => tokens.push_back(TokenType {
-                        token: Token::Keyword(Keyword::Bool),
-                        val: String::from("bool"),
-                        linenum,
-                    }),
-                    "if" => tokens.push_back(TokenType {
-                        token: Token::Keyword(Keyword::If),
-                        val: String::from("if"),
-                        linenum,
-                    }),
-                    "else" => tokens.push_back(TokenType {
-                        token: Token::Keyword(Keyword::Else),
-                        val: String::from("else"),
-                        linenum,
-                    }),
-                    "while" => tokens.push_back(TokenType {
-                        token: Token::Keyword(Keyword::While),
-                        val: String::from("while"),
-                        linenum,
-                    }),
-                    "const" => tokens.push_back(TokenType {
-                        token: Token::Keyword(Keyword::Const),
-                        val: String::from("const"),
-                        linenum,
-                    }),
-                    "use" => tokens.push_back(TokenType {
-                        token: Token::Keyword(Keyword::Use),
-                        val: String::from("use"),
-                        linenum,
-                    }),
-                    "for" => tokens.push_back(TokenType {
-                        token: Token::Keyword(Keyword::For),
-                        val: String::from("for"),
-                        linenum,
-                    }),
-                    "pub" => tokens.push_back(TokenType {
-                        token: Token::Keyword(Keyword::Pub),
-                        val: String::from("pub"),
-                        linenum,
-                    }),
-                    other => tokens.push_back(TokenType {
-                        token: Token::Identifier(word.to_string()),
-                        val: String::from(other),
-                        linenum,
-                    }),
+                let keyword = match word {
+                    "async" => Some(Keyword::Async),
+                    "true" => Some(Keyword::True),
+                    "false" => Some(Keyword::False),
+                    "int" => Some(Keyword::Int),
+                    "str" => Some(Keyword::String),
+                    "fn" => Some(Keyword::Func),
+                    "let" => Some(Keyword::Let),
+                    "mlstr" => Some(Keyword::MLstr),
+                    "return" => Some(Keyword::Return),
+                    "void" => Some(Keyword::Void),
+                    "bool" => Some(Keyword::Bool),
+                    "if" => Some(Keyword::If),
+                    "else" => Some(Keyword::Else),
+                    "while" => Some(Keyword::While),
+                    "const" => Some(Keyword::Const),
+                    "use" => Some(Keyword::Use),
+                    "for" => Some(Keyword::For),
+                    "in" => Some(Keyword::In),
+                    "break" => Some(Keyword::Break),
+                    "continue" => Some(Keyword::Continue),
+                    "struct" => Some(Keyword::Struct),
+                    "enum" => Some(Keyword::Enum),
+                    "match" => Some(Keyword::Match),
+                    "float" => Some(Keyword::Float),
+                    "as" => Some(Keyword::As),
+                    "pub" => Some(Keyword::Pub),
+                    _ => None,
+                };
+                match keyword {
+                    Some(keyword) => tokens.push_back(Token::Keyword(keyword), word),
+                    None => tokens.push_back(Token::Identifier(word.to_string()), word),
                 }
             }
             '`' => {
                 tokens.p_next();
-                let t = tokens.get_string(|s| (s != &'`'));
-                tokens.push(TokenType {
-                    token: Token::Literal(Value::MLStr(t)),
-                    val: String::from("`"),
-                    linenum,
-                })
+                let t = tokens.get_string(|s| s != &'`');
+                tokens.push(Token::Literal(Value::MLStr(t)), "`")
             }
             '"' => {
                 tokens.p_next();
-                let t = tokens.get_string(|s| (s != &'"'));
-                tokens.push(TokenType {
-                    token: Token::Literal(Value::Char(t)),
-                    val: String::from('"'),
-                    linenum,
-                })
+                let t = tokens.get_string(|s| s != &'"');
+                tokens.push(Token::Literal(Value::Char(t)), "\"")
             }
             '\'' => {
                 tokens.p_next();
-                let t = tokens.get_string(|s| (s != &'\''));
-                tokens.push(TokenType {
-                    token: Token::Literal(Value::Char(t)),
-                    val: String::from("'"),
-                    linenum,
-                })
+                let t = tokens.get_string(|s| s != &'\'');
+                tokens.push(Token::Literal(Value::Char(t)), "'")
             }
             '0'..='9' => {
-                let word = tokens.get_string(|x| x.is_ascii() && (x.is_digit(16) || x == &'x'));
+                let mut word = tokens.get_string(|x| x.is_ascii() && (x.is_ascii_hexdigit() || x == &'x'));
+                if !word.starts_with("0x") {
+                    // A `.` only starts a fraction when a digit follows, so that `0..n` is a range.
+                    let fraction = tokens.peek_second().is_some_and(|c| c.is_ascii_digit());
+                    if tokens.peek() == Some(&'.') && fraction {
+                        tokens.p_next();
+                        word.push('.');
+                        word += &tokens.get_string(|x| x.is_ascii_hexdigit());
+                    }
+                    if word.ends_with(['e', 'E']) {
+                        if let Some(&sign @ '+') | Some(&sign @ '-') = tokens.peek() {
+                            tokens.p_next();
+                            word.push(sign);
+                            word += &tokens.get_string(|x| x.is_ascii_digit());
+                        }
+                    }
+                    if word.contains(['.', 'e', 'E']) {
+                        let float = match word.parse::<f64>() {
+                            Ok(float) if float.is_finite() => float,
+                            parsed => {
+                                return Err(TokenizeError::InvalidFloat {
+                                    reason: match parsed {
+                                        Ok(_) => "too large for a `float`".into(),
+                                        Err(e) => e.to_string().into(),
+                                    },
+                                    span: tokens.span(),
+                                    filename: fname.into(),
+                                })
+                            }
+                        };
+                        tokens.push_back(Token::Literal(Value::Float(Float(float))), &word);
+                        continue;
+                    }
+                }
 
                 #[allow(clippy::manual_strip)]
-                let int: u64 = if word.starts_with("0x") {
-                    u64::from_str_radix(&word[2..], 16)?
+                let int = if word.starts_with("0x") {
+                    u64::from_str_radix(&word[2..], 16)
                 } else {
-                    word.parse()?
+                    word.parse()
                 };
-                tokens.push_back(TokenType {
-                    token: Token::Literal(Value::Int(int)),
-                    val: int.to_string(),
-                    linenum,
-                })
+                let int = int.map_err(|source| TokenizeError::InvalidInteger {
+                    source,
+                    span: tokens.span(),
+                    filename: fname.into(),
+                })?;
+                tokens.push_back(Token::Literal(Value::Int(int)), &int.to_string())
             }
-            '~' => tokens.push(TokenType {
-                token: Token::BitComp,
-                val: String::from('~'),
-                linenum,
-            }),
-            ',' => tokens.push(TokenType {
-                token: Token::Comma,
-                val: String::from(","),
-                linenum,
-            }),
-            multi => match (tokens.p_next().unwrap(), tokens.peek()) {
-                ('&', Some(&'&')) => tokens.push(TokenType {
-                    token: Token::And,
-                    val: String::from("&&"),
-                    linenum,
-                }),
-                ('|', Some(&'|')) => tokens.push(TokenType {
-                    token: Token::Or,
-                    val: String::from("||"),
-                    linenum,
-                }),
-
-                ('=', Some(&'>')) => {
-                    tokens.p_next();
-                    tokens.push(TokenType {
-                        token: Token::AsignFunc,
-                        val: String::from("=>"),
-                        linenum,
-                    })
-                }
-                ('=', Some(&'=')) => tokens.push(TokenType {
-                    token: Token::Equal,
-                    val: String::from(","),
-                    linenum,
-                }),
-                ('<', Some(&'=')) => tokens.push(TokenType {
-                    token: Token::LessThanOrEqual,
-                    val: String::from("<="),
-                    linenum,
-                }),
-                ('>', Some(&'=')) => tokens.push(TokenType {
-                    token: Token::GreaterThanOrEqual,
-                    val: String::from(">="),
-                    linenum,
-                }),
-                ('!', Some(&'=')) => tokens.push(TokenType {
-                    token: Token::NotEqual,
-                    val: String::from("!="),
-                    linenum,
-                }),
+            '~' => tokens.push(Token::BitComp, "~"),
+            ',' => tokens.push(Token::Comma, ","),
+            ';' => tokens.push(Token::Semicolon, ";"),
+            multi => match (tokens.p_next().unwrap_or(multi), tokens.peek()) {
+                ('&', Some(&'&')) => tokens.push(Token::And, "&&"),
+                ('|', Some(&'|')) => tokens.push(Token::Or, "||"),
+                ('=', Some(&'>')) => tokens.push(Token::AsignFunc, "=>"),
+                ('=', Some(&'=')) => tokens.push(Token::Equal, "=="),
+                ('<', Some(&'=')) => tokens.push(Token::LessThanOrEqual, "<="),
+                ('>', Some(&'=')) => tokens.push(Token::GreaterThanOrEqual, ">="),
+                ('!', Some(&'=')) => tokens.push(Token::NotEqual, "!="),
                 ('<', Some(&'<')) => {
                     tokens.p_next();
                     if let Some(&'=') = tokens.peek() {
-                        tokens.push(TokenType {
-                            token: Token::AssignBitLeft,
-                            val: String::from("<<="),
-                            linenum,
-                        })
+                        tokens.push(Token::AssignBitLeft, "<<=")
                     } else {
-                        tokens.push_back(TokenType {
-                            token: Token::BitwiseLeft,
-                            val: String::from("<<"),
-                            linenum,
-                        })
+                        tokens.push_back(Token::BitwiseLeft, "<<")
                     }
                 }
                 ('>', Some(&'>')) => {
                     tokens.p_next();
                     if let Some(&'=') = tokens.peek() {
-                        tokens.push(TokenType {
-                            token: Token::AssignBitRight,
-                            val: String::from(">>="),
-                            linenum,
-                        })
+                        tokens.push(Token::AssignBitRight, ">>=")
                     } else {
-                        tokens.push_back(TokenType {
-                            token: Token::BitwiseRight,
-                            val: String::from(">>"),
-                            linenum,
-                        })
+                        tokens.push_back(Token::BitwiseRight, ">>")
                     }
                 }
-                ('+', Some(&'=')) => tokens.push(TokenType {
-                    token: Token::AssignAdd,
-                    val: String::from("+="),
-                    linenum,
-                }),
-                ('-', Some(&'=')) => tokens.push(TokenType {
-                    token: Token::AssignSub,
-                    val: String::from("-="),
-                    linenum,
-                }),
-                ('*', Some(&'=')) => tokens.push(TokenType {
-                    token: Token::AssignMul,
-                    val: String::from("*="),
-                    linenum,
-                }),
-                ('/', Some(&'=')) => tokens.push(TokenType {
-                    token: Token::AssignDiv,
-                    val: String::from("/="),
-                    linenum,
-                }),
-                ('/', _) => {
-                    let next = tokens.peek().unwrap();
-                    tokens.push_back(TokenType {
-                        token: Token::Division,
-                        val: String::from("/"),
-                        linenum,
-                    })
+                ('+', Some(&'=')) => tokens.push(Token::AssignAdd, "+="),
+                ('-', Some(&'=')) => tokens.push(Token::AssignSub, "-="),
+                ('*', Some(&'=')) => tokens.push(Token::AssignMul, "*="),
+                ('/', Some(&'=')) => tokens.push(Token::AssignDiv, "/="),
+                ('/', Some(&'/')) => {
+                    tokens.get_string(|s| s != &'\n');
                 }
-                ('%', Some(&'=')) => tokens.push(TokenType {
-                    token: Token::AssignMod,
-                    val: String::from("%="),
-                    linenum,
-                }),
-                ('&', Some(&'=')) => tokens.push(TokenType {
-                    token: Token::AssignAnd,
-                    val: String::from("&="),
-                    linenum,
-                }),
-                ('|', Some(&'=')) => tokens.push(TokenType {
-                    token: Token::AssignOr,
-                    val: String::from("|="),
-                    linenum,
-                }),
-                ('^', Some(&'=')) => tokens.push(TokenType {
-                    token: Token::AssignXor,
-                    val: String::from("^="),
-                    linenum,
-                }),
-                ('+', Some(&'+')) => tokens.push(TokenType {
-                    token: Token::Increment,
-                    val: String::from("++"),
-                    linenum,
-                }),
-                ('-', Some(&'-')) => tokens.push(TokenType {
-                    token: Token::Decrement,
-                    val: String::from("--"),
-                    linenum,
-                }),
-                (':', Some(&':')) => tokens.push(TokenType {
-                    token: Token::DoubleColon,
-                    val: String::from("::"),
-                    linenum,
-                }),
-
-                ('.', _) => tokens.push_back(TokenType {
-                    token: Token::Dot,
-                    val: String::from("."),
-                    linenum,
-                }),
-                ('$', _) => tokens.push_back(TokenType {
-                    token: Token::Dollar,
-                    val: String::from("$"),
-                    linenum,
-                }),
-                ('#', _) => tokens.push_back(TokenType {
-                    token: Token::HashTag,
-                    linenum,
-                    val: String::from("#"),
-                }),
-                ('<', _) => tokens.push_back(TokenType {
-                    token: Token::LessThan,
-                    val: String::from("<"),
-                    linenum,
-                }),
-                ('>', _) => tokens.push_back(TokenType {
-                    token: Token::GreaterThan,
-                    val: String::from(">"),
-                    linenum,
-                }),
-                ('!', _) => tokens.push_back(TokenType {
-                    token: Token::LogicalNeg,
-                    val: String::from("!"),
-                    linenum,
-                }),
-                ('&', _) => tokens.push_back(TokenType {
-                    linenum,
-                    token: Token::BitwiseAnd,
-                    val: String::from("&"),
-                }),
-                ('|', _) => tokens.push_back(TokenType {
-                    token: Token::BitwiseOr,
-                    linenum,
-                    val: String::from("|"),
-                }),
-                ('=', _) => tokens.push_back(TokenType {
-                    token: Token::Assign,
-                    val: String::from("="),
-                    linenum,
-                }),
-                ('+', _) => tokens.push_back(TokenType {
-                    token: Token::Addition,
-                    val: String::from("+"),
-                    linenum,
-                }),
-                ('-', _) => tokens.push_back(TokenType {
-                    token: Token::Negation,
-                    val: String::from("-"),
-                    linenum,
-                }),
-                ('*', _) => tokens.push_back(TokenType {
-                    token: Token::Multiplication,
-                    val: String::from("*"),
-                    linenum,
-                }),
-                ('%', _) => tokens.push_back(TokenType {
-                    token: Token::Modulus,
-                    val: String::from("%"),
-                    linenum,
-                }),
-                ('^', _) => tokens.push_back(TokenType {
-                    token: Token::BitwiseXor,
-                    val: String::from("^"),
-                    linenum,
-                }),
-                (':', _) => tokens.push_back(TokenType {
-                    token: Token::Colon,
-                    val: String::from(":"),
-                    linenum,
-                }),
-                ('?', _) => tokens.push_back(TokenType {
-                    token: Token::Question,
-                    val: String::from("?"),
-                    linenum,
-                }),
+                ('/', Some(&'*')) => {
+                    tokens.p_next();
+                    while let Some(c) = tokens.p_next() {
+                        if c == '*' && tokens.peek() == Some(&'/') {
+                            tokens.p_next();
+                            break;
+                        }
+                    }
+                }
+                ('/', _) => tokens.push_back(Token::Division, "/"),
+                ('%', Some(&'=')) => tokens.push(Token::AssignMod, "%="),
+                ('&', Some(&'=')) => tokens.push(Token::AssignAnd, "&="),
+                ('|', Some(&'=')) => tokens.push(Token::AssignOr, "|="),
+                ('^', Some(&'=')) => tokens.push(Token::AssignXor, "^="),
+                ('+', Some(&'+')) => tokens.push(Token::Increment, "++"),
+                ('-', Some(&'-')) => tokens.push(Token::Decrement, "--"),
+                (':', Some(&':')) => tokens.push(Token::DoubleColon, "::"),
+                ('.', Some(&'.')) => tokens.push(Token::DotDot, ".."),
+                ('.', _) => tokens.push_back(Token::Dot, "."),
+                ('$', _) => tokens.push_back(Token::Dollar, "$"),
+                ('#', _) => tokens.push_back(Token::HashTag, "#"),
+                ('<', _) => tokens.push_back(Token::LessThan, "<"),
+                ('>', _) => tokens.push_back(Token::GreaterThan, ">"),
+                ('!', _) => tokens.push_back(Token::LogicalNeg, "!"),
+                ('&', _) => tokens.push_back(Token::BitwiseAnd, "&"),
+                ('|', _) => tokens.push_back(Token::BitwiseOr, "|"),
+                ('=', _) => tokens.push_back(Token::Assign, "="),
+                ('+', _) => tokens.push_back(Token::Addition, "+"),
+                ('-', _) => tokens.push_back(Token::Negation, "-"),
+                ('*', _) => tokens.push_back(Token::Multiplication, "*"),
+                ('%', _) => tokens.push_back(Token::Modulus, "%"),
+                ('^', _) => tokens.push_back(Token::BitwiseXor, "^"),
+                (':', _) => tokens.push_back(Token::Colon, ":"),
+                ('?', _) => tokens.push_back(Token::Question, "?"),
+                ('_', _) => tokens.push_back(Token::Underscore, "_"),
                 _ => {
                     return Err(TokenizeError::UnknownToken {
                         c: multi,
-                        linenum,
+                        span: tokens.span(),
                         filename: fname.into(),
                     })
                 }
@@ -426,6 +209,10 @@ pub fn tokenize(contents: &str, fname: &str) -> Result<Vec<TokenType>, TokenizeE
 mod test {
     use super::*;
 
+    fn span(start: usize, end: usize, line: usize, col: usize) -> Span {
+        Span::new(0, start, end, line, col)
+    }
+
     #[test]
     fn single_char_ops() {
         assert_eq!(
@@ -434,17 +221,17 @@ mod test {
                 TokenType {
                     token: Token::OpenBrace,
                     val: String::from("{"),
-                    linenum: 1
+                    span: span(0, 1, 1, 1),
                 },
                 TokenType {
                     token: Token::CloseBrace,
                     val: String::from("}"),
-                    linenum: 1
+                    span: span(1, 2, 1, 2),
                 },
                 TokenType {
                     token: Token::Dollar,
                     val: String::from("$"),
-                    linenum: 1
+                    span: span(2, 3, 1, 3),
                 }
             ]
         );
@@ -458,22 +245,65 @@ mod test {
                 TokenType {
                     token: Token::And,
                     val: "&&".to_string(),
-                    linenum: 1
+                    span: span(0, 2, 1, 1),
                 },
                 TokenType {
                     token: Token::Or,
                     val: String::from("||"),
-                    linenum: 1
+                    span: span(2, 4, 1, 3),
                 },
                 TokenType {
                     token: Token::BitwiseRight,
                     val: String::from(">>"),
-                    linenum: 1
+                    span: span(4, 6, 1, 5),
                 }
             ]
         );
     }
 
+    #[test]
+    fn spans_track_lines_and_columns() {
+        let tokens = tokenize("fn main() {\n  let x = `é\nb` // trailing\n\"é\" }", "").unwrap();
+        let spans: Vec<(usize, usize, usize, usize)> = tokens
+            .iter()
+            .map(|t| (t.span.start, t.span.end, t.span.line, t.span.col))
+            .collect();
+        assert_eq!(tokens[5].token, Token::Keyword(Keyword::Let));
+        assert_eq!(spans[5], (14, 17, 2, 3));
+        assert_eq!(tokens[8].token, Token::Literal(Value::MLStr("é\nb".into())));
+        assert_eq!(spans[8], (22, 28, 2, 11));
+        assert_eq!(spans[9], (41, 45, 4, 1));
+        // Columns count characters, not bytes.
+        assert_eq!(tokens[10].token, Token::CloseBrace);
+        assert_eq!(spans[10], (46, 47, 4, 5));
+    }
+
+    #[test]
+    fn floats_and_ranges() {
+        let tokens: Vec<Token> = tokenize("1.5 2e3 6.02E-23 0..n a.0", "")
+            .unwrap()
+            .into_iter()
+            .map(|t| t.token)
+            .collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Literal(Value::Float(Float(1.5))),
+                Token::Literal(Value::Float(Float(2000.0))),
+                Token::Literal(Value::Float(Float(6.02e-23))),
+                Token::Literal(Value::Int(0)),
+                Token::DotDot,
+                Token::Identifier("n".into()),
+                Token::Identifier("a".into()),
+                Token::Dot,
+                Token::Literal(Value::Int(0)),
+            ]
+        );
+        for src in ["1e", "1.5e+", "1e999"].iter() {
+            assert_eq!(tokenize(src, "").unwrap_err().code(), "Z0043", "{}", src);
+        }
+    }
+
     // #[test]
     // fn drop_whitespace() {
     //     assert_eq!(
@@ -1,47 +1,107 @@
-use itertools::Itertools;
+use crate::{Float, Span};
+use std::fmt;
 use std::iter::Peekable;
-use std::str::Chars;
+use std::str::CharIndices;
 
 #[derive(Debug)]
 pub struct TokenParser<'a> {
     pub tokens: Vec<TokenType>,
-    iter: Peekable<Chars<'a>>,
+    source: &'a str,
+    iter: Peekable<CharIndices<'a>>,
+    file_id: usize,
+    /// The line and column of the character that `peek` returns, both from 1.
+    line: usize,
+    col: usize,
+    start: Span,
 }
 
 impl<'a> TokenParser<'a> {
-    pub fn new(source: &str) -> TokenParser {
+    pub fn new(source: &'a str, file_id: usize) -> TokenParser<'a> {
         TokenParser {
             tokens: vec![],
-            iter: source.chars().peekable(),
+            source,
+            iter: source.char_indices().peekable(),
+            file_id,
+            line: 1,
+            col: 1,
+            start: Span::new(file_id, 0, 0, 1, 1),
         }
     }
 
-    pub fn push(&mut self, token: TokenType) {
-        self.iter.next();
-        self.tokens.push(token);
+    /// Marks the current position as the start of the next token.
+    pub fn begin(&mut self) {
+        let start = self.offset();
+        self.start = Span::new(self.file_id, start, start, self.line, self.col);
     }
 
-    pub fn push_back(&mut self, token: TokenType) {
-        self.tokens.push(token);
+    /// Returns the span from the last call to `begin` up to the current position.
+    pub fn span(&mut self) -> Span {
+        Span {
+            end: self.offset(),
+            ..self.start
+        }
+    }
+
+    pub fn push(&mut self, token: Token, val: &str) {
+        self.p_next();
+        self.push_back(token, val);
+    }
+
+    pub fn push_back(&mut self, token: Token, val: &str) {
+        let span = self.span();
+        self.tokens.push(TokenType {
+            token,
+            val: val.to_string(),
+            span,
+        });
     }
 
     pub fn p_next(&mut self) -> Option<char> {
-        self.iter.next()
+        let (_, c) = self.iter.next()?;
+        if c == '\n' {
+            self.line += 1;
+            self.col = 1;
+        } else {
+            self.col += 1;
+        }
+        Some(c)
     }
 
     pub fn t_drop(&mut self) {
-        self.iter.next();
+        self.p_next();
     }
 
     pub fn peek(&mut self) -> Option<&char> {
-        self.iter.peek()
+        self.iter.peek().map(|(_, c)| c)
     }
 
     pub fn get_string<F>(&mut self, func: F) -> String
     where
         F: Fn(&char) -> bool,
     {
-        self.iter.peeking_take_while(|c| func(c)).collect()
+        let mut string = String::new();
+        while let Some(c) = self.peek() {
+            if !func(c) {
+                break;
+            }
+            let c = *c;
+            self.p_next();
+            string.push(c);
+        }
+        string
+    }
+
+    /// The character after the one `peek` returns.
+    pub fn peek_second(&mut self) -> Option<char> {
+        let offset = self.offset();
+        self.source[offset..].chars().nth(1)
+    }
+
+    fn offset(&mut self) -> usize {
+        match self.iter.peek() {
+            Some((offset, _)) => *offset,
+            None => self.source.len(),
+        }
     }
 }
 
@@ -49,7 +109,7 @@ impl<'a> TokenParser<'a> {
 pub struct TokenType {
     pub(crate) token: Token,
     pub(crate) val: String,
-    pub(crate) linenum: i32,
+    pub(crate) span: Span,
 }
 
 #[derive(Debug, Eq, PartialEq, Clone)]
@@ -72,11 +132,20 @@ pub enum Keyword {
     Pub,
     Const,
     For,
+    In,
+    Break,
+    Continue,
+    Struct,
+    Enum,
+    Match,
+    Float,
+    As,
 }
 
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub enum Value {
     Int(u64),
+    Float(Float),
     Char(String),
     MLStr(String),
 }
@@ -100,6 +169,8 @@ pub enum Token {
     Division,
     Modulus,
     Dot,
+    DotDot,
+    Semicolon,
     And,
     Or,
     Equal,
@@ -131,6 +202,7 @@ pub enum Token {
     Decrement,
     Colon,
     DoubleColon,
+    Underscore,
     Dollar,
     Question,
 }
@@ -139,7 +211,136 @@ pub enum Token {
 pub enum ParserDescriptor {
     AnyIdentifier,
     AnyVariable,
+    AnyExpression,
+    AnyType,
+    AnyInteger,
+    AnyPattern,
+    AnyItem,
     Token(Token),
     Newline,
     NoToken,
 }
+
+impl Keyword {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Keyword::Int => "int",
+            Keyword::Async => "async",
+            Keyword::String => "str",
+            Keyword::Void => "void",
+            Keyword::True => "true",
+            Keyword::False => "false",
+            Keyword::MLstr => "mlstr",
+            Keyword::Let => "let",
+            Keyword::Func => "fn",
+            Keyword::Bool => "bool",
+            Keyword::Return => "return",
+            Keyword::If => "if",
+            Keyword::Else => "else",
+            Keyword::While => "while",
+            Keyword::Use => "use",
+            Keyword::Pub => "pub",
+            Keyword::Const => "const",
+            Keyword::For => "for",
+            Keyword::In => "in",
+            Keyword::Break => "break",
+            Keyword::Continue => "continue",
+            Keyword::Struct => "struct",
+            Keyword::Enum => "enum",
+            Keyword::Match => "match",
+            Keyword::Float => "float",
+            Keyword::As => "as",
+        }
+    }
+}
+
+impl Token {
+    /// The source text of tokens that always look the same, e.g. `{` or `+=`.
+    pub fn as_str(&self) -> Option<&'static str> {
+        Some(match self {
+            Token::OpenBrace => "{",
+            Token::CloseBrace => "}",
+            Token::OpenParen => "(",
+            Token::CloseParen => ")",
+            Token::OpenSquareParen => "[",
+            Token::CloseSquareParen => "]",
+            Token::Keyword(k) => k.as_str(),
+            Token::Identifier(_) | Token::Literal(_) => return None,
+            Token::BitComp => "~",
+            Token::LogicalNeg => "!",
+            Token::Negation => "-",
+            Token::Addition => "+",
+            Token::Multiplication => "*",
+            Token::Division => "/",
+            Token::Modulus => "%",
+            Token::Dot => ".",
+            Token::DotDot => "..",
+            Token::Semicolon => ";",
+            Token::And => "&&",
+            Token::Or => "||",
+            Token::Equal => "==",
+            Token::NotEqual => "!=",
+            Token::LessThan => "<",
+            Token::LessThanOrEqual => "<=",
+            Token::GreaterThan => ">",
+            Token::GreaterThanOrEqual => ">=",
+            Token::BitwiseLeft => "<<",
+            Token::BitwiseRight => ">>",
+            Token::BitwiseAnd => "&",
+            Token::BitwiseXor => "^",
+            Token::BitwiseOr => "|",
+            Token::Assign => "=",
+            Token::Comma => ",",
+            Token::AsignFunc => "=>",
+            Token::AssignAdd => "+=",
+            Token::AssignSub => "-=",
+            Token::AssignDiv => "/=",
+            Token::AssignMul => "*=",
+            Token::AssignMod => "%=",
+            Token::AssignBitLeft => "<<=",
+            Token::AssignBitRight => ">>=",
+            Token::AssignAnd => "&=",
+            Token::AssignOr => "|=",
+            Token::AssignXor => "^=",
+            Token::HashTag => "#",
+            Token::Increment => "++",
+            Token::Decrement => "--",
+            Token::Colon => ":",
+            Token::DoubleColon => "::",
+            Token::Underscore => "_",
+            Token::Dollar => "$",
+            Token::Question => "?",
+        })
+    }
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Identifier(name) => write!(f, "identifier `{}`", name),
+            Token::Keyword(k) => write!(f, "keyword `{}`", k.as_str()),
+            Token::Literal(Value::Int(i)) => write!(f, "integer `{}`", i),
+            Token::Literal(Value::Float(x)) => write!(f, "float `{}`", x),
+            Token::Literal(Value::Char(s)) => write!(f, "string {:?}", s),
+            Token::Literal(Value::MLStr(s)) => write!(f, "string `{}`", s),
+            other => write!(f, "`{}`", other.as_str().unwrap_or_default()),
+        }
+    }
+}
+
+impl fmt::Display for ParserDescriptor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParserDescriptor::AnyIdentifier => write!(f, "an identifier"),
+            ParserDescriptor::AnyVariable => write!(f, "a variable name"),
+            ParserDescriptor::AnyExpression => write!(f, "an expression"),
+            ParserDescriptor::AnyType => write!(f, "a type"),
+            ParserDescriptor::AnyInteger => write!(f, "an integer"),
+            ParserDescriptor::AnyPattern => write!(f, "a pattern"),
+            ParserDescriptor::AnyItem => write!(f, "`fn`, `const`, `let`, `struct`, `enum` or `use`"),
+            ParserDescriptor::Token(token) => write!(f, "{}", token),
+            ParserDescriptor::Newline => write!(f, "a new line"),
+            ParserDescriptor::NoToken => write!(f, "nothing"),
+        }
+    }
+}
@@ -0,0 +1,44 @@
+use lexer::Type;
+
+/// A function provided by the compiler rather than declared in Zeta source.
+/// A program may declare its own function of the same name, which then
+/// replaces the builtin.
+pub struct Builtin {
+    pub name: &'static str,
+    pub arguments: &'static [Type],
+    pub return_type: Type,
+    /// Whether the builtin may only be called from `main`.
+    pub entry_only: bool,
+}
+
+pub const BUILTINS: &[Builtin] = &[
+    // The process arguments, as passed to `main`. `argv(0)` is the program name.
+    Builtin {
+        name: "argc",
+        arguments: &[],
+        return_type: Type::Int,
+        entry_only: true,
+    },
+    Builtin {
+        name: "argv",
+        arguments: &[Type::Int],
+        return_type: Type::Str,
+        entry_only: true,
+    },
+    // The length of a string in bytes.
+    Builtin {
+        name: "len",
+        arguments: &[Type::Str],
+        return_type: Type::Int,
+        entry_only: false,
+    },
+];
+
+/// The C functions that the native runtime calls into. Unlike builtins they
+/// cannot be replaced, so the program's own functions and globals may not
+/// take their names.
+pub const RESERVED: &[&str] = &["abort", "dprintf", "malloc", "memcmp", "memcpy", "strlen"];
+
+pub fn builtin(name: &str) -> Option<&'static Builtin> {
+    BUILTINS.iter().find(|b| b.name == name)
+}
@@ -0,0 +1,337 @@
+use crate::errors::SemanticError;
+use crate::resolve::{Resolution, SymbolId, SymbolKind};
+use lexer::{BinOp, Expression, ExpressionKind, Float, Global, Program, Span, Type, UnOp};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+/// The value of a `const`, or the initial value of a top-level `let`,
+/// worked out while compiling.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ConstValue {
+    Int(i64),
+    Float(Float),
+    Bool(bool),
+    Str(String),
+    Array(Vec<ConstValue>),
+}
+
+impl ConstValue {
+    /// The type of the value. An empty array is typed as an array of `int`.
+    pub fn t(&self) -> Type {
+        match self {
+            ConstValue::Int(_) => Type::Int,
+            ConstValue::Float(_) => Type::Float,
+            ConstValue::Bool(_) => Type::Bool,
+            ConstValue::Str(_) => Type::Str,
+            ConstValue::Array(elements) => Type::Array(
+                Box::new(elements.first().map_or(Type::Int, ConstValue::t)),
+                elements.len() as u64,
+            ),
+        }
+    }
+}
+
+struct Evaluator<'a, 'r> {
+    resolution: &'r mut Resolution,
+    globals: HashMap<SymbolId, &'a Global>,
+    /// The value of every global evaluated so far, or `None` if it has an error.
+    values: HashMap<SymbolId, Option<ConstValue>>,
+    /// The globals being evaluated, each used by the one before it.
+    stack: Vec<SymbolId>,
+    errors: Vec<SemanticError>,
+}
+
+/// Evaluates the initialiser of every global in a type checked program,
+/// recording the values in `resolution`. Initialisers may use literals,
+/// other globals, operators, `?:`, arrays and indexing, and globals may
+/// refer to ones declared after them as long as none depends on itself. A
+/// `const` may not use a `let`, whose value can change while running.
+/// Integer arithmetic is checked, so a global that would overflow or divide
+/// by zero is an error rather than a different value on each backend.
+pub fn evaluate(program: &Program, resolution: &mut Resolution) -> Vec<SemanticError> {
+    let globals = program
+        .globals
+        .iter()
+        .filter_map(|g| Some((resolution.resolve(g.var.span)?, g)))
+        .collect();
+    let mut evaluator = Evaluator {
+        resolution,
+        globals,
+        values: HashMap::new(),
+        stack: Vec::new(),
+        errors: Vec::new(),
+    };
+    for global in program.globals.iter() {
+        if let Some(id) = evaluator.resolution.resolve(global.var.span) {
+            evaluator.global(id, global.span);
+        }
+    }
+    evaluator.errors
+}
+
+impl<'a, 'r> Evaluator<'a, 'r> {
+    /// The initial value of the global `id`, used at `span`.
+    fn global(&mut self, id: SymbolId, span: Span) -> Option<ConstValue> {
+        if let Some(value) = self.values.get(&id) {
+            return value.clone();
+        }
+        if self.stack.contains(&id) {
+            self.errors.push(SemanticError::ConstEvaluation {
+                reason: format!(
+                    "the value of `{}` depends on itself",
+                    self.resolution.symbol(id).name
+                )
+                .into(),
+                span,
+            });
+            return None;
+        }
+        // Duplicate declarations have no symbol of their own.
+        let global = *self.globals.get(&id)?;
+        self.stack.push(id);
+        let value = self.evaluate(&global.value);
+        self.stack.pop();
+        if let Some(value) = &value {
+            let symbol = self.resolution.symbol_mut(id);
+            if symbol.t.is_none() {
+                symbol.t = Some(value.t());
+            }
+            self.resolution
+                .set_global_value(id, value.clone(), global.is_const);
+        }
+        self.values.insert(id, value.clone());
+        value
+    }
+
+    fn error(&mut self, reason: &str, span: Span) -> Option<ConstValue> {
+        self.errors.push(SemanticError::ConstEvaluation {
+            reason: reason.into(),
+            span,
+        });
+        None
+    }
+
+    /// Evaluates `exp`. Operands of the wrong type have already been
+    /// reported by the type checker, and evaluate to `None` silently.
+    fn evaluate(&mut self, exp: &Expression) -> Option<ConstValue> {
+        match &exp.kind {
+            ExpressionKind::Int(i) => match i64::try_from(*i) {
+                Ok(i) => Some(ConstValue::Int(i)),
+                Err(_) => self.error("this integer does not fit in an `int`", exp.span),
+            },
+            ExpressionKind::Float(x) => Some(ConstValue::Float(*x)),
+            ExpressionKind::Bool(b) => Some(ConstValue::Bool(*b)),
+            ExpressionKind::Char(s) | ExpressionKind::MLStr(s) => Some(ConstValue::Str(s.clone())),
+            ExpressionKind::Variable(_) => {
+                let id = self.resolution.resolve(exp.span)?;
+                let is_const = |id| self.globals.get(&id).is_some_and(|g| g.is_const);
+                let in_const = self.stack.last().is_some_and(|&current| is_const(current));
+                if self.resolution.symbol(id).kind != SymbolKind::Global
+                    || (in_const && !is_const(id))
+                {
+                    return self.not_constant(exp.span);
+                }
+                self.global(id, exp.span)
+            }
+            ExpressionKind::UnOp(op, operand) => {
+                let operand = self.evaluate(operand)?;
+                match (op, operand) {
+                    (UnOp::Negation, ConstValue::Int(i)) => match i.checked_neg() {
+                        Some(i) => Some(ConstValue::Int(i)),
+                        None => self.error("this negation overflows", exp.span),
+                    },
+                    (UnOp::Negation, ConstValue::Float(x)) => Some(ConstValue::Float(Float(-x.0))),
+                    (UnOp::BitComp, ConstValue::Int(i)) => Some(ConstValue::Int(!i)),
+                    (UnOp::LogicalNeg, ConstValue::Bool(b)) => Some(ConstValue::Bool(!b)),
+                    _ => None,
+                }
+            }
+            ExpressionKind::BinOp(op, lhs, rhs) => {
+                let lhs = self.evaluate(lhs)?;
+                let rhs = self.evaluate(rhs)?;
+                self.binop(op, lhs, rhs, exp.span)
+            }
+            ExpressionKind::Ternary(condition, then, otherwise) => {
+                match self.evaluate(condition)? {
+                    ConstValue::Bool(true) => self.evaluate(then),
+                    ConstValue::Bool(false) => self.evaluate(otherwise),
+                    _ => None,
+                }
+            }
+            ExpressionKind::Array(elements) => {
+                let mut values = Vec::with_capacity(elements.len());
+                for element in elements.iter() {
+                    values.push(self.evaluate(element)?);
+                }
+                Some(ConstValue::Array(values))
+            }
+            ExpressionKind::Index(array, index) => {
+                match (self.evaluate(array)?, self.evaluate(index)?) {
+                    (ConstValue::Array(elements), ConstValue::Int(i)) => {
+                        match usize::try_from(i).ok().and_then(|i| elements.get(i)) {
+                            Some(element) => Some(element.clone()),
+                            None => self.error("this index is out of bounds", index.span),
+                        }
+                    }
+                    _ => None,
+                }
+            }
+            ExpressionKind::Cast(value, t) => match (self.evaluate(value)?, t) {
+                (ConstValue::Int(i), Type::Float) => Some(ConstValue::Float(Float(i as f64))),
+                (ConstValue::Float(x), Type::Int) => {
+                    // -2^63 is exactly representable, 2^63 is one past `i64::MAX`.
+                    let limit = -(i64::MIN as f64);
+                    match x.0.trunc() {
+                        x if x >= -limit && x < limit => Some(ConstValue::Int(x as i64)),
+                        _ => self.error("this float does not fit in an `int`", exp.span),
+                    }
+                }
+                (value, _) => Some(value),
+            },
+            _ => self.not_constant(exp.span),
+        }
+    }
+
+    fn not_constant(&mut self, span: Span) -> Option<ConstValue> {
+        self.errors.push(SemanticError::NotConstant { span });
+        None
+    }
+
+    fn binop(
+        &mut self,
+        op: &BinOp,
+        lhs: ConstValue,
+        rhs: ConstValue,
+        span: Span,
+    ) -> Option<ConstValue> {
+        use ConstValue::{Bool, Int, Str};
+        let value = match (op, lhs, rhs) {
+            (BinOp::Comma, _, rhs) => rhs,
+            // Floats follow IEEE 754 here as they do when running, so dividing
+            // by zero is infinite rather than an error.
+            (op, ConstValue::Float(Float(lhs)), ConstValue::Float(Float(rhs))) => match op {
+                BinOp::Addition => ConstValue::Float(Float(lhs + rhs)),
+                BinOp::Subtraction => ConstValue::Float(Float(lhs - rhs)),
+                BinOp::Multiplication => ConstValue::Float(Float(lhs * rhs)),
+                BinOp::Division => ConstValue::Float(Float(lhs / rhs)),
+                BinOp::Equal => Bool(lhs == rhs),
+                BinOp::NotEqual => Bool(lhs != rhs),
+                BinOp::LessThan => Bool(lhs < rhs),
+                BinOp::LessThanOrEqual => Bool(lhs <= rhs),
+                BinOp::GreaterThan => Bool(lhs > rhs),
+                BinOp::GreaterThanOrEqual => Bool(lhs >= rhs),
+                _ => return None,
+            },
+            (BinOp::Equal, lhs, rhs) => Bool(lhs == rhs),
+            (BinOp::NotEqual, lhs, rhs) => Bool(lhs != rhs),
+            (BinOp::Addition, Str(lhs), Str(rhs)) => Str(lhs + &rhs),
+            (BinOp::And, Bool(lhs), Bool(rhs)) => Bool(lhs && rhs),
+            (BinOp::Or, Bool(lhs), Bool(rhs)) => Bool(lhs || rhs),
+            (BinOp::LessThan, Int(lhs), Int(rhs)) => Bool(lhs < rhs),
+            (BinOp::LessThanOrEqual, Int(lhs), Int(rhs)) => Bool(lhs <= rhs),
+            (BinOp::GreaterThan, Int(lhs), Int(rhs)) => Bool(lhs > rhs),
+            (BinOp::GreaterThanOrEqual, Int(lhs), Int(rhs)) => Bool(lhs >= rhs),
+            (BinOp::BitwiseAnd, Int(lhs), Int(rhs)) => Int(lhs & rhs),
+            (BinOp::BitwiseXor, Int(lhs), Int(rhs)) => Int(lhs ^ rhs),
+            (BinOp::BitwiseOr, Int(lhs), Int(rhs)) => Int(lhs | rhs),
+            (BinOp::Division, Int(_), Int(0)) | (BinOp::Modulus, Int(_), Int(0)) => {
+                return self.error("this divides by zero", span)
+            }
+            (op, Int(lhs), Int(rhs)) => {
+                let shift = u32::try_from(rhs).ok().filter(|&s| s < 64);
+                let value = match op {
+                    BinOp::Addition => lhs.checked_add(rhs),
+                    BinOp::Subtraction => lhs.checked_sub(rhs),
+                    BinOp::Multiplication => lhs.checked_mul(rhs),
+                    BinOp::Division => lhs.checked_div(rhs),
+                    BinOp::Modulus => lhs.checked_rem(rhs),
+                    BinOp::BitwiseLeft => shift.map(|s| lhs << s),
+                    BinOp::BitwiseRight => shift.map(|s| lhs >> s),
+                    _ => return None,
+                };
+                match value {
+                    Some(value) => Int(value),
+                    None => return self.error(&format!("`{}` overflows here", op), span),
+                }
+            }
+            _ => return None,
+        };
+        Some(value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use lexer::{tokenize, Parser};
+
+    fn analyze_src(src: &str) -> (Program, Resolution, Vec<SemanticError>) {
+        let (mut program, errors) = Parser::new(tokenize(src, "").unwrap(), "".into()).parse();
+        assert!(errors.is_empty(), "{:?}", errors);
+        let (resolution, errors) = crate::analyze(&mut program);
+        (program, resolution, errors)
+    }
+
+    #[test]
+    fn evaluates_consts_in_any_order() {
+        let src = "const area = side * side\nconst side = big ? 4 : -2\nconst big = !false && \"a\" + \"b\" == \"ab\"\nconst row = [side, area % 5][1]";
+        let (program, resolution, errors) = analyze_src(src);
+        assert_eq!(errors, vec![]);
+        let values: Vec<_> = program
+            .globals
+            .iter()
+            .map(|g| {
+                let id = resolution.resolve(g.var.span).unwrap();
+                (
+                    resolution.const_value(id).cloned(),
+                    resolution.symbol(id).t.clone(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            values,
+            vec![
+                (Some(ConstValue::Int(16)), Some(Type::Int)),
+                (Some(ConstValue::Int(4)), Some(Type::Int)),
+                (Some(ConstValue::Bool(true)), Some(Type::Bool)),
+                (Some(ConstValue::Int(1)), Some(Type::Int)),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_consts_that_cannot_be_evaluated() {
+        let src = "const a = b\nconst b = a + 1\nconst c = 1 / (2 - 2)\nconst d = 4611686018427387904 * 2\nconst e = f()\nlet g = 1\nconst h = g\nfn f(): int {\n    c = 1\n    g = 2\n    return 0\n}";
+        let codes: Vec<_> = analyze_src(src).2.iter().map(|e| e.code()).collect();
+        assert_eq!(
+            codes,
+            vec!["Z0039", "Z0038", "Z0038", "Z0038", "Z0037", "Z0037"]
+        );
+    }
+
+    #[test]
+    fn evaluates_float_consts() {
+        let src = "const half = 1 as float / 2.0\nconst big = 1e300 * 1e300\nconst rounded = -2.75 as int\nconst same = 0.0 == -0.0\nconst huge = 1e19 as int";
+        let (program, resolution, errors) = analyze_src(src);
+        let codes: Vec<_> = errors.iter().map(|e| e.code()).collect();
+        assert_eq!(codes, vec!["Z0038"]);
+        let values: Vec<_> = program
+            .globals
+            .iter()
+            .map(|g| {
+                let id = resolution.resolve(g.var.span).unwrap();
+                resolution.const_value(id).cloned()
+            })
+            .collect();
+        assert_eq!(
+            values,
+            vec![
+                Some(ConstValue::Float(Float(0.5))),
+                Some(ConstValue::Float(Float(f64::INFINITY))),
+                Some(ConstValue::Int(-2)),
+                Some(ConstValue::Bool(true)),
+                None,
+            ]
+        );
+    }
+}
@@ -0,0 +1,338 @@
+use lexer::{diagnostics::Diagnostic, Span, Type};
+use thiserror::Error;
+
+#[derive(Error, Debug, Eq, PartialEq)]
+pub enum SemanticError {
+    #[error("mismatched types: expected {expected:}, found {found:} at {span:}")]
+    MismatchedTypes {
+        expected: Type,
+        found: Type,
+        span: Span,
+    },
+    #[error("cannot find variable `{name:}` at {span:}")]
+    UndefinedVariable { name: Box<str>, span: Span },
+    #[error("cannot find function `{name:}` at {span:}")]
+    UndefinedFunction { name: Box<str>, span: Span },
+    #[error(
+        "function `{fnname:}` takes {expected:} arguments but {found:} were supplied at {span:}"
+    )]
+    ArgumentCount {
+        fnname: Box<str>,
+        expected: usize,
+        found: usize,
+        span: Span,
+    },
+    #[error("cannot apply `{op:}` to a value of type {found:} at {span:}")]
+    InvalidOperand {
+        op: Box<str>,
+        found: Type,
+        span: Span,
+    },
+    #[error("function `{fnname:}` returns {expected:} but this returns {found:} at {span:}")]
+    MismatchedReturn {
+        fnname: Box<str>,
+        expected: Type,
+        found: Type,
+        span: Span,
+    },
+    #[error("function `{fnname:}` does not return {expected:} on every path at {span:}")]
+    MissingReturn {
+        fnname: Box<str>,
+        expected: Type,
+        span: Span,
+    },
+    #[error("`{name:}` is declared more than once in the same scope at {span:}")]
+    DuplicateName {
+        name: Box<str>,
+        span: Span,
+        previous: Span,
+    },
+    #[error("invalid `main` function: {reason:} at {span:}")]
+    InvalidEntryPoint { reason: Box<str>, span: Span },
+    #[error("`{name:}` can only be used in `main` at {span:}")]
+    OutsideEntryPoint { name: Box<str>, span: Span },
+    #[error("`{keyword:}` outside of a loop at {span:}")]
+    OutsideLoop { keyword: &'static str, span: Span },
+    #[error("use of undeclared label `{name:}` at {span:}")]
+    UndefinedLabel { name: Box<str>, span: Span },
+    #[error("index {index:} is out of bounds for an array of length {len:} at {span:}")]
+    IndexOutOfBounds { index: u64, len: u64, span: Span },
+    #[error("cannot find type `{name:}` at {span:}")]
+    UndefinedType { name: Box<str>, span: Span },
+    #[error("struct `{ty:}` has no field named `{name:}` at {span:}")]
+    UnknownField {
+        ty: Box<str>,
+        name: Box<str>,
+        span: Span,
+    },
+    #[error("missing field `{name:}` in initializer of `{ty:}` at {span:}")]
+    MissingField {
+        ty: Box<str>,
+        name: Box<str>,
+        span: Span,
+    },
+    #[error("`{name:}` contains itself at {span:}")]
+    RecursiveType { name: Box<str>, span: Span },
+    #[error("enum `{ty:}` has no variant named `{name:}` at {span:}")]
+    UnknownVariant {
+        ty: Box<str>,
+        name: Box<str>,
+        span: Span,
+    },
+    #[error("non-exhaustive patterns: `{missing:}` not covered at {span:}")]
+    NonExhaustiveMatch { missing: Box<str>, span: Span },
+    #[error("unresolved import `{path:}` at {span:}")]
+    UnresolvedImport { path: Box<str>, span: Span },
+    #[error("`{name:}` is declared in another module and not imported at {span:}")]
+    NotImported { name: Box<str>, span: Span },
+    #[error("`{name:}` is private at {span:}")]
+    PrivateItem {
+        name: Box<str>,
+        span: Span,
+        defined: Span,
+    },
+    #[error("a global can only be initialised with a constant expression at {span:}")]
+    NotConstant { span: Span },
+    #[error("cannot evaluate const: {reason:} at {span:}")]
+    ConstEvaluation { reason: Box<str>, span: Span },
+    #[error("cannot assign to const `{name:}` at {span:}")]
+    AssignToConst {
+        name: Box<str>,
+        span: Span,
+        defined: Span,
+    },
+    #[error("async function `{name:}` is not supported at {span:}")]
+    UnsupportedAsync { name: Box<str>, span: Span },
+    #[error("cannot convert {from:} to {to:} at {span:}")]
+    InvalidCast { from: Type, to: Type, span: Span },
+    #[error("cannot infer the type of `[]` at {span:}")]
+    UntypedEmptyArray { span: Span },
+    #[error("no field `{name:}` on type `{ty:}` at {span:}")]
+    NoFields {
+        ty: Box<str>,
+        name: Box<str>,
+        span: Span,
+    },
+    #[error("`{name:}` is reserved by the runtime at {span:}")]
+    ReservedName { name: Box<str>, span: Span },
+}
+
+impl SemanticError {
+    /// The stable error code shown as `error[Z....]`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            SemanticError::MismatchedTypes { .. } => "Z0012",
+            SemanticError::UndefinedVariable { .. } => "Z0013",
+            SemanticError::UndefinedFunction { .. } => "Z0014",
+            SemanticError::ArgumentCount { .. } => "Z0015",
+            SemanticError::InvalidOperand { .. } => "Z0016",
+            SemanticError::MismatchedReturn { .. } => "Z0017",
+            SemanticError::MissingReturn { .. } => "Z0018",
+            SemanticError::DuplicateName { .. } => "Z0019",
+            SemanticError::InvalidEntryPoint { .. } => "Z0020",
+            SemanticError::OutsideEntryPoint { .. } => "Z0021",
+            SemanticError::OutsideLoop { .. } => "Z0022",
+            SemanticError::UndefinedLabel { .. } => "Z0023",
+            SemanticError::IndexOutOfBounds { .. } => "Z0024",
+            SemanticError::UndefinedType { .. } => "Z0025",
+            SemanticError::UnknownField { .. } => "Z0026",
+            SemanticError::MissingField { .. } => "Z0027",
+            SemanticError::RecursiveType { .. } => "Z0028",
+            SemanticError::UnknownVariant { .. } => "Z0029",
+            SemanticError::NonExhaustiveMatch { .. } => "Z0030",
+            SemanticError::UnresolvedImport { .. } => "Z0034",
+            SemanticError::NotImported { .. } => "Z0035",
+            SemanticError::PrivateItem { .. } => "Z0036",
+            SemanticError::NotConstant { .. } => "Z0037",
+            SemanticError::ConstEvaluation { .. } => "Z0038",
+            SemanticError::AssignToConst { .. } => "Z0039",
+            SemanticError::UnsupportedAsync { .. } => "Z0040",
+            SemanticError::InvalidCast { .. } => "Z0044",
+            SemanticError::UntypedEmptyArray { .. } => "Z0045",
+            SemanticError::NoFields { .. } => "Z0046",
+            SemanticError::ReservedName { .. } => "Z0047",
+        }
+    }
+
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        let diagnostic = match self {
+            SemanticError::MismatchedTypes {
+                expected,
+                found,
+                span,
+            } => {
+                let diagnostic = Diagnostic::error("mismatched types")
+                    .with_label(*span, format!("expected `{}`, found `{}`", expected, found));
+                match (expected, found) {
+                    (Type::Int, Type::Float) | (Type::Float, Type::Int) => {
+                        diagnostic.with_help(format!("convert it with `as {}`", expected))
+                    }
+                    _ => diagnostic,
+                }
+            }
+            SemanticError::UndefinedVariable { name, span } => {
+                Diagnostic::error(format!("cannot find variable `{}` in this scope", name))
+                    .with_label(*span, "not found in this scope")
+            }
+            SemanticError::UndefinedFunction { name, span } => {
+                Diagnostic::error(format!("cannot find function `{}` in this scope", name))
+                    .with_label(*span, "not found in this scope")
+            }
+            SemanticError::ArgumentCount {
+                fnname,
+                expected,
+                found,
+                span,
+            } => Diagnostic::error(format!(
+                "function `{}` takes {} argument{} but {} {} supplied",
+                fnname,
+                expected,
+                if *expected == 1 { "" } else { "s" },
+                found,
+                if *found == 1 { "was" } else { "were" }
+            ))
+            .with_label(*span, format!("expected {}", expected)),
+            SemanticError::InvalidOperand { op, found, span } => {
+                Diagnostic::error(format!("cannot apply `{}` to type `{}`", op, found))
+                    .with_label(*span, format!("this is of type `{}`", found))
+            }
+            SemanticError::MismatchedReturn {
+                fnname,
+                expected,
+                found,
+                span,
+            } => Diagnostic::error("mismatched types")
+                .with_label(*span, format!("expected `{}`, found `{}`", expected, found))
+                .with_note(format!("`{}` is declared to return `{}`", fnname, expected)),
+            SemanticError::MissingReturn {
+                fnname,
+                expected,
+                span,
+            } => Diagnostic::error(format!("function `{}` may not return a value", fnname))
+                .with_label(*span, format!("expected `{}` to be returned", expected))
+                .with_help("end every path through the function with a `return`"),
+            SemanticError::DuplicateName {
+                name,
+                span,
+                previous,
+            } => Diagnostic::error(format!("`{}` is defined multiple times", name))
+                .with_label(*span, format!("`{}` redefined here", name))
+                .with_secondary_label(*previous, format!("previous definition of `{}` here", name))
+                .with_help("declarations in an inner block may shadow outer ones, but not ones in the same block"),
+            SemanticError::InvalidEntryPoint { reason, span } => {
+                Diagnostic::error("invalid `main` function")
+                    .with_label(*span, reason.to_string())
+                    .with_note("`main` is the entry point of the program, e.g. `fn main(): int`")
+            }
+            SemanticError::OutsideEntryPoint { name, span } => {
+                Diagnostic::error(format!("`{}` can only be used in `main`", name))
+                    .with_label(*span, "used outside of `main`")
+                    .with_help("pass the value to this function as a parameter instead")
+            }
+            SemanticError::OutsideLoop { keyword, span } => {
+                Diagnostic::error(format!("`{}` outside of a loop", keyword))
+                    .with_label(*span, format!("cannot `{}` outside of a loop", keyword))
+            }
+            SemanticError::UndefinedLabel { name, span } => {
+                Diagnostic::error(format!("use of undeclared label `{}`", name))
+                    .with_label(*span, "no enclosing loop has this label")
+                    .with_help(format!("label the loop with `{}: while (...)`", name))
+            }
+            SemanticError::IndexOutOfBounds { index, len, span } => {
+                Diagnostic::error("this index is out of bounds")
+                    .with_label(
+                        *span,
+                        format!("index {} is past the end of an array of length {}", index, len),
+                    )
+                    .with_note("the program would stop with an out of bounds error here")
+            }
+            SemanticError::UndefinedType { name, span } => {
+                Diagnostic::error(format!("cannot find type `{}` in this scope", name))
+                    .with_label(*span, "not found in this scope")
+                    .with_help(format!("declare it with `struct {} {{ ... }}`", name))
+            }
+            SemanticError::UnknownField { ty, name, span } => {
+                Diagnostic::error(format!("struct `{}` has no field named `{}`", ty, name))
+                    .with_label(*span, "unknown field")
+            }
+            SemanticError::MissingField { ty, name, span } => Diagnostic::error(format!(
+                "missing field `{}` in initializer of `{}`",
+                name, ty
+            ))
+            .with_label(*span, format!("missing `{}`", name)),
+            SemanticError::RecursiveType { name, span } => {
+                Diagnostic::error(format!("`{}` contains itself", name))
+                    .with_label(*span, "recursive without indirection")
+                    .with_note("a value stored inside itself would have infinite size")
+            }
+            SemanticError::UnknownVariant { ty, name, span } => {
+                Diagnostic::error(format!("enum `{}` has no variant named `{}`", ty, name))
+                    .with_label(*span, "unknown variant")
+            }
+            SemanticError::NonExhaustiveMatch { missing, span } => {
+                Diagnostic::error(format!("non-exhaustive patterns: `{}` not covered", missing))
+                    .with_label(*span, "not every value is matched")
+                    .with_help("add an arm for each missing pattern, or a `_ => ...` arm")
+            }
+            SemanticError::UnresolvedImport { path, span } => {
+                Diagnostic::error(format!("unresolved import `{}`", path))
+                    .with_label(*span, "no such item in this module")
+            }
+            SemanticError::NotImported { name, span } => Diagnostic::error(format!(
+                "`{}` is declared in another module",
+                name
+            ))
+            .with_label(*span, "not imported into this file")
+            .with_help(format!("import it with `use module::{}`", name)),
+            SemanticError::PrivateItem {
+                name,
+                span,
+                defined,
+            } => Diagnostic::error(format!("`{}` is private", name))
+                .with_label(*span, "private item")
+                .with_secondary_label(*defined, format!("`{}` is declared here", name))
+                .with_help("mark it `pub` to use it from other modules"),
+            SemanticError::NotConstant { span } => {
+                Diagnostic::error("a global can only be initialised with a constant expression")
+                    .with_label(*span, "not known while compiling")
+                    .with_note("globals are built from literals, other globals and operators, and a `const` only from other consts")
+                    .with_help("use `let` inside a function for values computed at run time")
+            }
+            SemanticError::ConstEvaluation { reason, span } => {
+                Diagnostic::error("cannot evaluate const").with_label(*span, reason.to_string())
+            }
+            SemanticError::AssignToConst {
+                name,
+                span,
+                defined,
+            } => Diagnostic::error(format!("cannot assign to const `{}`", name))
+                .with_label(*span, "cannot assign twice to a const")
+                .with_secondary_label(*defined, format!("`{}` is declared here", name)),
+            SemanticError::UnsupportedAsync { name, span } => {
+                Diagnostic::error(format!("async function `{}` is not supported", name))
+                    .with_label(*span, "declared `async` here")
+                    .with_help("remove `async`; Zeta has no runtime to run async functions on yet")
+            }
+            SemanticError::InvalidCast { from, to, span } => {
+                Diagnostic::error(format!("cannot convert `{}` to `{}`", from, to))
+                    .with_label(*span, "only `int` and `float` convert with `as`")
+            }
+            SemanticError::UntypedEmptyArray { span } => {
+                Diagnostic::error("cannot infer the type of `[]`")
+                    .with_label(*span, "the element type is unknown here")
+                    .with_help("use `[]` where an array type is expected, e.g. `let a: [int; 0] = []`")
+            }
+            SemanticError::NoFields { ty, name, span } => {
+                Diagnostic::error(format!("no field `{}` on type `{}`", name, ty))
+                    .with_label(*span, format!("`{}` is an enum, which has no fields", ty))
+                    .with_help("use `match` to read the values of a variant")
+            }
+            SemanticError::ReservedName { name, span } => {
+                Diagnostic::error(format!("`{}` is a reserved name", name))
+                    .with_label(*span, format!("the runtime calls the C function `{}`", name))
+                    .with_help("rename the item, or move it into a module of its own")
+            }
+        };
+        diagnostic.with_code(self.code())
+    }
+}
@@ -0,0 +1,39 @@
+mod builtins;
+mod consteval;
+pub mod errors;
+mod resolve;
+mod typeck;
+mod visibility;
+
+pub use builtins::{builtin, Builtin, BUILTINS, RESERVED};
+pub use consteval::ConstValue;
+pub use resolve::{Resolution, Symbol, SymbolId, SymbolKind};
+
+use errors::SemanticError;
+use lexer::Program;
+use visibility::Visibility;
+
+/// Resolves names, type checks `program` and evaluates its globals, returning
+/// the resolution for the backends together with every error found.
+///
+/// The items of every module but the entry point are renamed to their path
+/// in `program`, like `shapes::Square`, so that each has a name of its own
+/// however many modules declare one like it. See [`mangle`] for how the
+/// backends spell those names.
+pub fn analyze(program: &mut Program) -> (Resolution, Vec<SemanticError>) {
+    let visibility = Visibility::new(program);
+    let mut errors = visibility.check_imports(program);
+    errors.extend(visibility.qualify(program));
+    let (mut resolution, resolve_errors) = resolve::resolve(program, &visibility);
+    errors.extend(resolve_errors);
+    errors.extend(typeck::check(program, &mut resolution));
+    errors.extend(consteval::evaluate(program, &mut resolution));
+    (resolution, errors)
+}
+
+/// The name that a backend gives the item `name`, as named by [`analyze`].
+/// Zeta names cannot contain `_`, so joining the path of a module's item
+/// with `_` cannot clash with any other item.
+pub fn mangle(name: &str) -> String {
+    name.replace("::", "_")
+}
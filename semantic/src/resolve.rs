@@ -0,0 +1,650 @@
+use crate::builtins::{BUILTINS, RESERVED};
+use crate::consteval::ConstValue;
+use crate::errors::SemanticError;
+use crate::visibility::{Namespace, Visibility};
+use lexer::{
+    Expression, ExpressionKind, Function, PatternKind, Program, Span, Statement, StatementKind,
+    Type,
+};
+use std::collections::{HashMap, HashSet};
+
+/// Identifies one declared name: a global, function, parameter or local.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, PartialOrd, Ord)]
+pub struct SymbolId(usize);
+
+impl SymbolId {
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum SymbolKind {
+    Global,
+    Function,
+    /// A function provided by the compiler, see [`BUILTINS`].
+    Builtin,
+    Parameter,
+    Local,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    /// The type of the value, or the return type for functions. Inferred
+    /// declarations are filled in by the type checker and stay `None` if
+    /// their initialiser has no type.
+    pub t: Option<Type>,
+    /// Where the name is declared.
+    pub span: Span,
+}
+
+/// The result of name resolution: every symbol in the program, and which
+/// symbol each declaration or use refers to.
+///
+/// Uses are keyed by the span of the node naming them:
+/// * the `Variable` of a `Declare` statement or function parameter,
+/// * the `Function` itself,
+/// * `Variable`, `VariableRef`, `Assign`, `AssignPostfix` and `FunctionCall` expressions.
+///
+/// `break` and `continue` statements are likewise mapped to the span of the
+/// loop they leave, see [`Resolution::loop_target`].
+#[derive(Debug, Default, Clone)]
+pub struct Resolution {
+    symbols: Vec<Symbol>,
+    uses: HashMap<Span, SymbolId>,
+    loops: HashMap<Span, Span>,
+    /// The initial value of every global, and whether it is a `const`.
+    globals: HashMap<SymbolId, (ConstValue, bool)>,
+    /// The element type of every `[]`, by its span. It is only known from
+    /// where the array is used, see `typeck`.
+    empty_arrays: HashMap<Span, Type>,
+    /// The `+`s that concatenate strings rather than add numbers, by span.
+    concatenations: HashSet<Span>,
+}
+
+impl Resolution {
+    pub fn symbol(&self, id: SymbolId) -> &Symbol {
+        &self.symbols[id.0]
+    }
+
+    pub(crate) fn symbol_mut(&mut self, id: SymbolId) -> &mut Symbol {
+        &mut self.symbols[id.0]
+    }
+
+    /// Returns the symbol declared or used by the node at `span`.
+    pub fn resolve(&self, span: Span) -> Option<SymbolId> {
+        self.uses.get(&span).copied()
+    }
+
+    /// The `while` or `for` statement that the `break` or `continue` at `span` applies to.
+    pub fn loop_target(&self, span: Span) -> Option<Span> {
+        self.loops.get(&span).copied()
+    }
+
+    /// The value of the `const` global `id`, if it could be evaluated.
+    pub fn const_value(&self, id: SymbolId) -> Option<&ConstValue> {
+        match self.globals.get(&id) {
+            Some((value, true)) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// The value that the global `id`, a `const` or a top-level `let`,
+    /// starts out with.
+    pub fn initial_value(&self, id: SymbolId) -> Option<&ConstValue> {
+        self.globals.get(&id).map(|(value, _)| value)
+    }
+
+    pub(crate) fn set_global_value(&mut self, id: SymbolId, value: ConstValue, is_const: bool) {
+        self.globals.insert(id, (value, is_const));
+    }
+
+    /// The element type of the empty array literal at `span`.
+    pub fn empty_array_element(&self, span: Span) -> Option<&Type> {
+        self.empty_arrays.get(&span)
+    }
+
+    pub(crate) fn set_empty_array_element(&mut self, span: Span, element: Type) {
+        self.empty_arrays.insert(span, element);
+    }
+
+    /// Whether the `+` at `span` concatenates two strings.
+    pub fn is_concatenation(&self, span: Span) -> bool {
+        self.concatenations.contains(&span)
+    }
+
+    pub(crate) fn set_concatenation(&mut self, span: Span) {
+        self.concatenations.insert(span);
+    }
+
+    pub fn symbols(&self) -> impl Iterator<Item = (SymbolId, &Symbol)> {
+        self.symbols
+            .iter()
+            .enumerate()
+            .map(|(i, symbol)| (SymbolId(i), symbol))
+    }
+
+    fn declare(&mut self, symbol: Symbol) -> SymbolId {
+        let id = self.declare_builtin(symbol);
+        self.uses.insert(self.symbols[id.0].span, id);
+        id
+    }
+
+    /// Adds a symbol that has no declaration in the source.
+    fn declare_builtin(&mut self, symbol: Symbol) -> SymbolId {
+        let id = SymbolId(self.symbols.len());
+        self.symbols.push(symbol);
+        id
+    }
+}
+
+/// Resolves names with lexical scoping. Every function and global of the
+/// program is visible throughout the file declaring it and the files that
+/// import it (see [`Visibility`]), so functions may be called before they
+/// are defined. Parameters share a scope with the top level of the function
+/// body; every `{ }` block, `if`, `while` and `for` body opens a new one,
+/// a `for` header opens one around its body for the loop variable, and each
+/// `match` arm opens one for the names its pattern binds. A
+/// declaration may shadow a name from an enclosing scope but not one
+/// declared earlier in the same scope.
+struct Resolver<'v> {
+    resolution: Resolution,
+    /// The functions and globals, by their names throughout the program.
+    functions: HashMap<String, SymbolId>,
+    globals: HashMap<String, SymbolId>,
+    builtins: HashMap<&'static str, SymbolId>,
+    scopes: Vec<HashMap<String, SymbolId>>,
+    /// The loops enclosing the statement being resolved, innermost last, with their labels.
+    loops: Vec<(Option<String>, Span)>,
+    /// The globals declared with `const`, which cannot be assigned to.
+    consts: HashSet<SymbolId>,
+    visibility: &'v Visibility,
+    errors: Vec<SemanticError>,
+}
+
+/// Resolves the names in `program`, whose items have already been renamed
+/// by [`Visibility::qualify`].
+pub(crate) fn resolve(
+    program: &Program,
+    visibility: &Visibility,
+) -> (Resolution, Vec<SemanticError>) {
+    let mut resolver = Resolver {
+        errors: Vec::new(),
+        visibility,
+        resolution: Resolution::default(),
+        functions: HashMap::new(),
+        globals: HashMap::new(),
+        builtins: HashMap::new(),
+        scopes: Vec::new(),
+        loops: Vec::new(),
+        consts: HashSet::new(),
+    };
+    resolver.resolve_program(program);
+    (resolver.resolution, resolver.errors)
+}
+
+impl Resolver<'_> {
+    fn resolve_program(&mut self, program: &Program) {
+        for function in program.func.iter() {
+            let symbol = Symbol {
+                name: function.name.clone(),
+                kind: SymbolKind::Function,
+                t: Some(function.return_type.clone()),
+                span: function.span,
+            };
+            self.check_reserved(&symbol);
+            if let Some(&previous) = self.functions.get(&function.name) {
+                self.duplicate(&symbol, previous);
+                continue;
+            }
+            let id = self.resolution.declare(symbol);
+            self.functions.insert(function.name.clone(), id);
+        }
+        for builtin in BUILTINS.iter() {
+            let id = self.resolution.declare_builtin(Symbol {
+                name: builtin.name.to_string(),
+                kind: SymbolKind::Builtin,
+                t: Some(builtin.return_type.clone()),
+                span: Span::default(),
+            });
+            self.builtins.insert(builtin.name, id);
+        }
+        // Globals are declared up front, so that they may refer to ones declared later.
+        for global in program.globals.iter() {
+            let symbol = Symbol {
+                name: global.var.name.clone(),
+                kind: SymbolKind::Global,
+                t: global.var.t.clone(),
+                span: global.var.span,
+            };
+            self.check_reserved(&symbol);
+            if let Some(&previous) = self.globals.get(&global.var.name) {
+                self.duplicate(&symbol, previous);
+                continue;
+            }
+            let id = self.resolution.declare(symbol);
+            self.globals.insert(global.var.name.clone(), id);
+            if global.is_const {
+                self.consts.insert(id);
+            }
+        }
+        for global in program.globals.iter() {
+            self.resolve_expression(&global.value);
+        }
+        for function in program.func.iter() {
+            self.resolve_function(function);
+        }
+    }
+
+    fn resolve_function(&mut self, function: &Function) {
+        self.scopes.push(HashMap::new());
+        for argument in function.arguments.iter() {
+            self.declare(Symbol {
+                name: argument.name.clone(),
+                kind: SymbolKind::Parameter,
+                t: argument.t.clone(),
+                span: argument.span,
+            });
+        }
+        for statement in function.statements.iter() {
+            self.resolve_statement(statement, SymbolKind::Local);
+        }
+        self.scopes.pop();
+    }
+
+    fn duplicate(&mut self, symbol: &Symbol, previous: SymbolId) {
+        self.errors.push(SemanticError::DuplicateName {
+            name: symbol.name.as_str().into(),
+            span: symbol.span,
+            previous: self.resolution.symbol(previous).span,
+        });
+    }
+
+    /// Items of modules are named by their path, so only those of the entry
+    /// file can take the name of one of the runtime's C functions.
+    fn check_reserved(&mut self, symbol: &Symbol) {
+        if RESERVED.contains(&symbol.name.as_str()) {
+            self.errors.push(SemanticError::ReservedName {
+                name: symbol.name.as_str().into(),
+                span: symbol.span,
+            });
+        }
+    }
+
+    fn declare(&mut self, symbol: Symbol) {
+        let previous = self
+            .scopes
+            .last()
+            .and_then(|scope| scope.get(&symbol.name))
+            .copied();
+        if let Some(previous) = previous {
+            self.duplicate(&symbol, previous);
+            return;
+        }
+        let name = symbol.name.clone();
+        let id = self.resolution.declare(symbol);
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name, id);
+        }
+    }
+
+    /// The local or global that `name`, used at `span`, refers to.
+    fn lookup(&mut self, name: &str, span: Span) -> Option<SymbolId> {
+        let local = self.scopes.iter().rev().find_map(|scope| scope.get(name));
+        if let Some(&id) = local {
+            return Some(id);
+        }
+        let visibility = self.visibility;
+        let (global, error) = visibility.lookup(Namespace::Global, name, span)?;
+        self.errors.extend(error);
+        self.globals.get(global).copied()
+    }
+
+    /// The function that the call of `name` at `span` refers to. A file's
+    /// own functions and the ones it imports come before the builtins.
+    fn lookup_function(&mut self, name: &str, span: Span) -> Option<SymbolId> {
+        let visibility = self.visibility;
+        let found = visibility.lookup(Namespace::Function, name, span);
+        let (function, error) = match (found, self.builtins.get(name)) {
+            (Some((function, None)), _) => (function, None),
+            (_, Some(&builtin)) => return Some(builtin),
+            (found, None) => found?,
+        };
+        self.errors.extend(error);
+        self.functions.get(function).copied()
+    }
+
+    fn resolve_statement(&mut self, statement: &Statement, kind: SymbolKind) {
+        match &statement.kind {
+            StatementKind::Declare(var, value) => {
+                // The initialiser is resolved first, so `let x = x` refers to an outer `x`.
+                if let Some(value) = value {
+                    self.resolve_expression(value);
+                }
+                self.declare(Symbol {
+                    name: var.name.clone(),
+                    kind,
+                    t: var.t.clone(),
+                    span: var.span,
+                });
+            }
+            StatementKind::Return(exp) | StatementKind::Exp(exp) => self.resolve_expression(exp),
+            StatementKind::If(condition, then, otherwise) => {
+                self.resolve_expression(condition);
+                self.resolve_scoped(then);
+                if let Some(otherwise) = otherwise {
+                    self.resolve_scoped(otherwise);
+                }
+            }
+            StatementKind::While(..) | StatementKind::For(..) | StatementKind::ForRange(..) => {
+                self.resolve_loop(statement, None)
+            }
+            StatementKind::Labeled(label, inner) => self.resolve_loop(inner, Some(label)),
+            StatementKind::Break(label) => self.resolve_jump("break", label, statement.span),
+            StatementKind::Continue(label) => self.resolve_jump("continue", label, statement.span),
+            StatementKind::Compound(statements) => {
+                self.scopes.push(HashMap::new());
+                for statement in statements.iter() {
+                    self.resolve_statement(statement, SymbolKind::Local);
+                }
+                self.scopes.pop();
+            }
+        }
+    }
+
+    fn resolve_loop(&mut self, statement: &Statement, label: Option<&String>) {
+        self.loops.push((label.cloned(), statement.span));
+        match &statement.kind {
+            StatementKind::While(condition, body) => {
+                self.resolve_expression(condition);
+                self.resolve_scoped(body);
+            }
+            StatementKind::For(init, condition, step, body) => {
+                self.scopes.push(HashMap::new());
+                if let Some(init) = init {
+                    self.resolve_statement(init, SymbolKind::Local);
+                }
+                for exp in condition.iter().chain(step.iter()) {
+                    self.resolve_expression(exp);
+                }
+                self.resolve_scoped(body);
+                self.scopes.pop();
+            }
+            StatementKind::ForRange(var, from, to, body) => {
+                self.resolve_expression(from);
+                self.resolve_expression(to);
+                self.scopes.push(HashMap::new());
+                self.declare(Symbol {
+                    name: var.name.clone(),
+                    kind: SymbolKind::Local,
+                    t: var.t.clone(),
+                    span: var.span,
+                });
+                self.resolve_scoped(body);
+                self.scopes.pop();
+            }
+            // The parser only allows loops to be labelled.
+            _ => self.resolve_statement(statement, SymbolKind::Local),
+        }
+        self.loops.pop();
+    }
+
+    /// Records which loop a `break` or `continue` leaves.
+    fn resolve_jump(&mut self, keyword: &'static str, label: &Option<String>, span: Span) {
+        let target = match label {
+            Some(name) => self
+                .loops
+                .iter()
+                .rev()
+                .find(|(l, _)| l.as_ref() == Some(name)),
+            None => self.loops.last(),
+        };
+        match (target, label) {
+            (Some(&(_, target)), _) => {
+                self.resolution.loops.insert(span, target);
+            }
+            (None, Some(name)) => self.errors.push(SemanticError::UndefinedLabel {
+                name: name.as_str().into(),
+                span,
+            }),
+            (None, None) => self
+                .errors
+                .push(SemanticError::OutsideLoop { keyword, span }),
+        }
+    }
+
+    fn resolve_scoped(&mut self, statement: &Statement) {
+        self.scopes.push(HashMap::new());
+        self.resolve_statement(statement, SymbolKind::Local);
+        self.scopes.pop();
+    }
+
+    fn resolve_variable(&mut self, name: &str, span: Span) {
+        match self.lookup(name, span) {
+            Some(id) => {
+                self.resolution.uses.insert(span, id);
+            }
+            None => self.errors.push(SemanticError::UndefinedVariable {
+                name: name.into(),
+                span,
+            }),
+        }
+    }
+
+    /// Reports assignments to a const, or to an element or field of one.
+    fn check_assignable(&mut self, target: &Expression) {
+        match &target.kind {
+            ExpressionKind::Index(base, _) | ExpressionKind::Field(base, _) => {
+                self.check_assignable(base)
+            }
+            _ => {
+                let id = match self.resolution.resolve(target.span) {
+                    Some(id) if self.consts.contains(&id) => id,
+                    _ => return,
+                };
+                let symbol = self.resolution.symbol(id);
+                self.errors.push(SemanticError::AssignToConst {
+                    name: symbol.name.as_str().into(),
+                    span: target.span,
+                    defined: symbol.span,
+                });
+            }
+        }
+    }
+
+    fn resolve_expression(&mut self, exp: &Expression) {
+        match &exp.kind {
+            ExpressionKind::Int(_)
+            | ExpressionKind::Float(_)
+            | ExpressionKind::Bool(_)
+            | ExpressionKind::Char(_)
+            | ExpressionKind::MLStr(_) => {}
+            ExpressionKind::Variable(name) | ExpressionKind::VariableRef(name) => {
+                self.resolve_variable(name, exp.span)
+            }
+            ExpressionKind::Assign(name, value) | ExpressionKind::AssignPostfix(name, value) => {
+                self.resolve_expression(value);
+                self.resolve_variable(name, exp.span);
+                self.check_assignable(exp);
+            }
+            ExpressionKind::UnOp(_, operand) => self.resolve_expression(operand),
+            ExpressionKind::Array(elements) => {
+                for element in elements.iter() {
+                    self.resolve_expression(element);
+                }
+            }
+            ExpressionKind::Index(array, index) => {
+                self.resolve_expression(array);
+                self.resolve_expression(index);
+            }
+            ExpressionKind::AssignIndex(array, index, value) => {
+                self.resolve_expression(value);
+                self.resolve_expression(array);
+                self.resolve_expression(index);
+                self.check_assignable(array);
+            }
+            ExpressionKind::StructLiteral(_, fields) => {
+                for (_, value) in fields.iter() {
+                    self.resolve_expression(value);
+                }
+            }
+            ExpressionKind::Field(value, _) | ExpressionKind::Cast(value, _) => {
+                self.resolve_expression(value)
+            }
+            ExpressionKind::AssignField(target, _, value) => {
+                self.resolve_expression(value);
+                self.resolve_expression(target);
+                self.check_assignable(target);
+            }
+            ExpressionKind::Variant(_, _, values) => {
+                for value in values.iter() {
+                    self.resolve_expression(value);
+                }
+            }
+            ExpressionKind::Match(value, arms) => {
+                self.resolve_expression(value);
+                for arm in arms.iter() {
+                    self.scopes.push(HashMap::new());
+                    if let PatternKind::Variant(_, _, bindings) = &arm.pattern.kind {
+                        for binding in bindings.iter().flatten() {
+                            // The type checker fills in the type from the variant.
+                            self.declare(Symbol {
+                                name: binding.name.clone(),
+                                kind: SymbolKind::Local,
+                                t: None,
+                                span: binding.span,
+                            });
+                        }
+                    }
+                    self.resolve_expression(&arm.value);
+                    self.scopes.pop();
+                }
+            }
+            ExpressionKind::BinOp(_, lhs, rhs) => {
+                self.resolve_expression(lhs);
+                self.resolve_expression(rhs);
+            }
+            ExpressionKind::Ternary(condition, then, otherwise) => {
+                self.resolve_expression(condition);
+                self.resolve_expression(then);
+                self.resolve_expression(otherwise);
+            }
+            ExpressionKind::FunctionCall(name, arguments) => {
+                match self.lookup_function(name, exp.span) {
+                    Some(id) => {
+                        self.resolution.uses.insert(exp.span, id);
+                    }
+                    None => self.errors.push(SemanticError::UndefinedFunction {
+                        name: name.as_str().into(),
+                        span: exp.span,
+                    }),
+                }
+                for argument in arguments.iter() {
+                    self.resolve_expression(argument);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use lexer::{tokenize, Parser};
+
+    fn resolve_src(src: &str) -> (Program, Resolution, Vec<SemanticError>) {
+        let (program, errors) = Parser::new(tokenize(src, "").unwrap(), "".into()).parse();
+        assert!(errors.is_empty(), "{:?}", errors);
+        let (resolution, errors) = resolve(&program, &Visibility::new(&program));
+        (program, resolution, errors)
+    }
+
+    #[test]
+    fn inner_blocks_shadow_outer_names() {
+        let src = "fn f(x: int): int {\n    {\n        int x = x\n        return x\n    }\n}";
+        let (program, resolution, errors) = resolve_src(src);
+        assert_eq!(errors, vec![]);
+        let body = match &program.func[0].statements[0].kind {
+            StatementKind::Compound(body) => body,
+            other => panic!("expected a block, got {:?}", other),
+        };
+        let (local, initialiser) = match &body[0].kind {
+            StatementKind::Declare(var, Some(value)) => (var.span, value.span),
+            other => panic!("expected a declaration, got {:?}", other),
+        };
+        let returned = match &body[1].kind {
+            StatementKind::Return(exp) => exp.span,
+            other => panic!("expected a return, got {:?}", other),
+        };
+        let param = resolution
+            .resolve(program.func[0].arguments[0].span)
+            .unwrap();
+        let local = resolution.resolve(local).unwrap();
+        assert_eq!(resolution.symbol(param).kind, SymbolKind::Parameter);
+        assert_eq!(resolution.symbol(local).kind, SymbolKind::Local);
+        assert_eq!(resolution.resolve(initialiser), Some(param));
+        assert_eq!(resolution.resolve(returned), Some(local));
+    }
+
+    #[test]
+    fn reports_duplicate_and_undefined_names() {
+        let src = "const a = 1\nconst a = 2\nfn f(x: int, x: int) {\n    int y = 1\n    int y = z\n    if (true) { int y = 2 }\n}\nfn f() {\n    y = 3\n}";
+        let (_, _, errors) = resolve_src(src);
+        let found: Vec<(&str, usize)> = errors
+            .iter()
+            .map(|e| match e {
+                SemanticError::DuplicateName { name, span, .. } => (&**name, span.line),
+                SemanticError::UndefinedVariable { name, span } => (&**name, span.line),
+                other => panic!("unexpected error {:?}", other),
+            })
+            .collect();
+        assert_eq!(
+            found,
+            vec![("f", 8), ("a", 2), ("x", 3), ("z", 5), ("y", 5), ("y", 9)]
+        );
+    }
+
+    #[test]
+    fn reports_names_reserved_by_the_runtime() {
+        let src = "let malloc = 0\nfn abort() {}\nfn len(s: str): int {\n    return 0\n}";
+        let (_, _, errors) = resolve_src(src);
+        let found: Vec<(&str, usize)> = errors
+            .iter()
+            .map(|e| match e {
+                SemanticError::ReservedName { name, span } => (&**name, span.line),
+                other => panic!("unexpected error {:?}", other),
+            })
+            .collect();
+        // Builtins such as `len` may be replaced.
+        assert_eq!(found, vec![("abort", 2), ("malloc", 1)]);
+    }
+
+    #[test]
+    fn jumps_resolve_to_their_loop() {
+        let src = "fn f() {\n    outer: while (true) {\n        while (true) {\n            break outer\n            continue\n        }\n    }\n}";
+        let (program, resolution, errors) = resolve_src(src);
+        assert_eq!(errors, vec![]);
+        let outer = match &program.func[0].statements[0].kind {
+            StatementKind::Labeled(_, outer) => outer,
+            other => panic!("expected a labelled loop, got {:?}", other),
+        };
+        let inner = match &outer.kind {
+            StatementKind::While(_, body) => match &body.kind {
+                StatementKind::Compound(body) => &body[0],
+                other => panic!("expected a block, got {:?}", other),
+            },
+            other => panic!("expected a loop, got {:?}", other),
+        };
+        let jumps = match &inner.kind {
+            StatementKind::While(_, body) => match &body.kind {
+                StatementKind::Compound(body) => body,
+                other => panic!("expected a block, got {:?}", other),
+            },
+            other => panic!("expected a loop, got {:?}", other),
+        };
+        assert_eq!(resolution.loop_target(jumps[0].span), Some(outer.span));
+        assert_eq!(resolution.loop_target(jumps[1].span), Some(inner.span));
+    }
+}
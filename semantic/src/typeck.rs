@@ -0,0 +1,1175 @@
+use crate::builtins::builtin;
+use crate::errors::SemanticError;
+use crate::resolve::{Resolution, SymbolId, SymbolKind};
+use lexer::{
+    BinOp, Enum, Expression, ExpressionKind, Function, Global, MatchArm, Pattern, PatternKind,
+    Program, Span, Statement, StatementKind, Struct, Type, UnOp, Variable,
+};
+use std::collections::HashMap;
+
+/// Walks a resolved program and checks that every expression is used at a
+/// type it can have. Expressions whose type could not be worked out (because
+/// an error was already reported inside them) are typed `None`, which is
+/// accepted everywhere so that one mistake is only reported once.
+struct TypeChecker<'a, 'r> {
+    resolution: &'r mut Resolution,
+    /// The parameter types of every function, by the function's symbol.
+    signatures: HashMap<SymbolId, Vec<Type>>,
+    structs: HashMap<&'a str, &'a Struct>,
+    enums: HashMap<&'a str, &'a Enum>,
+    /// The globals not checked yet. A global is checked the first time it is
+    /// used, so that its type is known wherever it is read.
+    globals: HashMap<SymbolId, &'a Global>,
+    /// The `[]`s whose element type is not known yet. It is known once one
+    /// is stored somewhere that has an array type.
+    empty_arrays: Vec<Span>,
+    current: Option<&'a Function>,
+    errors: Vec<SemanticError>,
+}
+
+/// Type checks `program`, recording the inferred type of every `let` and
+/// `const` in `resolution` and returning every error found.
+pub fn check(program: &Program, resolution: &mut Resolution) -> Vec<SemanticError> {
+    let mut checker = TypeChecker {
+        resolution,
+        signatures: HashMap::new(),
+        structs: HashMap::new(),
+        enums: HashMap::new(),
+        globals: HashMap::new(),
+        empty_arrays: Vec::new(),
+        current: None,
+        errors: Vec::new(),
+    };
+    checker.check_program(program);
+    checker.errors
+}
+
+/// `str`, `mlstr` and `char` all hold text and can be used interchangeably.
+fn is_string(t: &Type) -> bool {
+    matches!(t, Type::Str | Type::Mlstr | Type::Char)
+}
+
+fn compatible(expected: &Type, found: &Type) -> bool {
+    expected == found || (is_string(expected) && is_string(found))
+}
+
+/// Whether every path through `statements` ends in a `return`.
+fn always_returns(statements: &[Statement]) -> bool {
+    statements.iter().any(|s| match &s.kind {
+        StatementKind::Return(_) => true,
+        StatementKind::Compound(block) => always_returns(block),
+        StatementKind::If(_, then, Some(otherwise)) => {
+            always_returns(std::slice::from_ref(then))
+                && always_returns(std::slice::from_ref(otherwise))
+        }
+        _ => false,
+    })
+}
+
+impl<'a, 'r> TypeChecker<'a, 'r> {
+    fn check_program(&mut self, program: &'a Program) {
+        for declaration in program.structs.iter() {
+            if let Some(previous) = self.structs.get(declaration.name.as_str()) {
+                self.errors.push(SemanticError::DuplicateName {
+                    name: declaration.name.as_str().into(),
+                    span: declaration.span,
+                    previous: previous.span,
+                });
+                continue;
+            }
+            self.structs.insert(&declaration.name, declaration);
+        }
+        for declaration in program.enums.iter() {
+            let previous = match self.structs.get(declaration.name.as_str()) {
+                Some(previous) => Some(previous.span),
+                None => self.enums.get(declaration.name.as_str()).map(|e| e.span),
+            };
+            if let Some(previous) = previous {
+                self.errors.push(SemanticError::DuplicateName {
+                    name: declaration.name.as_str().into(),
+                    span: declaration.span,
+                    previous,
+                });
+                continue;
+            }
+            self.enums.insert(&declaration.name, declaration);
+        }
+        for declaration in program.structs.iter() {
+            self.check_struct(declaration);
+        }
+        for declaration in program.enums.iter() {
+            self.check_enum(declaration);
+        }
+        for function in program.func.iter() {
+            for argument in function.arguments.iter() {
+                if let Some(t) = &argument.t {
+                    self.check_type(t, argument.span);
+                }
+            }
+            self.check_type(&function.return_type, function.span);
+            if let Some(id) = self.resolution.resolve(function.span) {
+                // Parameters always carry a type, see `Parser::parse_arguments`.
+                let arguments = function
+                    .arguments
+                    .iter()
+                    .map(|a| a.t.clone().unwrap_or(Type::Void))
+                    .collect();
+                self.signatures.insert(id, arguments);
+            }
+        }
+        for global in program.globals.iter() {
+            if let Some(id) = self.resolution.resolve(global.var.span) {
+                self.globals.insert(id, global);
+            }
+        }
+        for global in program.globals.iter() {
+            match self.resolution.resolve(global.var.span) {
+                Some(id) => self.check_global(id),
+                // A duplicate, which has no symbol of its own.
+                None => self.check_declaration(&global.var, Some(&global.value)),
+            }
+            self.check_empty_arrays();
+        }
+        for function in program.func.iter() {
+            self.check_function(function);
+            self.check_empty_arrays();
+        }
+    }
+
+    /// Reports every `[]` that was not used where an array type is expected.
+    fn check_empty_arrays(&mut self) {
+        for span in self.empty_arrays.drain(..) {
+            self.errors.push(SemanticError::UntypedEmptyArray { span });
+        }
+    }
+
+    fn check_function(&mut self, function: &'a Function) {
+        if function.name == "main" {
+            self.check_entry_point(function);
+        }
+        // Neither backend has an executor to run them on.
+        if function.is_async {
+            self.errors.push(SemanticError::UnsupportedAsync {
+                name: function.name.as_str().into(),
+                span: function.span,
+            });
+        }
+        self.current = Some(function);
+        for statement in function.statements.iter() {
+            self.check_statement(statement);
+        }
+        self.current = None;
+
+        if function.return_type != Type::Void && !always_returns(&function.statements) {
+            self.errors.push(SemanticError::MissingReturn {
+                fnname: function.name.as_str().into(),
+                expected: function.return_type.clone(),
+                span: function.span,
+            });
+        }
+    }
+
+    fn check_struct(&mut self, declaration: &'a Struct) {
+        let mut seen: HashMap<&str, Span> = HashMap::new();
+        for field in declaration.fields.iter() {
+            if let Some(&previous) = seen.get(field.name.as_str()) {
+                self.errors.push(SemanticError::DuplicateName {
+                    name: field.name.as_str().into(),
+                    span: field.span,
+                    previous,
+                });
+            }
+            seen.insert(&field.name, field.span);
+            if let Some(t) = &field.t {
+                self.check_type(t, field.span);
+            }
+        }
+        self.check_recursion(&declaration.name, declaration.span);
+    }
+
+    fn check_enum(&mut self, declaration: &'a Enum) {
+        let mut seen: HashMap<&str, Span> = HashMap::new();
+        for variant in declaration.variants.iter() {
+            if let Some(&previous) = seen.get(variant.name.as_str()) {
+                self.errors.push(SemanticError::DuplicateName {
+                    name: variant.name.as_str().into(),
+                    span: variant.span,
+                    previous,
+                });
+            }
+            seen.insert(&variant.name, variant.span);
+            for t in variant.fields.iter() {
+                self.check_type(t, variant.span);
+            }
+        }
+        self.check_recursion(&declaration.name, declaration.span);
+    }
+
+    fn check_recursion(&mut self, name: &str, span: Span) {
+        let members = self.members(name);
+        if members
+            .iter()
+            .any(|t| self.contains(t, name, &mut Vec::new()))
+        {
+            self.errors.push(SemanticError::RecursiveType {
+                name: name.into(),
+                span,
+            });
+        }
+    }
+
+    /// The types stored directly in the struct or enum `name`.
+    fn members(&self, name: &str) -> Vec<&'a Type> {
+        if let Some(declaration) = self.structs.get(name) {
+            declaration
+                .fields
+                .iter()
+                .filter_map(|f| f.t.as_ref())
+                .collect()
+        } else if let Some(declaration) = self.enums.get(name) {
+            declaration
+                .variants
+                .iter()
+                .flat_map(|v| v.fields.iter())
+                .collect()
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Whether a value of type `t` stores a `name` inside it. `visited` holds
+    /// the types already searched, so that cycles not involving `name` still end.
+    fn contains(&self, t: &Type, name: &str, visited: &mut Vec<String>) -> bool {
+        match t {
+            Type::Array(element, _) => self.contains(element, name, visited),
+            Type::Named(other) if other == name => true,
+            Type::Named(other) if !visited.contains(other) => {
+                visited.push(other.clone());
+                self.members(other)
+                    .iter()
+                    .any(|t| self.contains(t, name, visited))
+            }
+            _ => false,
+        }
+    }
+
+    /// Reports the struct and enum names in `t` that are not declared.
+    fn check_type(&mut self, t: &Type, span: Span) {
+        match t {
+            Type::Array(element, _) => self.check_type(element, span),
+            Type::Named(name)
+                if !self.structs.contains_key(name.as_str())
+                    && !self.enums.contains_key(name.as_str()) =>
+            {
+                self.errors.push(SemanticError::UndefinedType {
+                    name: name.as_str().into(),
+                    span,
+                });
+            }
+            _ => {}
+        }
+    }
+
+    /// `main` is called by the operating system, so it takes no parameters
+    /// (see the `argc` and `argv` builtins) and its result is the exit code.
+    fn check_entry_point(&mut self, function: &Function) {
+        let reason = if !function.arguments.is_empty() {
+            "`main` cannot take parameters, use `argc()` and `argv(i)` instead"
+        } else if !matches!(function.return_type, Type::Int | Type::Void) {
+            "`main` must return `int` or nothing"
+        } else {
+            return;
+        };
+        self.errors.push(SemanticError::InvalidEntryPoint {
+            reason: reason.into(),
+            span: function.span,
+        });
+    }
+
+    fn expect(&mut self, expected: &Type, found: Option<Type>, span: Span) {
+        if let Some(found) = found {
+            if !compatible(expected, &found) {
+                self.errors.push(SemanticError::MismatchedTypes {
+                    expected: expected.clone(),
+                    found,
+                    span,
+                });
+            }
+        }
+    }
+
+    /// The type of `value`, `found`, when it is stored somewhere of type
+    /// `expected`. That is what gives a `[]` its element type.
+    fn value_type(
+        &mut self,
+        expected: &Type,
+        found: Option<Type>,
+        value: &Expression,
+    ) -> Option<Type> {
+        match (&value.kind, expected) {
+            (ExpressionKind::Array(elements), Type::Array(element, _)) if elements.is_empty() => {
+                self.empty_arrays.retain(|&span| span != value.span);
+                self.resolution
+                    .set_empty_array_element(value.span, element.as_ref().clone());
+                Some(Type::Array(element.clone(), 0))
+            }
+            _ => found,
+        }
+    }
+
+    /// Checks that `value`, of type `found`, can be stored somewhere of type
+    /// `expected`.
+    fn expect_value(&mut self, expected: &Type, found: Option<Type>, value: &Expression) {
+        let found = self.value_type(expected, found, value);
+        self.expect(expected, found, value.span);
+    }
+
+    /// Checks a `let`, `const` or typed declaration, inferring the type of
+    /// the variable from `value` when none is given.
+    fn check_declaration(&mut self, var: &Variable, value: Option<&'a Expression>) {
+        if let Some(t) = &var.t {
+            self.check_type(t, var.span);
+        }
+        let found = value.and_then(|exp| self.check_expression(exp));
+        match (&var.t, value) {
+            (Some(t), Some(exp)) => self.expect_value(t, found, exp),
+            (Some(_), None) => {}
+            (None, _) => {
+                if let Some(id) = self.resolution.resolve(var.span) {
+                    self.resolution.symbol_mut(id).t = found;
+                }
+            }
+        }
+    }
+
+    /// Checks the global `id` if it has not been checked yet. It is removed
+    /// first, so a global that refers to itself is typed `None` there rather
+    /// than checked forever.
+    fn check_global(&mut self, id: SymbolId) {
+        if let Some(global) = self.globals.remove(&id) {
+            self.check_declaration(&global.var, Some(&global.value));
+        }
+    }
+
+    fn check_statement(&mut self, statement: &'a Statement) {
+        match &statement.kind {
+            StatementKind::Declare(var, value) => self.check_declaration(var, value.as_ref()),
+            StatementKind::Return(exp) => {
+                let found = self.check_expression(exp);
+                let found = match self.current {
+                    Some(function) => self.value_type(&function.return_type, found, exp),
+                    None => found,
+                };
+                if let (Some(function), Some(found)) = (self.current, found) {
+                    if !compatible(&function.return_type, &found) {
+                        self.errors.push(SemanticError::MismatchedReturn {
+                            fnname: function.name.as_str().into(),
+                            expected: function.return_type.clone(),
+                            found,
+                            span: exp.span,
+                        });
+                    }
+                }
+            }
+            StatementKind::If(condition, then, otherwise) => {
+                let found = self.check_expression(condition);
+                self.expect(&Type::Bool, found, condition.span);
+                self.check_statement(then);
+                if let Some(otherwise) = otherwise {
+                    self.check_statement(otherwise);
+                }
+            }
+            StatementKind::While(condition, body) => {
+                let found = self.check_expression(condition);
+                self.expect(&Type::Bool, found, condition.span);
+                self.check_statement(body);
+            }
+            StatementKind::For(init, condition, step, body) => {
+                if let Some(init) = init {
+                    self.check_statement(init);
+                }
+                if let Some(condition) = condition {
+                    let found = self.check_expression(condition);
+                    self.expect(&Type::Bool, found, condition.span);
+                }
+                if let Some(step) = step {
+                    self.check_expression(step);
+                }
+                self.check_statement(body);
+            }
+            StatementKind::ForRange(_, from, to, body) => {
+                for bound in [from, to].iter() {
+                    let found = self.check_expression(bound);
+                    self.expect(&Type::Int, found, bound.span);
+                }
+                self.check_statement(body);
+            }
+            StatementKind::Labeled(_, inner) => self.check_statement(inner),
+            StatementKind::Break(_) | StatementKind::Continue(_) => {}
+            StatementKind::Exp(exp) => {
+                self.check_expression(exp);
+            }
+            StatementKind::Compound(statements) => {
+                for statement in statements.iter() {
+                    self.check_statement(statement);
+                }
+            }
+        }
+    }
+
+    /// The type of the variable used at `span`. Undefined names were already
+    /// reported by the resolver.
+    fn variable_type(&mut self, span: Span) -> Option<Type> {
+        let id = self.resolution.resolve(span)?;
+        self.check_global(id);
+        self.resolution.symbol(id).t.clone()
+    }
+
+    fn check_expression(&mut self, exp: &'a Expression) -> Option<Type> {
+        match &exp.kind {
+            ExpressionKind::Int(_) => Some(Type::Int),
+            ExpressionKind::Float(_) => Some(Type::Float),
+            ExpressionKind::Bool(_) => Some(Type::Bool),
+            ExpressionKind::Char(_) => Some(Type::Str),
+            ExpressionKind::MLStr(_) => Some(Type::Mlstr),
+            ExpressionKind::Variable(_) | ExpressionKind::VariableRef(_) => {
+                self.variable_type(exp.span)
+            }
+            ExpressionKind::Assign(_, value) | ExpressionKind::AssignPostfix(_, value) => {
+                let found = self.check_expression(value);
+                let t = self.variable_type(exp.span)?;
+                self.expect_value(&t, found, value);
+                Some(t)
+            }
+            ExpressionKind::UnOp(op, operand) => {
+                let found = self.check_expression(operand)?;
+                let expected = match op {
+                    UnOp::Negation if found == Type::Float => Type::Float,
+                    UnOp::Negation | UnOp::BitComp => Type::Int,
+                    UnOp::LogicalNeg => Type::Bool,
+                };
+                self.operand(&op.to_string(), &expected, found, operand.span)
+            }
+            ExpressionKind::BinOp(op, lhs, rhs) => self.check_binop(op, lhs, rhs, exp.span),
+            ExpressionKind::Array(elements) => {
+                let mut element_type: Option<Type> = None;
+                for element in elements.iter() {
+                    let found = self.check_expression(element);
+                    match element_type.clone() {
+                        Some(t) => self.expect_value(&t, found, element),
+                        None => element_type = found,
+                    }
+                }
+                if elements.is_empty() {
+                    // Its type is only known from where it is used.
+                    self.empty_arrays.push(exp.span);
+                }
+                element_type.map(|t| Type::Array(Box::new(t), elements.len() as u64))
+            }
+            ExpressionKind::Index(array, index) => self.check_index(array, index),
+            ExpressionKind::AssignIndex(array, index, value) => {
+                let found = self.check_expression(value);
+                let t = self.check_index(array, index)?;
+                self.expect_value(&t, found, value);
+                Some(t)
+            }
+            ExpressionKind::StructLiteral(name, fields) => {
+                self.check_struct_literal(name, fields, exp.span)
+            }
+            ExpressionKind::Field(value, name) => self.check_field(value, name, exp.span),
+            ExpressionKind::Variant(name, variant, values) => {
+                let found: Vec<Option<Type>> =
+                    values.iter().map(|v| self.check_expression(v)).collect();
+                let fields = self.variant_fields(name, variant, exp.span)?;
+                if fields.len() != values.len() {
+                    self.errors.push(SemanticError::ArgumentCount {
+                        fnname: format!("{}::{}", name, variant).into(),
+                        expected: fields.len(),
+                        found: values.len(),
+                        span: exp.span,
+                    });
+                } else {
+                    for ((t, found), value) in fields.iter().zip(found).zip(values.iter()) {
+                        self.expect_value(t, found, value);
+                    }
+                }
+                Some(Type::Named(name.clone()))
+            }
+            ExpressionKind::Match(value, arms) => self.check_match(value, arms, exp.span),
+            ExpressionKind::Cast(value, t) => {
+                let is_number = |t: &Type| matches!(t, Type::Int | Type::Float);
+                match self.check_expression(value) {
+                    Some(found) if !is_number(&found) || !is_number(t) => {
+                        self.errors.push(SemanticError::InvalidCast {
+                            from: found,
+                            to: t.clone(),
+                            span: exp.span,
+                        })
+                    }
+                    _ => {}
+                }
+                Some(t.clone())
+            }
+            ExpressionKind::AssignField(target, name, value) => {
+                let found = self.check_expression(value);
+                let t = self.check_field(target, name, exp.span)?;
+                self.expect_value(&t, found, value);
+                Some(t)
+            }
+            ExpressionKind::Ternary(condition, then, otherwise) => {
+                let found = self.check_expression(condition);
+                self.expect(&Type::Bool, found, condition.span);
+                let then_type = self.check_expression(then);
+                let otherwise_type = self.check_expression(otherwise);
+                match then_type {
+                    Some(t) => {
+                        self.expect_value(&t, otherwise_type, otherwise);
+                        Some(t)
+                    }
+                    None => otherwise_type,
+                }
+            }
+            ExpressionKind::FunctionCall(name, arguments) => {
+                let found: Vec<Option<Type>> =
+                    arguments.iter().map(|a| self.check_expression(a)).collect();
+                let id = self.resolution.resolve(exp.span)?;
+                let symbol = self.resolution.symbol(id);
+                let return_type = symbol.t.clone();
+                let expected = match symbol.kind {
+                    SymbolKind::Builtin => {
+                        let builtin = builtin(name)?;
+                        let in_main = self.current.is_some_and(|f| f.name == "main");
+                        if builtin.entry_only && !in_main {
+                            self.errors.push(SemanticError::OutsideEntryPoint {
+                                name: name.as_str().into(),
+                                span: exp.span,
+                            });
+                        }
+                        builtin.arguments.to_vec()
+                    }
+                    _ => self.signatures.get(&id).cloned().unwrap_or_default(),
+                };
+                if expected.len() != arguments.len() {
+                    self.errors.push(SemanticError::ArgumentCount {
+                        fnname: name.as_str().into(),
+                        expected: expected.len(),
+                        found: arguments.len(),
+                        span: exp.span,
+                    });
+                    return return_type;
+                }
+                for ((t, found), argument) in expected.iter().zip(found).zip(arguments.iter()) {
+                    self.expect_value(t, found, argument);
+                }
+                return_type
+            }
+        }
+    }
+
+    /// The type of `array[index]`. Indices that are known at compile time are
+    /// checked against the length here, the rest when the program runs.
+    fn check_index(&mut self, array: &'a Expression, index: &'a Expression) -> Option<Type> {
+        let array_type = self.check_expression(array);
+        let index_type = self.check_expression(index);
+        self.expect(&Type::Int, index_type, index.span);
+        match array_type? {
+            Type::Array(element, len) => {
+                if let ExpressionKind::Int(i) = index.kind {
+                    if i >= len {
+                        self.errors.push(SemanticError::IndexOutOfBounds {
+                            index: i,
+                            len,
+                            span: index.span,
+                        });
+                    }
+                }
+                Some(*element)
+            }
+            found => {
+                self.errors.push(SemanticError::InvalidOperand {
+                    op: "[]".into(),
+                    found,
+                    span: array.span,
+                });
+                None
+            }
+        }
+    }
+
+    /// The payload types of `name::variant`, reporting it if there is no such variant.
+    fn variant_fields(&mut self, name: &str, variant: &str, span: Span) -> Option<&'a [Type]> {
+        let declaration = match self.enums.get(name) {
+            Some(&declaration) => declaration,
+            None => {
+                self.errors.push(SemanticError::UndefinedType {
+                    name: name.into(),
+                    span,
+                });
+                return None;
+            }
+        };
+        match declaration.variants.iter().find(|v| v.name == variant) {
+            Some(variant) => Some(&variant.fields),
+            None => {
+                self.errors.push(SemanticError::UnknownVariant {
+                    ty: name.into(),
+                    name: variant.into(),
+                    span,
+                });
+                None
+            }
+        }
+    }
+
+    /// Checks every arm against the type of `value`, and that together they
+    /// cover every value it can have. The arms must all have the same type,
+    /// which is the type of the `match`.
+    fn check_match(
+        &mut self,
+        value: &'a Expression,
+        arms: &'a [MatchArm],
+        span: Span,
+    ) -> Option<Type> {
+        let t = self.check_expression(value);
+        let mut result: Option<Type> = None;
+        for arm in arms.iter() {
+            self.check_pattern(&arm.pattern, t.as_ref());
+            let found = self.check_expression(&arm.value);
+            match result.clone() {
+                Some(expected) => self.expect(&expected, found, arm.value.span),
+                None => result = found,
+            }
+        }
+        let patterns: Vec<&PatternKind> = arms.iter().map(|a| &a.pattern.kind).collect();
+        if let Some(missing) = t.and_then(|t| self.missing_patterns(&t, &patterns)) {
+            self.errors.push(SemanticError::NonExhaustiveMatch {
+                missing: missing.into(),
+                span,
+            });
+        }
+        result
+    }
+
+    /// Checks that `pattern` can match a value of type `t`, and gives the
+    /// names it binds their types.
+    fn check_pattern(&mut self, pattern: &Pattern, t: Option<&Type>) {
+        let found = match &pattern.kind {
+            PatternKind::Wildcard => return,
+            PatternKind::Int(_) => Type::Int,
+            PatternKind::Bool(_) => Type::Bool,
+            PatternKind::Str(_) => Type::Str,
+            PatternKind::Variant(name, variant, bindings) => {
+                let fields = match self.variant_fields(name, variant, pattern.span) {
+                    Some(fields) => fields,
+                    None => return,
+                };
+                if fields.len() != bindings.len() {
+                    self.errors.push(SemanticError::ArgumentCount {
+                        fnname: format!("{}::{}", name, variant).into(),
+                        expected: fields.len(),
+                        found: bindings.len(),
+                        span: pattern.span,
+                    });
+                }
+                for (binding, field) in bindings.iter().zip(fields.iter()) {
+                    let id = binding
+                        .as_ref()
+                        .and_then(|b| self.resolution.resolve(b.span));
+                    if let Some(id) = id {
+                        self.resolution.symbol_mut(id).t = Some(field.clone());
+                    }
+                }
+                Type::Named(name.clone())
+            }
+        };
+        if let Some(t) = t {
+            self.expect(t, Some(found), pattern.span);
+        }
+    }
+
+    /// The values of type `t` that none of `patterns` match, written as
+    /// patterns, or `None` if every value is matched.
+    fn missing_patterns(&self, t: &Type, patterns: &[&PatternKind]) -> Option<String> {
+        if patterns.iter().any(|p| matches!(p, PatternKind::Wildcard)) {
+            return None;
+        }
+        let missing: Vec<String> = match t {
+            Type::Bool => [true, false]
+                .iter()
+                .filter(|&&b| !patterns.contains(&&PatternKind::Bool(b)))
+                .map(|b| b.to_string())
+                .collect(),
+            Type::Named(name) if self.enums.contains_key(name.as_str()) => self.enums[name.as_str()]
+                .variants
+                .iter()
+                .filter(|variant| {
+                    !patterns.iter().any(|p| {
+                        matches!(p, PatternKind::Variant(e, v, _) if e == name && v == &variant.name)
+                    })
+                })
+                .map(|variant| format!("{}::{}", name, variant.name))
+                .collect(),
+            _ => vec!["_".to_string()],
+        };
+        if missing.is_empty() {
+            None
+        } else {
+            Some(missing.join("`, `"))
+        }
+    }
+
+    /// Checks that `name { ... }` sets every field of the struct exactly once.
+    fn check_struct_literal(
+        &mut self,
+        name: &str,
+        fields: &'a [(String, Expression)],
+        span: Span,
+    ) -> Option<Type> {
+        let found: Vec<Option<Type>> = fields
+            .iter()
+            .map(|(_, value)| self.check_expression(value))
+            .collect();
+        let declaration = match self.structs.get(name) {
+            Some(&declaration) => declaration,
+            None => {
+                self.errors.push(SemanticError::UndefinedType {
+                    name: name.into(),
+                    span,
+                });
+                return None;
+            }
+        };
+        let mut seen: HashMap<&str, Span> = HashMap::new();
+        for ((field, value), found) in fields.iter().zip(found) {
+            if let Some(&previous) = seen.get(field.as_str()) {
+                self.errors.push(SemanticError::DuplicateName {
+                    name: field.as_str().into(),
+                    span: value.span,
+                    previous,
+                });
+            }
+            seen.insert(field, value.span);
+            match declaration.fields.iter().find(|f| &f.name == field) {
+                Some(expected) => {
+                    if let Some(t) = &expected.t {
+                        self.expect_value(t, found, value);
+                    }
+                }
+                None => self.errors.push(SemanticError::UnknownField {
+                    ty: name.into(),
+                    name: field.as_str().into(),
+                    span: value.span,
+                }),
+            }
+        }
+        for field in declaration.fields.iter() {
+            if !seen.contains_key(field.name.as_str()) {
+                self.errors.push(SemanticError::MissingField {
+                    ty: name.into(),
+                    name: field.name.as_str().into(),
+                    span,
+                });
+            }
+        }
+        Some(Type::Named(name.to_string()))
+    }
+
+    /// The type of `value.name`.
+    fn check_field(&mut self, value: &'a Expression, name: &str, span: Span) -> Option<Type> {
+        match self.check_expression(value)? {
+            Type::Named(ty) => {
+                let declaration = match self.structs.get(ty.as_str()) {
+                    Some(&declaration) => declaration,
+                    None => {
+                        // Undeclared types were reported where they were named.
+                        if self.enums.contains_key(ty.as_str()) {
+                            self.errors.push(SemanticError::NoFields {
+                                ty: ty.as_str().into(),
+                                name: name.into(),
+                                span,
+                            });
+                        }
+                        return None;
+                    }
+                };
+                match declaration.fields.iter().find(|f| f.name == name) {
+                    Some(field) => field.t.clone(),
+                    None => {
+                        self.errors.push(SemanticError::UnknownField {
+                            ty: ty.as_str().into(),
+                            name: name.into(),
+                            span,
+                        });
+                        None
+                    }
+                }
+            }
+            found => {
+                self.errors.push(SemanticError::InvalidOperand {
+                    op: ".".into(),
+                    found,
+                    span: value.span,
+                });
+                None
+            }
+        }
+    }
+
+    /// Reports `found` if it is not `expected`, otherwise returns the type of the operation.
+    fn operand(&mut self, op: &str, expected: &Type, found: Type, span: Span) -> Option<Type> {
+        if compatible(expected, &found) {
+            Some(found)
+        } else {
+            self.errors.push(SemanticError::InvalidOperand {
+                op: op.into(),
+                found,
+                span,
+            });
+            None
+        }
+    }
+
+    fn check_binop(
+        &mut self,
+        op: &BinOp,
+        lhs: &'a Expression,
+        rhs: &'a Expression,
+        span: Span,
+    ) -> Option<Type> {
+        let lhs_type = self.check_expression(lhs);
+        let rhs_type = self.check_expression(rhs);
+        let symbol = op.to_string();
+        match op {
+            BinOp::Comma => rhs_type,
+            // Only scalars and strings compare; structs, enums and arrays
+            // would have to be compared field by field.
+            BinOp::Equal | BinOp::NotEqual => {
+                match lhs_type {
+                    Some(t)
+                        if matches!(t, Type::Int | Type::Float | Type::Bool) || is_string(&t) =>
+                    {
+                        self.expect(&t, rhs_type, rhs.span)
+                    }
+                    Some(found) => self.errors.push(SemanticError::InvalidOperand {
+                        op: symbol.into(),
+                        found,
+                        span: lhs.span,
+                    }),
+                    None => {}
+                }
+                Some(Type::Bool)
+            }
+            // `+` on two strings concatenates them.
+            BinOp::Addition
+                if lhs_type.as_ref().is_some_and(is_string)
+                    && rhs_type.as_ref().is_none_or(is_string) =>
+            {
+                self.resolution.set_concatenation(span);
+                Some(Type::Str)
+            }
+            BinOp::And | BinOp::Or => {
+                if let Some(t) = lhs_type {
+                    self.operand(&symbol, &Type::Bool, t, lhs.span);
+                }
+                if let Some(t) = rhs_type {
+                    self.operand(&symbol, &Type::Bool, t, rhs.span);
+                }
+                Some(Type::Bool)
+            }
+            _ => {
+                let comparison = matches!(
+                    op,
+                    BinOp::LessThan
+                        | BinOp::LessThanOrEqual
+                        | BinOp::GreaterThan
+                        | BinOp::GreaterThanOrEqual
+                );
+                let arithmetic = matches!(
+                    op,
+                    BinOp::Addition | BinOp::Subtraction | BinOp::Multiplication | BinOp::Division
+                );
+                // Floats are never converted implicitly, so both sides have to be floats.
+                let float = Some(Type::Float);
+                if (comparison || arithmetic) && (lhs_type == float || rhs_type == float) {
+                    self.expect(&Type::Float, lhs_type, lhs.span);
+                    self.expect(&Type::Float, rhs_type, rhs.span);
+                    return Some(if comparison { Type::Bool } else { Type::Float });
+                }
+                if let Some(t) = lhs_type {
+                    self.operand(&symbol, &Type::Int, t, lhs.span);
+                }
+                if let Some(t) = rhs_type {
+                    self.operand(&symbol, &Type::Int, t, rhs.span);
+                }
+                Some(if comparison { Type::Bool } else { Type::Int })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use lexer::{tokenize, Parser};
+
+    fn check_src(src: &str) -> Vec<SemanticError> {
+        let (mut program, errors) = Parser::new(tokenize(src, "").unwrap(), "".into()).parse();
+        assert!(errors.is_empty(), "{:?}", errors);
+        crate::analyze(&mut program).1
+    }
+
+    fn codes(src: &str) -> Vec<&'static str> {
+        check_src(src).iter().map(|e| e.code()).collect()
+    }
+
+    #[test]
+    fn accepts_well_typed_program() {
+        let src = "const greeting = \"hi\"\nfn add(a: int, b: int): int {\n    return a + b\n}\nfn isFour(): bool {\n    int x = add(1, 2)\n    let s = greeting\n    if (x > 2 && !false) {\n        x += 1\n    }\n    return x == 4\n}";
+        assert_eq!(check_src(src), vec![]);
+    }
+
+    #[test]
+    fn reports_mismatched_declaration() {
+        let errors = check_src("fn main() {\n    int x = \"hi\"\n}");
+        match &errors[..] {
+            [SemanticError::MismatchedTypes {
+                expected: Type::Int,
+                found: Type::Str,
+                span,
+            }] => assert_eq!((span.line, span.col), (2, 13)),
+            other => panic!("unexpected errors {:?}", other),
+        }
+    }
+
+    #[test]
+    fn checks_returns_conditions_and_calls() {
+        assert_eq!(codes("fn f(): bool {\n    return 1\n}"), vec!["Z0017"]);
+        assert_eq!(codes("fn f(): int {\n    int x = 1\n}"), vec!["Z0018"]);
+        assert_eq!(codes("fn f() {\n    while (1) { }\n}"), vec!["Z0012"]);
+        assert_eq!(
+            codes("fn g(a: int): int {\n    return a\n}\nfn f() {\n    g(1, 2)\n    g(true)\n}"),
+            vec!["Z0015", "Z0012"]
+        );
+        assert_eq!(codes("fn f() {\n    int x = 1 + true\n}"), vec!["Z0016"]);
+        assert_eq!(codes("fn f() {\n    y = h()\n}"), vec!["Z0014", "Z0013"]);
+    }
+
+    #[test]
+    fn types_globals_before_they_are_declared() {
+        assert_eq!(codes("const a = b + 1\nconst b = \"x\""), vec!["Z0016"]);
+        assert_eq!(
+            codes("const a = b * 2\nconst b = 1\nfn f(): str {\n    return a\n}"),
+            vec!["Z0017"]
+        );
+        assert_eq!(codes("const a = b\nconst b = a + 1"), vec!["Z0038"]);
+    }
+
+    #[test]
+    fn checks_break_and_continue_targets() {
+        let src = "fn f() {\n    outer: while (true) {\n        for i in 0..3 {\n            if (i == 1) { continue outer }\n            break\n        }\n    }\n}";
+        assert_eq!(check_src(src), vec![]);
+        assert_eq!(codes("fn f() {\n    break\n}"), vec!["Z0022"]);
+        assert_eq!(
+            codes("fn f() {\n    while (true) {\n        continue outer\n    }\n}"),
+            vec!["Z0023"]
+        );
+        // A label only applies inside its own loop.
+        assert_eq!(
+            codes("fn f() {\n    outer: while (true) { }\n    while (true) { break outer }\n}"),
+            vec!["Z0023"]
+        );
+    }
+
+    #[test]
+    fn checks_arrays() {
+        let src = "fn sum(a: [int; 3]): int {\n    int total = 0\n    for i in 0..3 { total += a[i] }\n    return total\n}\nfn f(): int {\n    let grid = [[1, 2], [3, 4]]\n    grid[1][0] = sum([1, 2, 3])\n    return grid[1][1]\n}";
+        assert_eq!(check_src(src), vec![]);
+        assert_eq!(codes("fn f() {\n    let a = [1, true]\n}"), vec!["Z0012"]);
+        assert_eq!(
+            codes("fn f() {\n    let a = [1, 2]\n    a[true] = 3\n}"),
+            vec!["Z0012"]
+        );
+        assert_eq!(
+            codes("fn f() {\n    int x = 1\n    int y = x[0]\n}"),
+            vec!["Z0016"]
+        );
+        assert_eq!(codes("fn f() {\n    bool b = [1] == [1]\n}"), vec!["Z0016"]);
+        assert_eq!(
+            codes("fn f() {\n    let a = [1, 2]\n    a[2] = 3\n}"),
+            vec!["Z0024"]
+        );
+        assert_eq!(
+            codes("fn f(a: [int; 2]) {\n}\nfn g() {\n    f([1, 2, 3])\n}"),
+            vec!["Z0012"]
+        );
+    }
+
+    #[test]
+    fn types_empty_arrays_from_where_they_are_used() {
+        let src = "const none: [str; 0] = []\nfn g(a: [int; 0]): [int; 0] {\n    return []\n}\nfn f() {\n    let a: [bool; 0] = []\n    g([])\n}";
+        assert_eq!(check_src(src), vec![]);
+        assert_eq!(
+            codes("fn f() {\n    let a: [int; 3] = []\n}"),
+            vec!["Z0012"]
+        );
+        assert_eq!(codes("fn f() {\n    let b = [[1], []]\n}"), vec!["Z0012"]);
+        assert_eq!(codes("fn f() {\n    let a = []\n}"), vec!["Z0045"]);
+        assert_eq!(codes("fn f() {\n    int x = []\n}"), vec!["Z0045"]);
+    }
+
+    #[test]
+    fn checks_structs() {
+        let src = "struct Point { x: int, y: int }\nstruct Line { from: Point, to: Point }\nfn length(l: Line): int {\n    return l.to.x - l.from.x\n}\nfn f(): int {\n    let p = Point { x: 1, y: 2 }\n    let l = Line { from: p, to: Point { y: 0, x: 3 } }\n    l.to.y = p.x\n    return length(l)\n}";
+        assert_eq!(check_src(src), vec![]);
+        let point = "struct Point { x: int, y: int }\n";
+        assert_eq!(codes("fn f(p: Point) {\n}"), vec!["Z0025"]);
+        assert_eq!(
+            codes(&format!(
+                "{}fn f() {{\n    let p = Point {{ x: 1, z: 2 }}\n}}",
+                point
+            )),
+            vec!["Z0026", "Z0027"]
+        );
+        assert_eq!(
+            codes(&format!(
+                "{}fn f() {{\n    let p = Point {{ x: 1, y: true, x: 2 }}\n}}",
+                point
+            )),
+            vec!["Z0012", "Z0019"]
+        );
+        assert_eq!(
+            codes(&format!(
+                "{}fn f(p: Point): int {{\n    return p.z\n}}",
+                point
+            )),
+            vec!["Z0026"]
+        );
+        assert_eq!(
+            codes("fn f(): int {\n    int x = 1\n    return x.y\n}"),
+            vec!["Z0016"]
+        );
+        assert_eq!(
+            codes(&format!(
+                "{}fn f(p: Point): bool {{\n    return p == p\n}}",
+                point
+            )),
+            vec!["Z0016"]
+        );
+        assert_eq!(
+            codes("struct A { b: [B; 2] }\nstruct B { a: A }\nstruct C { a: A }"),
+            vec!["Z0028", "Z0028"]
+        );
+    }
+
+    #[test]
+    fn checks_enums_and_match() {
+        let command = "enum Command {\n    Help,\n    Add(int, int),\n    Greet(str)\n}\n";
+        let src = format!("{}fn run(c: Command): int {{\n    return match c {{\n        Command::Add(a, b) => a + b\n        Command::Greet(name) => len(name)\n        Command::Help => 0\n    }}\n}}\nfn f(flag: bool, name: str): int {{\n    int x = match flag {{ true => 1, false => 0 }}\n    return run(Command::Add(x, 2)) + match name {{ \"add\" => 1, _ => 0 }}\n}}", command);
+        assert_eq!(check_src(&src), vec![]);
+        let run = |body: &str| {
+            codes(&format!(
+                "{}fn f(c: Command): int {{\n{}\n}}",
+                command, body
+            ))
+        };
+        assert_eq!(
+            run("    return match c { Command::Help => 0, Command::Add(a, b) => a }"),
+            vec!["Z0030"]
+        );
+        assert_eq!(run("    return c.x"), vec!["Z0046"]);
+        assert_eq!(
+            run("    bool same = c != Command::Help\n    return 0"),
+            vec!["Z0016"]
+        );
+        assert_eq!(
+            run("    return match c { Command::Add(a) => a, _ => 0 }"),
+            vec!["Z0015"]
+        );
+        assert_eq!(
+            run("    return match c { Command::Greet(s) => s, _ => 0 }"),
+            vec!["Z0012", "Z0017"]
+        );
+        assert_eq!(
+            run("    return match 1 { Command::Help => 0, 2 => 1 }"),
+            vec!["Z0012", "Z0030"]
+        );
+        assert_eq!(
+            run("    let d = Command::Quit\n    return 0"),
+            vec!["Z0029"]
+        );
+        assert_eq!(
+            run("    let d = Command::Add(1, true)\n    return 0"),
+            vec!["Z0012"]
+        );
+        assert_eq!(codes("enum List { Empty, Cons(int, List) }"), vec!["Z0028"]);
+    }
+
+    #[test]
+    fn checks_for_loops() {
+        let src = "fn f(n: int): int {\n    int total = 0\n    for (int i = 0; i < n; i++) { total += i }\n    for i in 0..n { total += i }\n    return total\n}";
+        assert_eq!(check_src(src), vec![]);
+        assert_eq!(
+            codes("fn f() {\n    for (int i = 0; i; i++) { }\n}"),
+            vec!["Z0012"]
+        );
+        assert_eq!(
+            codes("fn f() {\n    for i in 0..true { }\n}"),
+            vec!["Z0012"]
+        );
+        // The loop variable is scoped to the loop.
+        assert_eq!(
+            codes("fn f(): int {\n    for i in 0..3 { }\n    return i\n}"),
+            vec!["Z0013"]
+        );
+    }
+
+    #[test]
+    fn strings_concatenate_and_compare() {
+        let src = "fn greet(name: str): str {\n    return \"hello \" + name\n}\nfn f(): bool {\n    str s = greet(\"zeta\")\n    return s == \"hello zeta\" && len(s) > 3\n}";
+        assert_eq!(check_src(src), vec![]);
+        assert_eq!(codes("fn f() {\n    int x = \"a\" + 1\n}"), vec!["Z0016"]);
+        assert_eq!(
+            codes("fn f() {\n    bool b = \"a\" < \"b\"\n}"),
+            vec!["Z0016", "Z0016"]
+        );
+    }
+
+    #[test]
+    fn checks_the_entry_point() {
+        assert_eq!(
+            codes("fn main(): int {\n    str name = argv(0)\n    return argc()\n}"),
+            Vec::<&str>::new()
+        );
+        assert_eq!(codes("fn main(n: int) {\n}"), vec!["Z0020"]);
+        assert_eq!(
+            codes("fn main(): bool {\n    return true\n}"),
+            vec!["Z0020"]
+        );
+        assert_eq!(codes("fn f(): int {\n    return argc()\n}"), vec!["Z0021"]);
+        assert_eq!(
+            codes("fn argc(): int {\n    return 1\n}\nfn f(): int {\n    return argc()\n}"),
+            Vec::<&str>::new()
+        );
+    }
+
+    #[test]
+    fn rejects_async_functions() {
+        let src =
+            "fn async fetch(): int {\n    return 1\n}\nfn main(): int {\n    return fetch()\n}";
+        assert_eq!(codes(src), vec!["Z0040"]);
+    }
+
+    #[test]
+    fn floats_only_mix_with_ints_through_casts() {
+        let src = "fn mean(a: [int; 2]): float {\n    float total = (a[0] + a[1]) as float\n    return -total / 2.0\n}\nfn f(): bool {\n    return mean([1, 2]) as int < 2 && 1.5 >= 1e0\n}";
+        assert_eq!(check_src(src), vec![]);
+        assert_eq!(
+            codes("fn f(x: float): float {\n    int i = x\n    return x + 1\n}"),
+            vec!["Z0012", "Z0012"]
+        );
+        assert_eq!(
+            codes("fn f(x: float, s: str): int {\n    int i = x % 2.0\n    return s as int\n}"),
+            vec!["Z0016", "Z0016", "Z0044"]
+        );
+    }
+}
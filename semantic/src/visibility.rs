@@ -0,0 +1,435 @@
+use crate::errors::SemanticError;
+use lexer::{
+    Expression, ExpressionKind, PatternKind, Program, Span, Statement, StatementKind, Type,
+};
+use std::collections::HashMap;
+
+/// What an item's name can stand for. Each kind has names of its own, so a
+/// call only ever names a function and a type only a struct or an enum.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub(crate) enum Namespace {
+    Function,
+    Global,
+    Type,
+}
+
+/// A function, global, struct or enum declared at the top level of a file.
+struct Item {
+    /// The name of the item throughout the program, see [`Visibility::qualify`].
+    name: String,
+    span: Span,
+    is_pub: bool,
+}
+
+/// Decides what the names of a program loaded from several files refer to.
+/// Every file is a module with items of its own, so two modules may declare
+/// items of the same name. An item can be used in the file that declares
+/// it, and in any file that imports it, either by name with
+/// `use module::item` or together with the rest of its module with
+/// `use module`. Only `pub` items can be imported.
+pub(crate) struct Visibility {
+    /// Every item, by the file that declares it, its namespace and its name.
+    items: HashMap<(usize, Namespace, String), Item>,
+    /// The first file to declare an item of each name, so that an item
+    /// that is not imported can still be pointed at.
+    declared: HashMap<(Namespace, String), usize>,
+    /// The module each file imports an item from by name, by `(file, item)`.
+    explicit: HashMap<(usize, String), usize>,
+    /// The modules each file imports as a whole, in order.
+    globs: HashMap<usize, Vec<usize>>,
+    /// The path of every imported module, by file id. The entry point is
+    /// the only file without one.
+    modules: HashMap<usize, String>,
+}
+
+impl Visibility {
+    pub fn new(program: &Program) -> Self {
+        let mut modules = HashMap::new();
+        let mut explicit = HashMap::new();
+        let mut globs: HashMap<usize, Vec<usize>> = HashMap::new();
+        for import in program.imports.iter() {
+            let module = match import.module {
+                Some(module) => module,
+                // Modules that failed to load have already been reported.
+                None => continue,
+            };
+            modules.insert(module, import.module_path().join("::"));
+            let file = import.span.file_id;
+            match import.item() {
+                Some(item) => {
+                    explicit.insert((file, item.to_string()), module);
+                }
+                None => globs.entry(file).or_default().push(module),
+            }
+        }
+        let declared = program
+            .func
+            .iter()
+            .map(|f| (Namespace::Function, &f.name, f.span, f.is_pub))
+            .chain(
+                program
+                    .globals
+                    .iter()
+                    .map(|g| (Namespace::Global, &g.var.name, g.var.span, g.is_pub)),
+            )
+            .chain(
+                program
+                    .structs
+                    .iter()
+                    .map(|s| (Namespace::Type, &s.name, s.span, s.is_pub)),
+            )
+            .chain(
+                program
+                    .enums
+                    .iter()
+                    .map(|e| (Namespace::Type, &e.name, e.span, e.is_pub)),
+            );
+        let mut visibility = Visibility {
+            items: HashMap::new(),
+            declared: HashMap::new(),
+            explicit,
+            globs,
+            modules,
+        };
+        for (namespace, name, span, is_pub) in declared {
+            let item = Item {
+                name: visibility.qualified(span.file_id, name),
+                span,
+                is_pub,
+            };
+            // Duplicates are reported by the resolver and type checker.
+            visibility
+                .items
+                .entry((span.file_id, namespace, name.clone()))
+                .or_insert(item);
+            visibility
+                .declared
+                .entry((namespace, name.clone()))
+                .or_insert(span.file_id);
+        }
+        visibility
+    }
+
+    /// The name of the item `name` declared in `file` throughout the
+    /// program: its path, like `shapes::square`, unless it is declared in
+    /// the entry point.
+    fn qualified(&self, file: usize, name: &str) -> String {
+        match self.modules.get(&file) {
+            Some(module) => format!("{}::{}", module, name),
+            None => name.to_string(),
+        }
+    }
+
+    /// Finds the item that `name`, used at `span`, refers to, and returns
+    /// its name throughout the program. The file using it looks at its own
+    /// items first, then those it imports by name, then the modules it
+    /// imports as a whole. An item that it may not use is returned all the
+    /// same, with the error to report. Names that are not items are left to
+    /// the caller to report.
+    pub fn lookup(
+        &self,
+        namespace: Namespace,
+        name: &str,
+        span: Span,
+    ) -> Option<(&str, Option<SemanticError>)> {
+        let file = span.file_id;
+        let item = |module: usize| self.items.get(&(module, namespace, name.to_string()));
+        if let Some(item) = item(file) {
+            return Some((&item.name, None));
+        }
+        // A private item imported by name is reported at the `use`.
+        if let Some(item) = self
+            .explicit
+            .get(&(file, name.to_string()))
+            .and_then(|&m| item(m))
+        {
+            return Some((&item.name, None));
+        }
+        let globs = self.globs.get(&file).map_or(&[][..], Vec::as_slice);
+        if let Some(item) = globs.iter().find_map(|&module| item(module)) {
+            let error = if item.is_pub {
+                None
+            } else {
+                Some(SemanticError::PrivateItem {
+                    name: name.into(),
+                    span,
+                    defined: item.span,
+                })
+            };
+            return Some((&item.name, error));
+        }
+        let item = item(*self.declared.get(&(namespace, name.to_string()))?)?;
+        let error = SemanticError::NotImported {
+            name: name.into(),
+            span,
+        };
+        Some((&item.name, Some(error)))
+    }
+
+    /// Checks that every `use module::item` names a `pub` item of the module.
+    pub fn check_imports(&self, program: &Program) -> Vec<SemanticError> {
+        let mut errors = Vec::new();
+        for import in program.imports.iter() {
+            let (item, module) = match (import.item(), import.module) {
+                (Some(item), Some(module)) => (item, module),
+                // Modules that failed to load have already been reported.
+                _ => continue,
+            };
+            let found: Vec<&Item> = [Namespace::Function, Namespace::Global, Namespace::Type]
+                .iter()
+                .filter_map(|&namespace| self.items.get(&(module, namespace, item.to_string())))
+                .collect();
+            match found.first() {
+                Some(defined) if !found.iter().any(|i| i.is_pub) => {
+                    errors.push(SemanticError::PrivateItem {
+                        name: item.into(),
+                        span: import.span,
+                        defined: defined.span,
+                    })
+                }
+                Some(_) => {}
+                None => errors.push(SemanticError::UnresolvedImport {
+                    path: import.name.join("::").into(),
+                    span: import.span,
+                }),
+            }
+        }
+        errors
+    }
+
+    /// Renames every item declared in a module, and every struct and enum
+    /// named in the program, to its name throughout the program, so that
+    /// the items of different modules can share a name. Functions and
+    /// globals are found by the resolver, which uses [`Visibility::lookup`]
+    /// for them too. Returns the errors for types that may not be used
+    /// where they are named.
+    pub fn qualify(&self, program: &mut Program) -> Vec<SemanticError> {
+        let mut errors = Vec::new();
+        for function in program.func.iter_mut() {
+            function.name = self.qualified(function.span.file_id, &function.name);
+            for argument in function.arguments.iter_mut() {
+                if let Some(t) = &mut argument.t {
+                    self.qualify_type(t, argument.span, &mut errors);
+                }
+            }
+            self.qualify_type(&mut function.return_type, function.span, &mut errors);
+            for statement in function.statements.iter_mut() {
+                self.qualify_statement(statement, &mut errors);
+            }
+        }
+        for global in program.globals.iter_mut() {
+            global.var.name = self.qualified(global.var.span.file_id, &global.var.name);
+            if let Some(t) = &mut global.var.t {
+                self.qualify_type(t, global.var.span, &mut errors);
+            }
+            self.qualify_expression(&mut global.value, &mut errors);
+        }
+        for declaration in program.structs.iter_mut() {
+            declaration.name = self.qualified(declaration.span.file_id, &declaration.name);
+            for field in declaration.fields.iter_mut() {
+                if let Some(t) = &mut field.t {
+                    self.qualify_type(t, field.span, &mut errors);
+                }
+            }
+        }
+        for declaration in program.enums.iter_mut() {
+            declaration.name = self.qualified(declaration.span.file_id, &declaration.name);
+            for variant in declaration.variants.iter_mut() {
+                for t in variant.fields.iter_mut() {
+                    self.qualify_type(t, variant.span, &mut errors);
+                }
+            }
+        }
+        errors
+    }
+
+    /// Renames the struct or enum `name`, named at `span`. Undeclared types
+    /// keep their name, and are reported by the type checker.
+    fn qualify_name(&self, name: &mut String, span: Span, errors: &mut Vec<SemanticError>) {
+        if let Some((qualified, error)) = self.lookup(Namespace::Type, name, span) {
+            *name = qualified.to_string();
+            errors.extend(error);
+        }
+    }
+
+    fn qualify_type(&self, t: &mut Type, span: Span, errors: &mut Vec<SemanticError>) {
+        match t {
+            Type::Array(element, _) => self.qualify_type(element, span, errors),
+            Type::Named(name) => self.qualify_name(name, span, errors),
+            _ => {}
+        }
+    }
+
+    fn qualify_statement(&self, statement: &mut Statement, errors: &mut Vec<SemanticError>) {
+        match &mut statement.kind {
+            StatementKind::Declare(var, value) => {
+                if let Some(t) = &mut var.t {
+                    self.qualify_type(t, var.span, errors);
+                }
+                if let Some(value) = value {
+                    self.qualify_expression(value, errors);
+                }
+            }
+            StatementKind::Return(exp) | StatementKind::Exp(exp) => {
+                self.qualify_expression(exp, errors)
+            }
+            StatementKind::If(condition, then, otherwise) => {
+                self.qualify_expression(condition, errors);
+                self.qualify_statement(then, errors);
+                if let Some(otherwise) = otherwise {
+                    self.qualify_statement(otherwise, errors);
+                }
+            }
+            StatementKind::While(condition, body) => {
+                self.qualify_expression(condition, errors);
+                self.qualify_statement(body, errors);
+            }
+            StatementKind::For(init, condition, step, body) => {
+                if let Some(init) = init {
+                    self.qualify_statement(init, errors);
+                }
+                for exp in condition.iter_mut().chain(step.iter_mut()) {
+                    self.qualify_expression(exp, errors);
+                }
+                self.qualify_statement(body, errors);
+            }
+            StatementKind::ForRange(var, from, to, body) => {
+                if let Some(t) = &mut var.t {
+                    self.qualify_type(t, var.span, errors);
+                }
+                self.qualify_expression(from, errors);
+                self.qualify_expression(to, errors);
+                self.qualify_statement(body, errors);
+            }
+            StatementKind::Labeled(_, inner) => self.qualify_statement(inner, errors),
+            StatementKind::Break(_) | StatementKind::Continue(_) => {}
+            StatementKind::Compound(statements) => {
+                for statement in statements.iter_mut() {
+                    self.qualify_statement(statement, errors);
+                }
+            }
+        }
+    }
+
+    fn qualify_expression(&self, exp: &mut Expression, errors: &mut Vec<SemanticError>) {
+        let span = exp.span;
+        match &mut exp.kind {
+            ExpressionKind::Int(_)
+            | ExpressionKind::Float(_)
+            | ExpressionKind::Bool(_)
+            | ExpressionKind::Char(_)
+            | ExpressionKind::MLStr(_)
+            | ExpressionKind::Variable(_)
+            | ExpressionKind::VariableRef(_) => {}
+            ExpressionKind::Assign(_, value)
+            | ExpressionKind::AssignPostfix(_, value)
+            | ExpressionKind::UnOp(_, value)
+            | ExpressionKind::Field(value, _) => self.qualify_expression(value, errors),
+            ExpressionKind::Cast(value, t) => {
+                self.qualify_expression(value, errors);
+                self.qualify_type(t, span, errors);
+            }
+            ExpressionKind::BinOp(_, lhs, rhs)
+            | ExpressionKind::Index(lhs, rhs)
+            | ExpressionKind::AssignField(lhs, _, rhs) => {
+                self.qualify_expression(lhs, errors);
+                self.qualify_expression(rhs, errors);
+            }
+            ExpressionKind::Ternary(a, b, c) | ExpressionKind::AssignIndex(a, b, c) => {
+                for exp in [a, b, c] {
+                    self.qualify_expression(exp, errors);
+                }
+            }
+            ExpressionKind::Array(values) | ExpressionKind::FunctionCall(_, values) => {
+                for value in values.iter_mut() {
+                    self.qualify_expression(value, errors);
+                }
+            }
+            ExpressionKind::StructLiteral(name, fields) => {
+                self.qualify_name(name, span, errors);
+                for (_, value) in fields.iter_mut() {
+                    self.qualify_expression(value, errors);
+                }
+            }
+            ExpressionKind::Variant(name, _, values) => {
+                self.qualify_name(name, span, errors);
+                for value in values.iter_mut() {
+                    self.qualify_expression(value, errors);
+                }
+            }
+            ExpressionKind::Match(value, arms) => {
+                self.qualify_expression(value, errors);
+                for arm in arms.iter_mut() {
+                    if let PatternKind::Variant(name, _, bindings) = &mut arm.pattern.kind {
+                        self.qualify_name(name, arm.pattern.span, errors);
+                        for binding in bindings.iter_mut().flatten() {
+                            if let Some(t) = &mut binding.t {
+                                self.qualify_type(t, binding.span, errors);
+                            }
+                        }
+                    }
+                    self.qualify_expression(&mut arm.value, errors);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use lexer::{tokenize_file, Parser, Program};
+
+    /// Links `main` (file 0) with the module `util` (file 1) the way
+    /// `lexer::load_program` would.
+    fn codes(main: &str, util: &str) -> Vec<&'static str> {
+        let mut program = Program::default();
+        for (file_id, src) in [main, util].iter().enumerate() {
+            let tokens = tokenize_file(src, "", file_id).unwrap();
+            let (parsed, errors) = Parser::new(tokens, "".into()).parse();
+            assert!(errors.is_empty(), "{:?}", errors);
+            program.imports.extend(parsed.imports);
+            program.func.extend(parsed.func);
+            program.globals.extend(parsed.globals);
+            program.structs.extend(parsed.structs);
+        }
+        for import in program.imports.iter_mut() {
+            import.module = Some(1);
+        }
+        crate::analyze(&mut program)
+            .1
+            .iter()
+            .map(|e| e.code())
+            .collect()
+    }
+
+    const UTIL: &str = "pub struct Point {\n    x: int\n}\npub fn origin(): Point {\n    return Point { x: secret() }\n}\nfn secret(): int {\n    return 0\n}";
+
+    #[test]
+    fn imported_pub_items_are_visible() {
+        let main = "use util::origin\nuse util::Point\nfn start(p: Point): int {\n    return p.x\n}\nfn main(): int {\n    return start(origin())\n}";
+        assert_eq!(codes(main, UTIL), Vec::<&str>::new());
+        let glob = "use util\nfn main(): int {\n    return origin().x\n}";
+        assert_eq!(codes(glob, UTIL), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn reports_missing_and_private_imports() {
+        let main = "use util::secret\nuse util::nothing\nfn main(): int {\n    return secret()\n}";
+        assert_eq!(codes(main, UTIL), vec!["Z0036", "Z0034"]);
+        let glob = "use util\nfn main(): int {\n    return secret()\n}";
+        assert_eq!(codes(glob, UTIL), vec!["Z0036"]);
+        let none = "fn main(): int {\n    return origin().x\n}";
+        assert_eq!(codes(none, UTIL), vec!["Z0035"]);
+    }
+
+    #[test]
+    fn modules_have_names_of_their_own() {
+        let util = "const limit = 2\npub struct Point {\n    x: int\n}\npub fn origin(): Point {\n    return Point { x: helper() }\n}\nfn helper(): int {\n    return limit\n}";
+        let main = "use util\nconst limit = true\nstruct Point {\n    y: bool\n}\nfn helper(): bool {\n    return limit\n}\nfn main(): int {\n    let p = Point { y: helper() }\n    return origin().x\n}";
+        assert_eq!(codes(main, util), Vec::<&str>::new());
+        // A file's own items come first, so `Point` is not `util::Point` here.
+        let mistyped =
+            "use util\nstruct Point {\n    x: int\n}\nfn f(): Point {\n    return origin()\n}";
+        assert_eq!(codes(mistyped, util), vec!["Z0017"]);
+    }
+}
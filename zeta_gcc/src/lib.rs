@@ -1,45 +1,295 @@
 extern crate gccjit;
-mod globalvals;
-use std::{collections::HashMap, convert::TryInto};
+mod runtime;
+
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::io;
+use std::mem;
+use std::os::raw::{c_char, c_int};
+use std::path::Path;
+use std::process::Command;
+use std::ptr;
 
 use gccjit::{
-    Block, Context, Function as gFunc, FunctionType, LValue, OptimizationLevel, Parameter, RValue,
-    ToRValue, Type,
+    Block, Context, Function as gFunc, FunctionType, GlobalKind, LValue, Location,
+    OptimizationLevel, Parameter, RValue, ToLValue, ToRValue, Type, UnaryOp,
+};
+use lexer::{
+    BinOp, Enum, Expression, ExpressionKind, Function, Global, MatchArm, Pattern, PatternKind,
+    Program, SourceMap, Span, Statement, StatementKind, Struct,
 };
-use lexer::{BinOp, Expression, Function, Program, Statement, Variable};
-
-use crate::globalvals::GlobVals;
-
-const MEMORY_SIZE: i32 = 1000;
-pub struct InitData<'a> {
-    getchar: gFunc<'a>,
-    parameter: Parameter<'a>,
-    putchar: gFunc<'a>,
-    memory_ty: Type<'a>,
-    char_ptr: Type<'a>,
-    void_param: Parameter<'a>,
-    size_t_param: Parameter<'a>,
-    void_ptr_ty: Type<'a>,
-    memset: gFunc<'a>,
+use runtime::Runtime;
+use semantic::{ConstValue, Resolution, SymbolId, SymbolKind};
+
+/// The function being compiled, and where each of its parameters and locals lives.
+struct Frame<'a, 'r> {
+    fun: gFunc<'a>,
+    resolution: &'r Resolution,
+    /// Every function in the program, declared up front so that calls can
+    /// refer to functions defined later on, or to themselves.
+    functions: &'r HashMap<SymbolId, gFunc<'a>>,
+    /// The program's top-level `const`s and `let`s.
+    globals: &'r HashMap<SymbolId, LValue<'a>>,
+    runtime: &'r Runtime<'a>,
+    locals: HashMap<SymbolId, LValue<'a>>,
+    /// The loops being compiled, by the span of the loop statement.
+    loops: HashMap<Span, LoopBlocks<'a>>,
+    /// Whether this is the user's `main`, which is lowered to the C entry
+    /// point `int main(int argc, char **argv)`.
+    entry: bool,
+}
+
+/// The blocks that `break` and `continue` jump to for one loop. They are only
+/// created once something jumps to them, since gccjit rejects unreachable blocks.
+#[derive(Default)]
+struct LoopBlocks<'a> {
+    exit: Option<Block<'a>>,
+    /// The condition of a `while`, or the step of a `for`.
+    next: Option<Block<'a>>,
+}
+
+impl<'a, 'r> Frame<'a, 'r> {
+    /// The block that leaves the loop at `span`, or with `next` the one that
+    /// starts its next iteration.
+    fn loop_block(&mut self, span: Span, next: bool) -> Block<'a> {
+        let fun = self.fun;
+        let blocks = self.loops.entry(span).or_default();
+        let (slot, name) = if next {
+            (&mut blocks.next, "loop_next")
+        } else {
+            (&mut blocks.exit, "loop_exit")
+        };
+        *slot.get_or_insert_with(|| fun.new_block(name))
+    }
+
+    /// Binds the declaration at `span` to `lvalue`.
+    fn declare(&mut self, span: Span, lvalue: LValue<'a>) {
+        if let Some(id) = self.resolution.resolve(span) {
+            self.locals.insert(id, lvalue);
+        }
+    }
+
+    /// The storage of the variable used or declared at `span`.
+    fn lookup(&self, span: Span) -> Option<LValue<'a>> {
+        let id = self.resolution.resolve(span)?;
+        self.locals.get(&id).or_else(|| self.globals.get(&id)).copied()
+    }
+
+    /// The function called by the call expression at `span`.
+    fn function(&self, span: Span) -> Option<gFunc<'a>> {
+        let id = self.resolution.resolve(span)?;
+        self.functions.get(&id).copied()
+    }
+
+    /// The type of the variable declared at `span`, as worked out by the type checker.
+    fn type_of(&self, span: Span) -> Option<&'r lexer::Type> {
+        let id = self.resolution.resolve(span)?;
+        self.resolution.symbol(id).t.as_ref()
+    }
+}
+
+/// How [`Compile`] builds a program.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Options {
+    /// Optimise as much as gccjit can, rather than not at all.
+    pub release: bool,
+    /// Print the code gccjit generates while compiling it.
+    pub verbose: bool,
+    pub toolchain: Toolchain,
+}
+
+/// The C toolchain that links the object file gccjit produces, and the C
+/// library it calls into, into an executable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Toolchain {
+    Clang,
+    Gcc,
+}
+
+impl Options {
+    /// How hard gccjit optimises the program.
+    pub fn optimization_level(self) -> OptimizationLevel {
+        if self.release {
+            OptimizationLevel::Aggressive
+        } else {
+            OptimizationLevel::None
+        }
+    }
+}
+
+impl Toolchain {
+    /// The compiler driver, which is looked up on `PATH`.
+    pub fn program(self) -> &'static str {
+        match self {
+            Toolchain::Clang => "clang",
+            Toolchain::Gcc => "gcc",
+        }
+    }
+}
+
+/// What [`Compile::compile`] writes to its output path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Emit {
+    /// An executable, linked by the chosen toolchain.
+    Executable,
+    Object,
+    Assembly,
+    DynamicLibrary,
+    /// gccjit's C-like dump of the code it was given.
+    Gimple,
 }
+
+impl Emit {
+    /// The extension of an output named after its source file, if any.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Emit::Executable => "",
+            Emit::Object => "o",
+            Emit::Assembly => "s",
+            Emit::DynamicLibrary => "so",
+            Emit::Gimple => "gimple",
+        }
+    }
+}
+
 pub struct Compile<'a> {
     context: gccjit::Context<'a>,
+    sources: SourceMap,
+    options: Options,
 }
 
 impl Compile<'static> {
-    pub fn new<'a>() -> Self {
+    pub fn new(sources: SourceMap, options: Options) -> Self {
         let context = Context::default();
-        context.set_optimization_level(OptimizationLevel::Aggressive);
-        context.set_dump_code_on_compile(true);
-        Self { context }
+        context.set_optimization_level(options.optimization_level());
+        context.set_dump_code_on_compile(options.verbose);
+        Self {
+            context,
+            sources,
+            options,
+        }
+    }
+    /// Maps a span onto a gccjit location so that debuggers can show Zeta source.
+    fn location<'a>(&'a self, span: &Span) -> Option<Location<'a>> {
+        Some(self.context.new_location(
+            self.sources.name(span.file_id),
+            span.line as i32,
+            span.col as i32,
+        ))
+    }
+    /// Compiles `lexer` into `output`, as whatever `emit` asks for. For an
+    /// executable, gccjit writes an object file next to it, which the chosen
+    /// toolchain then links.
+    pub fn compile(
+        &self,
+        lexer: Program,
+        resolution: &Resolution,
+        emit: Emit,
+        output: &Path,
+    ) -> io::Result<()> {
+        if !matches!(emit, Emit::Executable | Emit::Gimple) {
+            // Values that have to be stored by `main` would never be set up
+            // in code that is linked into something else.
+            let stored_by_main = lexer.globals.iter().find(|global| {
+                let id = resolution.resolve(global.var.span);
+                let value = id.and_then(|id| resolution.initial_value(id));
+                value.is_some_and(|value| const_bytes(value).is_none())
+            });
+            if let Some(global) = stored_by_main {
+                return Err(io::Error::other(format!(
+                    "`{}` starts out as a string, which only an executable can set up",
+                    global.var.name
+                )));
+            }
+        }
+        self.lower(lexer, resolution);
+        let kind = match emit {
+            Emit::Gimple => {
+                self.context.dump_to_file(output.to_string_lossy(), false);
+                return Ok(());
+            }
+            Emit::Executable => {
+                // Named so that it can't be `output` itself, even for `-o x.o`.
+                let object = output.with_extension("zeta.o");
+                self.compile_to_file(gccjit::OutputKind::ObjectFile, &object)?;
+                let linked = self.link(&object, output);
+                let _ = std::fs::remove_file(&object);
+                return linked;
+            }
+            Emit::Object => gccjit::OutputKind::ObjectFile,
+            Emit::Assembly => gccjit::OutputKind::Assembler,
+            Emit::DynamicLibrary => gccjit::OutputKind::DynamicLibrary,
+        };
+        self.compile_to_file(kind, output)
     }
-    pub fn compile<'a>(&self, lexer: Program) {
+
+    /// Compiles `lexer` in memory and calls its `main` with `args` as
+    /// `argv`, returning its exit code. Nothing is written to disk.
+    pub fn run(&self, lexer: Program, resolution: &Resolution, args: &[String]) -> io::Result<i32> {
+        self.lower(lexer, resolution);
+        let result = self.context.compile();
+        let main = result.get_function("main");
+        // gccjit reports its own errors on stderr and compiles nothing.
+        if main.is_null() {
+            return Err(io::Error::other("gccjit could not compile the program"));
+        }
+        let args = args
+            .iter()
+            .map(|arg| CString::new(arg.as_str()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        // C requires `argv[argc]` to be null.
+        let mut argv: Vec<*const c_char> = args.iter().map(|arg| arg.as_ptr()).collect();
+        argv.push(ptr::null());
+        // SAFETY: `main` is declared with exactly this signature, see
+        // `declare_fn`, and its code stays loaded until `result` is dropped.
+        let main: extern "C" fn(c_int, *const *const c_char) -> c_int =
+            unsafe { mem::transmute(main) };
+        let code = main(args.len() as c_int, argv.as_ptr());
+        drop(result);
+        Ok(code)
+    }
+
+    /// Lowers the whole of `lexer` into the context.
+    fn lower(&self, lexer: Program, resolution: &Resolution) {
         let Program {
-            imports,
+            imports: _,
             func,
             globals,
+            structs,
+            enums,
         } = lexer;
-        self.compile_fn(func)
+        self.compile_fn(func, &globals, &structs, &enums, resolution);
+    }
+
+    fn compile_to_file(&self, kind: gccjit::OutputKind, output: &Path) -> io::Result<()> {
+        // gccjit reports its own errors on stderr and writes nothing, so a
+        // file left over from an earlier build must not pass for this one.
+        let _ = std::fs::remove_file(output);
+        self.context.compile_to_file(kind, output.to_string_lossy());
+        if output.exists() {
+            Ok(())
+        } else {
+            Err(io::Error::other("gccjit could not compile the program"))
+        }
+    }
+
+    fn link(&self, object: &Path, output: &Path) -> io::Result<()> {
+        let program = self.options.toolchain.program();
+        let status = Command::new(program)
+            .arg(object)
+            .arg("-o")
+            .arg(output)
+            .status()
+            .map_err(|e| io::Error::new(e.kind(), format!("could not run `{}`: {}", program, e)))?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(io::Error::other(format!(
+                "`{}` could not link the program ({})",
+                program, status
+            )))
+        }
     }
     fn types<'a>(&'a self) -> (Type<'a>, Type<'a>, Type<'a>, Type<'a>) {
         let int_ty: Type<'a> = self.context.new_type::<i64>();
@@ -49,176 +299,439 @@ impl Compile<'static> {
         let void_ty: Type<'a> = self.context.new_type::<()>();
         (int_ty, bool_ty, void_ty, char_ty)
     }
-    pub fn init<'a>(&'a self) -> InitData<'a> {
-        let (int_ty, bool_ty, void_ty, char_ty) = self.types();
-        let getchar = self.context.new_function(
-            None,
-            gccjit::FunctionType::Extern,
-            char_ty,
-            &[],
-            "getchar",
-            false,
-        );
-        let parameter = self.context.new_parameter(None, char_ty, "c");
-        let putchar = self.context.new_function(
-            None,
-            gccjit::FunctionType::Extern,
-            void_ty,
-            &[parameter],
-            "putchar",
-            false,
-        );
-        let memory_ty = self.context.new_array_type(None, char_ty, MEMORY_SIZE);
-        let char_ptr = self.context.new_type::<u8>().make_pointer();
-        let void_param = self.context.new_parameter(None, char_ptr, "ptr");
-        let size_t_param = self.context.new_parameter(None, int_ty, "size");
-        let int_param = self.context.new_parameter(None, int_ty, "num");
-        let void_ptr_ty = self.context.new_type::<*mut ()>();
-        let memset = self.context.new_function(
-            None,
-            gccjit::FunctionType::Extern,
-            void_ptr_ty,
-            &[void_param, int_param, size_t_param],
-            "memset",
-            false,
-        );
-        InitData {
-            getchar,
-            parameter,
-            putchar,
-            memory_ty,
-            char_ptr,
-            void_param,
-            size_t_param,
-            void_ptr_ty,
-            memset,
+    fn lower_type<'a>(&'a self, t: &lexer::Type, runtime: &Runtime<'a>) -> Type<'a> {
+        let (int_ty, bool_ty, void_ty, _) = self.types();
+        match t {
+            lexer::Type::Bool => bool_ty,
+            lexer::Type::Void => void_ty,
+            lexer::Type::Int => int_ty,
+            lexer::Type::Float => self.context.new_type::<f64>(),
+            lexer::Type::Str | lexer::Type::Mlstr | lexer::Type::Char => runtime.str_ty,
+            lexer::Type::Array(element, len) => {
+                runtime
+                    .array_type(self.lower_type(element, runtime), *len)
+                    .ty
+            }
+            // The type checker has made sure that every named type exists.
+            lexer::Type::Named(name) => runtime.named_type(name).unwrap_or(void_ty),
         }
     }
-    pub fn compile_fn<'a>(&'a self, funcs: Vec<Function>) {
-        let (int_ty, bool_ty, void_ty, char_ty) = self.types();
-        let initialize = self.init();
-        let f_main = self.context.new_function(
-            None,
-            gccjit::FunctionType::Exported,
-            void_ty,
-            &[],
-            "main",
-            false,
-        );
-        let size = self
-            .context
-            .new_rvalue_from_int(int_ty, MEMORY_SIZE.try_into().unwrap());
-        let array = f_main.new_local(None, initialize.memory_ty, "memory");
-        let current_block = f_main.new_block("entry_block");
-        let zero_access = self.context.new_array_access(
-            None,
-            array.to_rvalue(),
-            self.context.new_rvalue_zero(int_ty),
-        );
-        current_block.add_eval(
-            None,
-            self.context.new_call(
-                None,
-                initialize.memset,
-                &[
-                    zero_access.get_address(None),
-                    self.context.new_rvalue_zero(int_ty),
-                    size,
-                ],
-            ),
-        );
-
-        current_block.end_with_void_return(None);
+    pub fn compile_fn(
+        &self,
+        funcs: Vec<Function>,
+        globals: &[Global],
+        structs: &[Struct],
+        enums: &[Enum],
+        resolution: &Resolution,
+    ) {
+        let c_int_ty = self.context.new_type::<i32>();
+        let runtime = Runtime::new(&self.context);
+        self.declare_types(structs, enums, &runtime);
+        let lvalues = self.declare_globals(globals, resolution, &runtime);
+        let mut functions = HashMap::new();
+        for func in funcs.iter() {
+            if let Some(id) = resolution.resolve(func.span) {
+                functions.insert(id, self.declare_fn(func, &runtime));
+            }
+        }
         for func in funcs.iter() {
             let Function {
-                is_async,
-                name,
                 return_type,
                 arguments,
                 statements,
+                span,
+                ..
             } = func;
-            let r: Type<'a> = match return_type {
-                lexer::Type::Bool => bool_ty,
-                lexer::Type::Str => todo!(),
-                lexer::Type::Void => void_ty,
-                lexer::Type::Int => int_ty,
-                lexer::Type::Mlstr => todo!(),
-                lexer::Type::Char => todo!(),
+            let fun = match resolution.resolve(*span).and_then(|id| functions.get(&id)) {
+                Some(&fun) => fun,
+                None => continue,
+            };
+            let r = self.lower_type(return_type, &runtime);
+            let mut frame = Frame {
+                fun,
+                resolution,
+                functions: &functions,
+                globals: &lvalues,
+                runtime: &runtime,
+                locals: HashMap::new(),
+                loops: HashMap::new(),
+                entry: func.name == "main",
+            };
+            if !frame.entry {
+                for (i, argument) in arguments.iter().enumerate() {
+                    frame.declare(argument.span, fun.get_param(i as i32).to_lvalue());
+                }
+            }
+            let mut block = fun.new_block("entry");
+            if frame.entry {
+                for global in globals.iter() {
+                    let id = resolution.resolve(global.var.span);
+                    let lvalue = id.and_then(|id| lvalues.get(&id));
+                    let value = id.and_then(|id| resolution.initial_value(id));
+                    // The rest were initialised where they were declared.
+                    let value = value.filter(|&value| const_bytes(value).is_none());
+                    if let (Some(&lvalue), Some(value)) = (lvalue, value) {
+                        let loc = self.location(&global.span);
+                        self.store_const(lvalue, value, loc, &mut block, &mut frame);
+                    }
+                }
+            }
+            // Falling off the end is only possible for `void` functions and
+            // `main`, the type checker rejects any other function that can get here.
+            if let Some(block) = self.compile_block(block, statements, &mut frame) {
+                let loc = self.location(span);
+                if frame.entry {
+                    block.end_with_return(loc, self.context.new_rvalue_zero(c_int_ty));
+                } else if *return_type == lexer::Type::Void {
+                    block.end_with_void_return(loc);
+                } else {
+                    block.end_with_return(loc, self.context.new_rvalue_zero(r));
+                }
+            }
+        }
+    }
+
+    /// Declares a global for every top-level `const` and `let`. Only `pub`
+    /// ones are visible to code linked with the program. Globals start out
+    /// with their initial values, apart from strings, which `main` stores
+    /// when it starts, see [`const_bytes`].
+    fn declare_globals<'a>(
+        &'a self,
+        globals: &[Global],
+        resolution: &Resolution,
+        runtime: &Runtime<'a>,
+    ) -> HashMap<SymbolId, LValue<'a>> {
+        let mut lvalues = HashMap::new();
+        for global in globals.iter() {
+            let id = match resolution.resolve(global.var.span) {
+                Some(id) => id,
+                None => continue,
             };
+            let t = match resolution.symbol(id).t.as_ref() {
+                Some(t) => t,
+                None => continue,
+            };
+            let kind = if global.is_pub {
+                GlobalKind::Exported
+            } else {
+                GlobalKind::Internal
+            };
+            let lvalue = self.context.new_global(
+                self.location(&global.span),
+                kind,
+                self.lower_type(t, runtime),
+                semantic::mangle(&global.var.name),
+            );
+            let bytes = resolution.initial_value(id).and_then(const_bytes);
+            // Zero-sized arrays have nothing to initialise.
+            if let Some(bytes) = bytes.filter(|bytes| !bytes.is_empty()) {
+                lvalue.global_set_initializer(&bytes);
+            }
+            lvalues.insert(id, lvalue);
+        }
+        lvalues
+    }
 
-            let parameter = self.context.new_parameter(None, int_ty, "x");
-            let fun = self.context.new_function(
-                None,
+    /// Lays out the program's structs and enums. All of them are declared
+    /// before any is given its fields, so that they can refer to each other.
+    fn declare_types<'a>(&'a self, structs: &[Struct], enums: &[Enum], runtime: &Runtime<'a>) {
+        for declaration in structs.iter() {
+            runtime.declare_struct(&declaration.name);
+        }
+        for declaration in enums.iter() {
+            runtime.declare_enum(&declaration.name);
+        }
+        for declaration in structs.iter() {
+            let fields: Vec<_> = declaration
+                .fields
+                .iter()
+                .filter_map(|f| Some((f.name.as_str(), self.lower_type(f.t.as_ref()?, runtime))))
+                .collect();
+            runtime.define_struct(&declaration.name, &fields);
+        }
+        for declaration in enums.iter() {
+            let variants: Vec<_> = declaration
+                .variants
+                .iter()
+                .map(|v| {
+                    let types = v.fields.iter().map(|t| self.lower_type(t, runtime));
+                    (v.name.as_str(), types.collect())
+                })
+                .collect();
+            runtime.define_enum(&declaration.name, &variants);
+        }
+    }
+
+    /// Declares `func` with its real parameter list, without a body. `main`
+    /// is declared with the C signature so that it can be the entry point.
+    fn declare_fn<'a>(&'a self, func: &Function, runtime: &Runtime<'a>) -> gFunc<'a> {
+        let loc = self.location(&func.span);
+        if func.name == "main" {
+            let c_int_ty = self.context.new_type::<i32>();
+            let argv_ty = self.context.new_type::<char>().make_pointer().make_pointer();
+            let argc = self.context.new_parameter(loc, c_int_ty, "argc");
+            let argv = self.context.new_parameter(loc, argv_ty, "argv");
+            return self.context.new_function(
+                loc,
                 FunctionType::Exported,
-                r,
-                &[parameter],
-                name,
+                c_int_ty,
+                &[argc, argv],
+                "main",
                 false,
             );
-            let mut gv = GlobVals::new();
-            let initi = self.init();
-            let block = fun.new_block("entry");
-            self.compile_statement(&block, statements, &fun, &initi, &mut gv);
-            if return_type.to_owned() == lexer::Type::Void {
-                block.end_with_void_return(None)
-            }
-            println!("{:?}", gv);
         }
-        self.context
-            .compile_to_file(gccjit::OutputKind::Executable, "main")
+        let parameters: Vec<Parameter<'a>> = func
+            .arguments
+            .iter()
+            .map(|a| {
+                let t = a.t.as_ref().unwrap_or(&lexer::Type::Int);
+                self.context
+                    .new_parameter(self.location(&a.span), self.lower_type(t, runtime), &a.name)
+            })
+            .collect();
+        // Only `pub` functions are visible to code linked with the program.
+        let kind = if func.is_pub {
+            FunctionType::Exported
+        } else {
+            FunctionType::Internal
+        };
+        self.context.new_function(
+            self.location(&func.span),
+            kind,
+            self.lower_type(&func.return_type, runtime),
+            &parameters,
+            semantic::mangle(&func.name),
+            false,
+        )
     }
 
-    pub fn compile_statement<'a>(
+    /// Lowers `statements` into `block`, returning the block that any code
+    /// after them should be added to, or `None` once every path has returned.
+    /// Statements after a `return` are unreachable and are not lowered, since
+    /// gccjit rejects blocks that nothing jumps to.
+    fn compile_block<'a>(
         &'a self,
-        block: &Block,
-        statements: &Vec<Statement>,
-        fun: &'a gccjit::Function<'a>,
-        init: &InitData,
-        gvars: &mut GlobVals<'a>,
-    ) {
-        let (int_ty, bool_ty, void_ty, char_ty) = self.types();
-        let size = self
-            .context
-            .new_rvalue_from_int(int_ty, MEMORY_SIZE.try_into().unwrap());
-        let array = fun.new_local(None, init.memory_ty, "memory");
-        let memory_ptr = fun.new_local(None, int_ty, "memory_ptr");
-        let zero_access = self.context.new_array_access(
-            None,
-            array.to_rvalue(),
-            self.context.new_rvalue_zero(int_ty),
-        );
-        block.add_eval(
-            None,
-            self.context.new_call(
-                None,
-                init.memset,
-                &[
-                    zero_access.get_address(None),
-                    self.context.new_rvalue_zero(int_ty),
-                    size,
-                ],
-            ),
-        );
-        let mut val = None;
+        block: Block<'a>,
+        statements: &[Statement],
+        frame: &mut Frame<'a, '_>,
+    ) -> Option<Block<'a>> {
+        let mut current = block;
         for statement in statements.iter() {
-            match statement {
-                Statement::Declare(a, b) => {
-                    val = self.compile_exp(b, block, fun, Some(a.to_owned()), gvars);
+            current = self.compile_statement(current, statement, frame)?;
+        }
+        Some(current)
+    }
+
+    fn compile_statement<'a>(
+        &'a self,
+        mut block: Block<'a>,
+        statement: &Statement,
+        frame: &mut Frame<'a, '_>,
+    ) -> Option<Block<'a>> {
+        let fun = frame.fun;
+        let loc = self.location(&statement.span);
+        match &statement.kind {
+            StatementKind::Declare(var, value) => {
+                let t = frame.type_of(var.span).unwrap_or(&lexer::Type::Int);
+                let local = fun.new_local(loc, self.lower_type(t, frame.runtime), &var.name);
+                frame.declare(var.span, local);
+                if let Some(rvalue) = self.compile_exp(value, &mut block, frame) {
+                    block.add_assignment(loc, local, rvalue);
                 }
-                Statement::Return(a) => match val {
-                    Some(a) => block.end_with_return(None, a),
-                    None => block.end_with_void_return(None),
-                },
-                Statement::If(_, _, _) => todo!(),
-                Statement::While(_, _) => todo!(),
-                Statement::Exp(exp) => {
-                    val = self.compile_exp(&Some(exp.to_owned()), block, fun, None, gvars)
+                Some(block)
+            }
+            StatementKind::Return(exp) => {
+                match self.compile_exp(&Some(exp.to_owned()), &mut block, frame) {
+                    // The exit code, truncated to the C `int` that `main` returns.
+                    Some(rvalue) if frame.entry => {
+                        let c_int_ty = self.context.new_type::<i32>();
+                        block.end_with_return(loc, self.context.new_cast(loc, rvalue, c_int_ty))
+                    }
+                    Some(rvalue) => block.end_with_return(loc, rvalue),
+                    None => block.end_with_void_return(loc),
                 }
-                Statement::Compound(_) => todo!(),
+                None
             }
+            StatementKind::If(condition, then, otherwise) => {
+                let condition = self.compile_exp(&Some(condition.to_owned()), &mut block, frame)?;
+                let then_block = fun.new_block("if_then");
+                match otherwise {
+                    None => {
+                        let end_block = fun.new_block("if_end");
+                        block.end_with_conditional(loc, condition, then_block, end_block);
+                        if let Some(then_end) = self.compile_statement(then_block, then, frame) {
+                            then_end.end_with_jump(loc, end_block);
+                        }
+                        Some(end_block)
+                    }
+                    Some(otherwise) => {
+                        let else_block = fun.new_block("if_else");
+                        block.end_with_conditional(loc, condition, then_block, else_block);
+                        let then_end = self.compile_statement(then_block, then, frame);
+                        let else_end = self.compile_statement(else_block, otherwise, frame);
+                        // Only create the join block if a branch falls through to it.
+                        if then_end.is_none() && else_end.is_none() {
+                            return None;
+                        }
+                        let end_block = fun.new_block("if_end");
+                        for branch_end in then_end.iter().chain(else_end.iter()) {
+                            branch_end.end_with_jump(loc, end_block);
+                        }
+                        Some(end_block)
+                    }
+                }
+            }
+            StatementKind::While(condition, body) => {
+                let cond_block = fun.new_block("while_cond");
+                let body_block = fun.new_block("while_body");
+                let end_block = fun.new_block("while_end");
+                frame.loops.insert(
+                    statement.span,
+                    LoopBlocks {
+                        exit: Some(end_block),
+                        next: Some(cond_block),
+                    },
+                );
+                block.end_with_jump(loc, cond_block);
+                let mut cond_end = cond_block;
+                let condition =
+                    self.compile_exp(&Some(condition.to_owned()), &mut cond_end, frame)?;
+                cond_end.end_with_conditional(loc, condition, body_block, end_block);
+                if let Some(body_end) = self.compile_statement(body_block, body, frame) {
+                    body_end.end_with_jump(loc, cond_block);
+                }
+                frame.loops.remove(&statement.span);
+                Some(end_block)
+            }
+            StatementKind::For(init, condition, step, body) => {
+                let block = match init {
+                    Some(init) => self.compile_statement(block, init, frame)?,
+                    None => block,
+                };
+                let cond_block = fun.new_block("for_cond");
+                let body_block = fun.new_block("for_body");
+                block.end_with_jump(loc, cond_block);
+                // Without a condition the loop only ends through a `break`.
+                match condition {
+                    Some(condition) => {
+                        let mut cond_end = cond_block;
+                        let condition =
+                            self.compile_exp(&Some(condition.to_owned()), &mut cond_end, frame)?;
+                        let end_block = frame.loop_block(statement.span, false);
+                        cond_end.end_with_conditional(loc, condition, body_block, end_block);
+                    }
+                    None => cond_block.end_with_jump(loc, body_block),
+                }
+                // The step runs after the body and on `continue`.
+                if let Some(body_end) = self.compile_statement(body_block, body, frame) {
+                    body_end.end_with_jump(loc, frame.loop_block(statement.span, true));
+                }
+                let blocks = frame.loops.remove(&statement.span).unwrap_or_default();
+                if let Some(mut step_block) = blocks.next {
+                    if let Some(step) = step {
+                        self.compile_eval(step, &mut step_block, frame);
+                    }
+                    step_block.end_with_jump(loc, cond_block);
+                }
+                blocks.exit
+            }
+            StatementKind::ForRange(var, from, to, body) => {
+                let (int_ty, ..) = self.types();
+                let counter = fun.new_local(loc, int_ty, &var.name);
+                frame.declare(var.span, counter);
+                let from = self.compile_exp(&Some(from.to_owned()), &mut block, frame)?;
+                block.add_assignment(loc, counter, from);
+                // The end is evaluated once, before the first iteration.
+                let end = fun.new_local(loc, int_ty, "for_end");
+                let to = self.compile_exp(&Some(to.to_owned()), &mut block, frame)?;
+                block.add_assignment(loc, end, to);
+
+                let cond_block = fun.new_block("for_cond");
+                let body_block = fun.new_block("for_body");
+                let end_block = frame.loop_block(statement.span, false);
+                block.end_with_jump(loc, cond_block);
+                let condition = self.context.new_comparison(
+                    loc,
+                    gccjit::ComparisonOp::LessThan,
+                    counter.to_rvalue(),
+                    end.to_rvalue(),
+                );
+                cond_block.end_with_conditional(loc, condition, body_block, end_block);
+                if let Some(body_end) = self.compile_statement(body_block, body, frame) {
+                    body_end.end_with_jump(loc, frame.loop_block(statement.span, true));
+                }
+                let blocks = frame.loops.remove(&statement.span).unwrap_or_default();
+                if let Some(step_block) = blocks.next {
+                    step_block.add_assignment_op(
+                        loc,
+                        counter,
+                        gccjit::BinaryOp::Plus,
+                        self.context.new_rvalue_one(int_ty),
+                    );
+                    step_block.end_with_jump(loc, cond_block);
+                }
+                Some(end_block)
+            }
+            StatementKind::Labeled(_, inner) => self.compile_statement(block, inner, frame),
+            StatementKind::Break(_) | StatementKind::Continue(_) => {
+                let target = frame.resolution.loop_target(statement.span)?;
+                let next = matches!(statement.kind, StatementKind::Continue(_));
+                block.end_with_jump(loc, frame.loop_block(target, next));
+                None
+            }
+            StatementKind::Exp(exp) => {
+                self.compile_eval(exp, &mut block, frame);
+                Some(block)
+            }
+            StatementKind::Compound(statements) => self.compile_block(block, statements, frame),
         }
     }
-    fn compile_binop<'a>(
+    /// Lowers an expression whose value is unused.
+    fn compile_eval<'a>(&'a self, exp: &Expression, block: &mut Block<'a>, frame: &mut Frame<'a, '_>) {
+        let rvalue = self.compile_exp(&Some(exp.to_owned()), block, frame);
+        // Assignments have already been added to the block, but a call
+        // made only for its side effects still has to be evaluated.
+        if let (ExpressionKind::FunctionCall(..), Some(rvalue)) = (&exp.kind, rvalue) {
+            block.add_eval(self.location(&exp.span), rvalue);
+        }
+    }
+
+    /// Lowers a call to one of `semantic::BUILTINS`.
+    fn compile_builtin<'a>(
+        &'a self,
+        name: &str,
+        arguments: &[Expression],
+        span: Span,
+        block: &mut Block<'a>,
+        frame: &mut Frame<'a, '_>,
+    ) -> Option<RValue<'a>> {
+        let (int_ty, ..) = self.types();
+        let loc = self.location(&span);
+        // `argc` and `argv` are only allowed in `main`, whose C parameters they read.
+        // Strings are lowered onto the runtime, see `runtime::Runtime`.
+        match name {
+            "argc" => Some(self.context.new_cast(loc, frame.fun.get_param(0), int_ty)),
+            "argv" => {
+                let index = self.compile_exp(&arguments.first().cloned(), block, frame)?;
+                let arg = self
+                    .context
+                    .new_array_access(loc, frame.fun.get_param(1), index);
+                let arg = self.context.new_cast(
+                    loc,
+                    arg.to_rvalue(),
+                    self.context.new_type::<char>().make_const().make_pointer(),
+                );
+                Some(self.context.new_call(loc, frame.runtime.from_c, &[arg]))
+            }
+            "len" => {
+                let s = self.compile_exp(&arguments.first().cloned(), block, frame)?;
+                Some(s.access_field(loc, frame.runtime.len_field).to_rvalue())
+            }
+            _ => None,
+        }
+    }
+
+    fn compile_binop(
         &self,
         binop: &BinOp,
     ) -> (Option<gccjit::BinaryOp>, Option<gccjit::ComparisonOp>) {
@@ -247,63 +760,598 @@ impl Compile<'static> {
         };
         (bnop, cop)
     }
-    fn compile_exp<'a>(
+    /// The storage of `exp`, which is a variable or an element or field of
+    /// one. Any other array or struct value is first stored in a temporary.
+    fn compile_place<'a>(
         &'a self,
-        expr: &Option<Expression>,
-        block: &Block,
-        fun: &'a gFunc<'a>,
-        var: Option<Variable>,
-        gvars: &mut GlobVals<'a>,
-    ) -> Option<RValue<'a>> {
-        let (int_ty, bool_ty, void_ty, char_ty) = self.types();
-        let mut rval = None;
-        let mut loc = None;
-
-        match expr {
-            Some(exp) => match exp {
-                Expression::BinOp(a, b, c) => {
-                    let name = match var.clone() {
-                        Some(a) => a.name,
-                        None => "somevar".to_string(),
-                    };
-                    loc = Some(fun.new_local(None, int_ty, name.clone()));
+        exp: &Expression,
+        block: &mut Block<'a>,
+        frame: &mut Frame<'a, '_>,
+    ) -> Option<LValue<'a>> {
+        match &exp.kind {
+            // A const is read as its value, which gcc can fold into the code
+            // using it. `&x` has the type of `x` itself, so it is read the same way.
+            ExpressionKind::Variable(_) | ExpressionKind::VariableRef(_) => match self.compile_const(exp, block, frame) {
+                Some(value) => Some(value),
+                None => frame.lookup(exp.span),
+            },
+            ExpressionKind::Index(array, index) => self.compile_index(array, index, block, frame),
+            ExpressionKind::Field(value, name) => {
+                let place = self.compile_place(value, block, frame)?;
+                let field = frame.runtime.field(place.to_rvalue().get_type(), name)?;
+                Some(place.access_field(self.location(&exp.span), field))
+            }
+            _ => {
+                let rvalue = self.compile_exp(&Some(exp.to_owned()), block, frame)?;
+                let local = frame.fun.new_local(self.location(&exp.span), rvalue.get_type(), "temp");
+                block.add_assignment(self.location(&exp.span), local, rvalue);
+                Some(local)
+            }
+        }
+    }
+
+    /// The `const` used at `exp`, in a temporary. The semantic checks have
+    /// worked out its value, so each use starts from that value.
+    fn compile_const<'a>(
+        &'a self,
+        exp: &Expression,
+        block: &mut Block<'a>,
+        frame: &mut Frame<'a, '_>,
+    ) -> Option<LValue<'a>> {
+        let id = frame.resolution.resolve(exp.span)?;
+        let value = frame.resolution.const_value(id)?;
+        let t = frame.resolution.symbol(id).t.as_ref()?;
+        let loc = self.location(&exp.span);
+        let local = frame
+            .fun
+            .new_local(loc, self.lower_type(t, frame.runtime), "const");
+        self.store_const(local, value, loc, block, frame);
+        Some(local)
+    }
 
-                    let binop = self.compile_binop(a);
-                    let parm = self
-                        .compile_exp(&Some(b.as_ref().to_owned()), block, fun, None, gvars)
-                        .unwrap();
+    fn store_const<'a>(
+        &'a self,
+        place: LValue<'a>,
+        value: &ConstValue,
+        loc: Option<Location<'a>>,
+        block: &mut Block<'a>,
+        frame: &mut Frame<'a, '_>,
+    ) {
+        let (int_ty, bool_ty, ..) = self.types();
+        let rvalue = match value {
+            ConstValue::Int(i) => self.context.new_rvalue_from_long(int_ty, *i),
+            ConstValue::Float(x) => {
+                self.context
+                    .new_rvalue_from_double(self.context.new_type::<f64>(), x.0)
+            }
+            ConstValue::Bool(b) => self.context.new_rvalue_from_int(bool_ty, *b as u64),
+            ConstValue::Str(s) => {
+                let ptr = self.context.new_string_literal(s);
+                let len = self.context.new_rvalue_from_long(int_ty, s.len() as i64);
+                frame.runtime.new_str(frame.fun, block, ptr, len)
+            }
+            ConstValue::Array(elements) => {
+                let array = match frame.runtime.as_array(place.to_rvalue().get_type()) {
+                    Some(array) => array,
+                    None => return,
+                };
+                let items = place.access_field(loc, array.items).to_rvalue();
+                for (i, element) in elements.iter().enumerate() {
+                    let i = self.context.new_rvalue_from_long(int_ty, i as i64);
+                    let item = self.context.new_array_access(loc, items, i);
+                    self.store_const(item, element, loc, block, frame);
+                }
+                return;
+            }
+        };
+        block.add_assignment(loc, place, rvalue);
+    }
 
-                    match binop.0 {
-                        Some(e) => block.add_assignment_op(None, loc.unwrap(), e, parm),
-                        None => todo!(),
+    /// The element `array[index]`, after checking that `index` is in bounds.
+    fn compile_index<'a>(
+        &'a self,
+        array: &Expression,
+        index: &Expression,
+        block: &mut Block<'a>,
+        frame: &mut Frame<'a, '_>,
+    ) -> Option<LValue<'a>> {
+        let (int_ty, ..) = self.types();
+        let loc = self.location(&index.span);
+        let place = self.compile_place(array, block, frame)?;
+        let index = self.compile_exp(&Some(index.to_owned()), block, frame)?;
+        let array = frame.runtime.as_array(place.to_rvalue().get_type())?;
+        let len = self.context.new_rvalue_from_long(int_ty, array.len as i64);
+        let index = self
+            .context
+            .new_call(loc, frame.runtime.check_index, &[index, len]);
+        let items = place.access_field(loc, array.items).to_rvalue();
+        Some(self.context.new_array_access(loc, items, index))
+    }
+
+    /// Lowers a `match` into a test per arm, each either jumping to the arm or
+    /// on to the next test. The last arm needs no test, since the type
+    /// checker has made sure that the arms cover every value. Each arm stores
+    /// its result in a temporary and jumps to a new block, which `block` is
+    /// left at.
+    fn compile_match<'a>(
+        &'a self,
+        value: &Expression,
+        arms: &[MatchArm],
+        span: Span,
+        block: &mut Block<'a>,
+        frame: &mut Frame<'a, '_>,
+    ) -> Option<RValue<'a>> {
+        let (_, _, void_ty, _) = self.types();
+        let loc = self.location(&span);
+        let place = self.compile_place(value, block, frame)?;
+        let end_block = frame.fun.new_block("match_end");
+        let mut result: Option<LValue<'a>> = None;
+        for (i, arm) in arms.iter().enumerate() {
+            let mut arm_block = frame.fun.new_block("match_arm");
+            let test = match i + 1 < arms.len() {
+                true => self.compile_pattern_test(&arm.pattern, place, block, frame),
+                false => None,
+            };
+            match test {
+                Some(test) => {
+                    let next = frame.fun.new_block("match_next");
+                    block.end_with_conditional(loc, test, arm_block, next);
+                    *block = next;
+                }
+                None => block.end_with_jump(loc, arm_block),
+            }
+            if let PatternKind::Variant(_, name, bindings) = &arm.pattern.kind {
+                let variant = frame.runtime.variant(place.to_rvalue().get_type(), name)?;
+                for (binding, field) in bindings.iter().zip(variant.fields.iter()) {
+                    let (binding, (payload, member)) = match (binding, variant.payload) {
+                        (Some(binding), Some(payload)) => (binding, payload),
+                        _ => continue,
                     };
-                    let mut hashmap: HashMap<String, LValue> = HashMap::new();
-                    hashmap.insert(name, loc.clone().unwrap());
-                    gvars.add_val(hashmap);
-                    rval = Some(parm.clone());
+                    let value = place
+                        .access_field(loc, payload)
+                        .access_field(loc, member)
+                        .access_field(loc, *field);
+                    let local =
+                        frame
+                            .fun
+                            .new_local(loc, value.to_rvalue().get_type(), &binding.name);
+                    arm_block.add_assignment(loc, local, value.to_rvalue());
+                    frame.declare(binding.span, local);
                 }
-                Expression::UnOp(_, _) => todo!(),
-                Expression::Int(a) => rval = Some(self.context.new_rvalue_from_int(int_ty, *a)),
-                Expression::Char(s) => todo!(),
-                Expression::MLStr(_) => todo!(),
-                Expression::FunctionCall(_, _) => todo!(),
-                Expression::Bool(_) => todo!(),
-                Expression::Variable(_) => rval = Some(fun.get_param(0).to_rvalue()),
-                Expression::VariableRef(_) => todo!(),
-                Expression::Assign(a, b) => {
-                    let parm =
-                        self.compile_exp(&Some(b.as_ref().to_owned()), block, fun, None, gvars);
-                    loc = Some(fun.new_local(None, int_ty, a));
-                    // let mut hashmap: HashMap<String, LValue> = HashMap::new();
-                    // hashmap.insert(a.to_string(), loc.clone().unwrap());
-                    // let i = gvars.add_val(hashmap);
-                    block.add_assignment(None, loc.unwrap(), parm.unwrap())
+            }
+            match self.compile_exp(&Some(arm.value.to_owned()), &mut arm_block, frame) {
+                Some(value) if value.get_type() == void_ty => arm_block.add_eval(loc, value),
+                Some(value) => {
+                    let result = *result.get_or_insert_with(|| {
+                        frame.fun.new_local(loc, value.get_type(), "match")
+                    });
+                    arm_block.add_assignment(loc, result, value);
                 }
-                Expression::AssignPostfix(_, _) => todo!(),
-                Expression::Ternary(_, _, _) => todo!(),
-            },
-            None => todo!(),
+                None => {}
+            }
+            arm_block.end_with_jump(loc, end_block);
+            // Nothing can reach the arms after one that matches everything.
+            if test.is_none() {
+                break;
+            }
+        }
+        if arms.is_empty() {
+            block.end_with_jump(loc, end_block);
+        }
+        *block = end_block;
+        result.map(|result| result.to_rvalue())
+    }
+
+    /// Whether `place` matches `pattern`, or `None` if every value does.
+    fn compile_pattern_test<'a>(
+        &'a self,
+        pattern: &Pattern,
+        place: LValue<'a>,
+        block: &mut Block<'a>,
+        frame: &mut Frame<'a, '_>,
+    ) -> Option<RValue<'a>> {
+        let (int_ty, bool_ty, ..) = self.types();
+        let loc = self.location(&pattern.span);
+        let value = place.to_rvalue();
+        let (lhs, rhs) = match &pattern.kind {
+            PatternKind::Wildcard => return None,
+            PatternKind::Int(i) => (value, self.context.new_rvalue_from_long(int_ty, *i as i64)),
+            PatternKind::Bool(b) => (value, self.context.new_rvalue_from_int(bool_ty, *b as u64)),
+            PatternKind::Str(s) => {
+                let ptr = self.context.new_string_literal(s);
+                let len = self.context.new_rvalue_from_long(int_ty, s.len() as i64);
+                let literal = frame.runtime.new_str(frame.fun, block, ptr, len);
+                return Some(self.context.new_call(loc, frame.runtime.eq, &[value, literal]));
+            }
+            PatternKind::Variant(_, name, _) => {
+                let variant = frame.runtime.variant(value.get_type(), name)?;
+                let index = self.context.new_rvalue_from_long(int_ty, variant.index);
+                (place.access_field(loc, variant.tag).to_rvalue(), index)
+            }
         };
-        rval
+        Some(
+            self.context
+                .new_comparison(loc, gccjit::ComparisonOp::Equals, lhs, rhs),
+        )
+    }
+
+    /// Lowers the operators the type checker allows on strings.
+    fn compile_str_binop<'a>(
+        &'a self,
+        op: &BinOp,
+        lhs: RValue<'a>,
+        rhs: RValue<'a>,
+        loc: Option<Location<'a>>,
+        frame: &Frame<'a, '_>,
+    ) -> Option<RValue<'a>> {
+        let runtime = frame.runtime;
+        match op {
+            BinOp::Addition => Some(self.context.new_call(loc, runtime.concat, &[lhs, rhs])),
+            BinOp::Equal => Some(self.context.new_call(loc, runtime.eq, &[lhs, rhs])),
+            BinOp::NotEqual => {
+                let eq = self.context.new_call(loc, runtime.eq, &[lhs, rhs]);
+                let (_, bool_ty, ..) = self.types();
+                Some(
+                    self.context
+                        .new_unary_op(loc, UnaryOp::LogicalNegate, bool_ty, eq),
+                )
+            }
+            _ => None,
+        }
+    }
+    fn compile_exp<'a>(
+        &'a self,
+        expr: &Option<Expression>,
+        block: &mut Block<'a>,
+        frame: &mut Frame<'a, '_>,
+    ) -> Option<RValue<'a>> {
+        let (int_ty, bool_ty, ..) = self.types();
+        let exp = match expr {
+            Some(exp) => exp,
+            // An absent expression, like the value of a bare `return`, has none.
+            None => return None,
+        };
+        let loc = self.location(&exp.span);
+        match &exp.kind {
+            ExpressionKind::BinOp(op, lhs, rhs) => {
+                let lhs = self.compile_exp(&Some(lhs.as_ref().to_owned()), block, frame)?;
+                let rhs = self.compile_exp(&Some(rhs.as_ref().to_owned()), block, frame)?;
+                if lhs.get_type() == frame.runtime.str_ty {
+                    return self.compile_str_binop(op, lhs, rhs, loc, frame);
+                }
+                match self.compile_binop(op) {
+                    (Some(op), _) => Some(self.context.new_binary_op(
+                        loc,
+                        op,
+                        lhs.get_type(),
+                        lhs,
+                        rhs,
+                    )),
+                    (None, Some(op)) => Some(self.context.new_comparison(loc, op, lhs, rhs)),
+                    (None, None) => None,
+                }
+            }
+            ExpressionKind::UnOp(op, operand) => {
+                let rvalue = self.compile_exp(&Some(operand.as_ref().to_owned()), block, frame)?;
+                let op = match op {
+                    lexer::UnOp::Negation => UnaryOp::Minus,
+                    lexer::UnOp::BitComp => UnaryOp::BitwiseNegate,
+                    lexer::UnOp::LogicalNeg => UnaryOp::LogicalNegate,
+                };
+                // In the operand's own type, so that negating a float gives a float.
+                Some(
+                    self.context
+                        .new_unary_op(loc, op, rvalue.get_type(), rvalue),
+                )
+            }
+            // A literal above `i64::MAX` wraps around, so that negating
+            // `9223372036854775808` gives `i64::MIN`.
+            ExpressionKind::Int(a) => Some(self.context.new_rvalue_from_long(int_ty, *a as i64)),
+            ExpressionKind::Float(x) => Some(
+                self.context
+                    .new_rvalue_from_double(self.context.new_type::<f64>(), x.0),
+            ),
+            // Converting a float to an int truncates it toward zero, as in C.
+            ExpressionKind::Cast(value, t) => {
+                let rvalue = self.compile_exp(&Some(value.as_ref().to_owned()), block, frame)?;
+                Some(
+                    self.context
+                        .new_cast(loc, rvalue, self.lower_type(t, frame.runtime)),
+                )
+            }
+            ExpressionKind::Char(s) | ExpressionKind::MLStr(s) => {
+                let ptr = self.context.new_string_literal(s);
+                let len = self.context.new_rvalue_from_long(int_ty, s.len() as i64);
+                Some(frame.runtime.new_str(frame.fun, block, ptr, len))
+            }
+            ExpressionKind::FunctionCall(name, arguments) => {
+                let id = frame.resolution.resolve(exp.span)?;
+                if frame.resolution.symbol(id).kind == SymbolKind::Builtin {
+                    return self.compile_builtin(name, arguments, exp.span, block, frame);
+                }
+                let function = frame.function(exp.span)?;
+                let mut values = Vec::with_capacity(arguments.len());
+                for argument in arguments.iter() {
+                    values.push(self.compile_exp(&Some(argument.to_owned()), block, frame)?);
+                }
+                Some(self.context.new_call(loc, function, &values))
+            }
+            ExpressionKind::Bool(b) => Some(self.context.new_rvalue_from_int(bool_ty, *b as u64)),
+            ExpressionKind::Variable(_) | ExpressionKind::VariableRef(_) => self
+                .compile_place(exp, block, frame)
+                .map(|l| l.to_rvalue()),
+            ExpressionKind::Assign(_, value) => {
+                let rvalue = self.compile_exp(&Some(value.as_ref().to_owned()), block, frame)?;
+                let lvalue = frame.lookup(exp.span)?;
+                block.add_assignment(loc, lvalue, rvalue);
+                Some(lvalue.to_rvalue())
+            }
+            ExpressionKind::Array(elements) => {
+                let mut values = Vec::with_capacity(elements.len());
+                for element in elements.iter() {
+                    values.push(self.compile_exp(&Some(element.to_owned()), block, frame)?);
+                }
+                let element = match values.first() {
+                    Some(value) => value.get_type(),
+                    // The type checker worked out the element type of `[]`
+                    // from where it is used.
+                    None => {
+                        let t = frame.resolution.empty_array_element(exp.span)?;
+                        self.lower_type(t, frame.runtime)
+                    }
+                };
+                let array = frame.runtime.array_type(element, values.len() as u64);
+                let local = frame.fun.new_local(loc, array.ty, "array");
+                let items = local.access_field(loc, array.items).to_rvalue();
+                for (i, value) in values.into_iter().enumerate() {
+                    let i = self.context.new_rvalue_from_long(int_ty, i as i64);
+                    block.add_assignment(loc, self.context.new_array_access(loc, items, i), value);
+                }
+                Some(local.to_rvalue())
+            }
+            ExpressionKind::Index(array, index) => self
+                .compile_index(array, index, block, frame)
+                .map(|element| element.to_rvalue()),
+            ExpressionKind::AssignIndex(array, index, value) => {
+                let rvalue = self.compile_exp(&Some(value.as_ref().to_owned()), block, frame)?;
+                let element = self.compile_index(array, index, block, frame)?;
+                block.add_assignment(loc, element, rvalue);
+                Some(element.to_rvalue())
+            }
+            ExpressionKind::StructLiteral(name, fields) => {
+                let ty = frame.runtime.named_type(name)?;
+                let local = frame.fun.new_local(loc, ty, "literal");
+                for (field, value) in fields.iter() {
+                    let rvalue = self.compile_exp(&Some(value.to_owned()), block, frame)?;
+                    let field = frame.runtime.field(ty, field)?;
+                    block.add_assignment(loc, local.access_field(loc, field), rvalue);
+                }
+                Some(local.to_rvalue())
+            }
+            ExpressionKind::Field(..) => self
+                .compile_place(exp, block, frame)
+                .map(|field| field.to_rvalue()),
+            ExpressionKind::AssignField(target, name, value) => {
+                let rvalue = self.compile_exp(&Some(value.as_ref().to_owned()), block, frame)?;
+                let place = self.compile_place(target, block, frame)?;
+                let field = frame.runtime.field(place.to_rvalue().get_type(), name)?;
+                let field = place.access_field(loc, field);
+                block.add_assignment(loc, field, rvalue);
+                Some(field.to_rvalue())
+            }
+            ExpressionKind::Variant(name, variant, values) => {
+                let ty = frame.runtime.named_type(name)?;
+                let layout = frame.runtime.variant(ty, variant)?;
+                let local = frame.fun.new_local(loc, ty, "variant");
+                let tag = self.context.new_rvalue_from_long(int_ty, layout.index);
+                block.add_assignment(loc, local.access_field(loc, layout.tag), tag);
+                if let Some((payload, member)) = layout.payload {
+                    let fields = local.access_field(loc, payload).access_field(loc, member);
+                    for (value, field) in values.iter().zip(layout.fields.iter()) {
+                        let rvalue = self.compile_exp(&Some(value.to_owned()), block, frame)?;
+                        block.add_assignment(loc, fields.access_field(loc, *field), rvalue);
+                    }
+                }
+                Some(local.to_rvalue())
+            }
+            ExpressionKind::Match(value, arms) => {
+                self.compile_match(value, arms, exp.span, block, frame)
+            }
+            ExpressionKind::AssignPostfix(_, value) => {
+                // `x++` evaluates to `x` as it was before the assignment.
+                let lvalue = frame.lookup(exp.span)?;
+                let old = frame
+                    .fun
+                    .new_local(loc, lvalue.to_rvalue().get_type(), "postfix");
+                block.add_assignment(loc, old, lvalue.to_rvalue());
+                let rvalue = self.compile_exp(&Some(value.as_ref().to_owned()), block, frame)?;
+                block.add_assignment(loc, lvalue, rvalue);
+                Some(old.to_rvalue())
+            }
+            ExpressionKind::Ternary(condition, then, otherwise) => {
+                self.compile_ternary(condition, then, otherwise, exp.span, block, frame)
+            }
+        }
+    }
+
+    /// Lowers `condition ? then : otherwise` into a block for each branch,
+    /// which store their value in a temporary and jump to a new block that
+    /// `block` is left at.
+    fn compile_ternary<'a>(
+        &'a self,
+        condition: &Expression,
+        then: &Expression,
+        otherwise: &Expression,
+        span: Span,
+        block: &mut Block<'a>,
+        frame: &mut Frame<'a, '_>,
+    ) -> Option<RValue<'a>> {
+        let (_, _, void_ty, _) = self.types();
+        let loc = self.location(&span);
+        let condition = self.compile_exp(&Some(condition.to_owned()), block, frame)?;
+        let then_block = frame.fun.new_block("ternary_then");
+        let else_block = frame.fun.new_block("ternary_else");
+        let end_block = frame.fun.new_block("ternary_end");
+        block.end_with_conditional(loc, condition, then_block, else_block);
+        let mut result: Option<LValue<'a>> = None;
+        for (mut branch, value) in [(then_block, then), (else_block, otherwise)] {
+            match self.compile_exp(&Some(value.to_owned()), &mut branch, frame) {
+                Some(value) if value.get_type() == void_ty => branch.add_eval(loc, value),
+                Some(value) => {
+                    let result = *result.get_or_insert_with(|| {
+                        frame.fun.new_local(loc, value.get_type(), "ternary")
+                    });
+                    branch.add_assignment(loc, result, value);
+                }
+                None => {}
+            }
+            branch.end_with_jump(loc, end_block);
+        }
+        *block = end_block;
+        result.map(|result| result.to_rvalue())
+    }
+}
+
+/// `value` as it is laid out in memory, if it can be the initial value of a
+/// global. Strings can't, since they point at bytes with no address until
+/// the program is loaded.
+fn const_bytes(value: &ConstValue) -> Option<Vec<u8>> {
+    match value {
+        ConstValue::Int(i) => Some(i.to_ne_bytes().to_vec()),
+        ConstValue::Float(x) => Some(x.0.to_ne_bytes().to_vec()),
+        ConstValue::Bool(b) => Some(vec![*b as u8]),
+        ConstValue::Str(_) => None,
+        // An array is a struct of nothing but its items, so it has no padding.
+        ConstValue::Array(elements) => {
+            let mut bytes = Vec::new();
+            for element in elements.iter() {
+                bytes.extend(const_bytes(element)?);
+            }
+            Some(bytes)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lexer::{tokenize, Parser};
+
+    fn options() -> Options {
+        Options {
+            release: false,
+            verbose: false,
+            toolchain: Toolchain::Clang,
+        }
+    }
+
+    /// Compiles `src` in memory and runs it with `args`, returning its exit code.
+    fn run(src: &str, args: &[&str]) -> i32 {
+        let mut program = Parser::new(tokenize(src, "").unwrap(), "".into()).parse().0;
+        let (resolution, errors) = semantic::analyze(&mut program);
+        assert!(errors.is_empty(), "{:?}", errors);
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        Compile::new(SourceMap::new(), options())
+            .run(program, &resolution, &args)
+            .unwrap()
+    }
+
+    /// Like `run`, for one of the programs in `examples`, which sees its
+    /// name as `argv[0]`.
+    fn run_example(name: &str, args: &[&str]) -> i32 {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../examples")
+            .join(name);
+        let mut sources = SourceMap::new();
+        let (mut program, errors) = lexer::load_program(&path, &mut sources).unwrap();
+        assert!(errors.is_empty(), "{:?}", errors);
+        let (resolution, errors) = semantic::analyze(&mut program);
+        assert!(errors.is_empty(), "{:?}", errors);
+        let args: Vec<String> = std::iter::once(&name)
+            .chain(args)
+            .map(|arg| arg.to_string())
+            .collect();
+        Compile::new(sources, options())
+            .run(program, &resolution, &args)
+            .unwrap()
+    }
+
+    #[test]
+    fn returns_the_exit_code_and_reads_argv() {
+        assert_eq!(run_example("args.trq", &[]), 0);
+        assert_eq!(run_example("args.trq", &["a", "--", "-b"]), 3);
+        let src = "fn main(): int {\n    return argc() * 10 + len(argv(1))\n}";
+        assert_eq!(run(src, &["prog", "four"]), 24);
+    }
+
+    #[test]
+    fn keeps_the_items_of_modules_apart() {
+        // `shapes` and `maths` both have a private `checked`.
+        assert_eq!(run_example("modules/main.trq", &[]), 0);
+    }
+
+    #[test]
+    fn passes_arguments_to_calls() {
+        assert_eq!(run_example("calls.trq", &[]), 0);
+        let src = "fn sub(a: int, b: int): int {\n    return a - b\n}\nfn main(): int {\n    int big = 9000000000\n    return sub(big, 8999999990) + sub(1, 2) * 2\n}";
+        assert_eq!(run(src, &[]), 8);
+        let src = "fn main(): int {\n    int min = -9223372036854775808\n    return min < 0 ? 3 : 4\n}";
+        assert_eq!(run(src, &[]), 3);
+    }
+
+    #[test]
+    fn lowers_unary_operators_and_ternaries() {
+        assert_eq!(run_example("loops.trq", &[]), 14);
+        let src = "fn main(): int {\n    bool done = false\n    int x = 0\n    while (!done) {\n        x += 1\n        done = x > 2\n    }\n    int y = ~x\n    return x > 2 ? -y : 0\n}";
+        assert_eq!(run(src, &[]), 4);
+    }
+
+    #[test]
+    fn negates_floats() {
+        assert_eq!(run_example("floats.trq", &[]), 0);
+        let src = "fn main(): int {\n    float x = 2.5\n    return (-x * 2.0) as int + 6\n}";
+        assert_eq!(run(src, &[]), 1);
+    }
+
+    #[test]
+    fn builds_empty_arrays() {
+        let src = "fn count(a: [int; 0]): int {\n    return 3\n}\nfn none(): [int; 0] {\n    return []\n}\nfn main(): int {\n    let a: [int; 0] = []\n    a = none()\n    return count([]) + count(a)\n}";
+        assert_eq!(run(src, &[]), 6);
+    }
+
+    #[test]
+    fn lays_out_global_initialisers() {
+        let array = ConstValue::Array(vec![ConstValue::Int(1), ConstValue::Int(-2)]);
+        let mut bytes = 1i64.to_ne_bytes().to_vec();
+        bytes.extend((-2i64).to_ne_bytes());
+        assert_eq!(const_bytes(&array), Some(bytes));
+        assert_eq!(const_bytes(&ConstValue::Bool(true)), Some(vec![1]));
+        let strings = ConstValue::Array(vec![ConstValue::Str("hi".into())]);
+        assert_eq!(const_bytes(&strings), None);
+    }
+
+    #[test]
+    fn initialises_globals_without_main() {
+        let src = "pub let count = 41\npub const scale = 2.5\npub fn bump(): int {\n    count += 1\n    return count\n}";
+        let mut program = Parser::new(tokenize(src, "").unwrap(), "".into()).parse().0;
+        let (resolution, errors) = semantic::analyze(&mut program);
+        assert!(errors.is_empty(), "{:?}", errors);
+        let output = std::env::temp_dir().join(format!("zeta_globals{}.o", std::process::id()));
+        Compile::new(SourceMap::new(), options())
+            .compile(program, &resolution, Emit::Object, &output)
+            .unwrap();
+        let _ = std::fs::remove_file(&output);
+
+        let src = "pub let greeting = \"hi\"";
+        let mut program = Parser::new(tokenize(src, "").unwrap(), "".into()).parse().0;
+        let (resolution, errors) = semantic::analyze(&mut program);
+        assert!(errors.is_empty(), "{:?}", errors);
+        let result = Compile::new(SourceMap::new(), options()).compile(
+            program,
+            &resolution,
+            Emit::Object,
+            &output,
+        );
+        assert!(result.is_err());
     }
 }
@@ -0,0 +1,484 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use gccjit::{
+    BinaryOp, Block, ComparisonOp, Context, Field, Function, FunctionType, LValue, RValue, Struct,
+    ToRValue, Type,
+};
+
+/// The runtime representation of Zeta strings and the helpers that operate
+/// on them, built into the same context as the program.
+///
+/// A string is a `struct zeta_str { const char *ptr; long len; }` passed by
+/// value. `ptr` is always NUL terminated so that it can be handed to C, but
+/// `len` is what the runtime goes by.
+pub(crate) struct Runtime<'a> {
+    pub str_ty: Type<'a>,
+    pub ptr_field: Field<'a>,
+    pub len_field: Field<'a>,
+    /// `bool zeta_str_eq(struct zeta_str, struct zeta_str)`
+    pub eq: Function<'a>,
+    /// `struct zeta_str zeta_str_concat(struct zeta_str, struct zeta_str)`,
+    /// which copies both strings into a new heap allocation.
+    pub concat: Function<'a>,
+    /// `struct zeta_str zeta_str_from_c(const char *)`
+    pub from_c: Function<'a>,
+    /// `long zeta_check_index(long index, long len)`, which returns `index`
+    /// if it is in bounds and otherwise stops the program.
+    pub check_index: Function<'a>,
+    context: &'a Context<'static>,
+    /// The struct wrapping each array type used so far, see [`Runtime::array_type`].
+    arrays: RefCell<Vec<ArrayType<'a>>>,
+    /// The program's own structs by name, see [`Runtime::declare_struct`].
+    structs: RefCell<HashMap<String, StructType<'a>>>,
+    /// The program's own enums by name, see [`Runtime::define_enum`].
+    enums: RefCell<HashMap<String, EnumType<'a>>>,
+}
+
+/// A `struct` declared in the program.
+struct StructType<'a> {
+    handle: Struct<'a>,
+    fields: Vec<(String, Field<'a>)>,
+}
+
+/// An `enum` declared in the program, as a tagged union:
+/// `struct { long tag; union { struct { ... } Variant; ... } payload; }`.
+/// Variants without a payload have no member in the union, and an enum
+/// without any payloads has no union at all.
+struct EnumType<'a> {
+    handle: Struct<'a>,
+    tag: Option<Field<'a>>,
+    payload: Option<Field<'a>>,
+    /// Each variant's name, union member and payload fields, in declaration order.
+    variants: Vec<(String, Option<Field<'a>>, Vec<Field<'a>>)>,
+}
+
+/// Where the parts of one enum variant are stored, see [`Runtime::variant`].
+pub(crate) struct VariantLayout<'a> {
+    pub tag: Field<'a>,
+    /// The value of `tag` for this variant.
+    pub index: i64,
+    /// The union and the variant's member of it, if the variant has a payload.
+    pub payload: Option<(Field<'a>, Field<'a>)>,
+    pub fields: Vec<Field<'a>>,
+}
+
+/// `struct { element items[len]; }`. Arrays are wrapped in a struct so that,
+/// unlike C arrays, they can be assigned, passed and returned by value.
+#[derive(Clone, Copy)]
+pub(crate) struct ArrayType<'a> {
+    pub ty: Type<'a>,
+    pub items: Field<'a>,
+    pub element: Type<'a>,
+    pub len: u64,
+}
+
+impl<'a> Runtime<'a> {
+    pub fn new(context: &'a Context<'static>) -> Self {
+        let int_ty = context.new_type::<i64>();
+        let size_ty = context.new_type::<usize>();
+        let c_int_ty = context.new_type::<i32>();
+        let bool_ty = context.new_type::<bool>();
+        let char_ptr_ty = context.new_type::<char>().make_const().make_pointer();
+        let void_ptr_ty = context.new_type::<*mut ()>();
+        let const_void_ptr_ty = context.new_type::<*const ()>();
+
+        let ptr_field = context.new_field(None, char_ptr_ty, "ptr");
+        let len_field = context.new_field(None, int_ty, "len");
+        let str_ty = context
+            .new_struct_type(None, "zeta_str", &[ptr_field, len_field])
+            .as_type();
+
+        // The program's own items cannot take the names of these, see
+        // `semantic::RESERVED`. The helpers defined here all start with
+        // `zeta_`, which no Zeta name can, since Zeta names cannot contain `_`.
+        let extern_fn = |name: &str, ret: Type<'a>, params: &[Type<'a>]| {
+            let params: Vec<_> = params
+                .iter()
+                .enumerate()
+                .map(|(i, &t)| context.new_parameter(None, t, format!("p{}", i)))
+                .collect();
+            context.new_function(None, FunctionType::Extern, ret, &params, name, false)
+        };
+        let memcmp = extern_fn(
+            "memcmp",
+            c_int_ty,
+            &[const_void_ptr_ty, const_void_ptr_ty, size_ty],
+        );
+        let memcpy = extern_fn(
+            "memcpy",
+            void_ptr_ty,
+            &[void_ptr_ty, const_void_ptr_ty, size_ty],
+        );
+        let malloc = extern_fn("malloc", void_ptr_ty, &[size_ty]);
+        let strlen = extern_fn("strlen", size_ty, &[char_ptr_ty]);
+
+        let string = |value: RValue<'a>| {
+            (
+                value.access_field(None, ptr_field).to_rvalue(),
+                value.access_field(None, len_field).to_rvalue(),
+            )
+        };
+
+        // Equal lengths, then equal bytes.
+        let a = context.new_parameter(None, str_ty, "a");
+        let b = context.new_parameter(None, str_ty, "b");
+        let eq = context.new_function(
+            None,
+            FunctionType::Internal,
+            bool_ty,
+            &[a, b],
+            "zeta_str_eq",
+            false,
+        );
+        let ((a_ptr, a_len), (b_ptr, b_len)) = (string(a.to_rvalue()), string(b.to_rvalue()));
+        let entry = eq.new_block("entry");
+        let compare = eq.new_block("compare");
+        let differ = eq.new_block("differ");
+        let same_len = context.new_comparison(None, ComparisonOp::Equals, a_len, b_len);
+        entry.end_with_conditional(None, same_len, compare, differ);
+        let bytes = context.new_call(
+            None,
+            memcmp,
+            &[
+                context.new_cast(None, a_ptr, const_void_ptr_ty),
+                context.new_cast(None, b_ptr, const_void_ptr_ty),
+                context.new_cast(None, a_len, size_ty),
+            ],
+        );
+        compare.end_with_return(
+            None,
+            context.new_comparison(
+                None,
+                ComparisonOp::Equals,
+                bytes,
+                context.new_rvalue_zero(c_int_ty),
+            ),
+        );
+        differ.end_with_return(None, context.new_rvalue_zero(bool_ty));
+
+        // Both strings and a NUL terminator in one allocation.
+        let a = context.new_parameter(None, str_ty, "a");
+        let b = context.new_parameter(None, str_ty, "b");
+        let concat = context.new_function(
+            None,
+            FunctionType::Internal,
+            str_ty,
+            &[a, b],
+            "zeta_str_concat",
+            false,
+        );
+        let ((a_ptr, a_len), (b_ptr, b_len)) = (string(a.to_rvalue()), string(b.to_rvalue()));
+        let block = concat.new_block("entry");
+        let len = context.new_binary_op(None, BinaryOp::Plus, int_ty, a_len, b_len);
+        let size = context.new_binary_op(
+            None,
+            BinaryOp::Plus,
+            size_ty,
+            context.new_cast(None, len, size_ty),
+            context.new_rvalue_one(size_ty),
+        );
+        let buf = concat.new_local(None, context.new_type::<char>().make_pointer(), "buf");
+        block.add_assignment(
+            None,
+            buf,
+            context.new_cast(
+                None,
+                context.new_call(None, malloc, &[size]),
+                context.new_type::<char>().make_pointer(),
+            ),
+        );
+        let copy = |dest: RValue<'a>, src: RValue<'a>, len: RValue<'a>| {
+            block.add_eval(
+                None,
+                context.new_call(
+                    None,
+                    memcpy,
+                    &[
+                        context.new_cast(None, dest, void_ptr_ty),
+                        context.new_cast(None, src, const_void_ptr_ty),
+                        context.new_cast(None, len, size_ty),
+                    ],
+                ),
+            );
+        };
+        copy(buf.to_rvalue(), a_ptr, a_len);
+        let tail = context
+            .new_array_access(None, buf.to_rvalue(), a_len)
+            .get_address(None);
+        copy(tail, b_ptr, b_len);
+        block.add_assignment(
+            None,
+            context.new_array_access(None, buf.to_rvalue(), len),
+            context.new_rvalue_zero(context.new_type::<char>()),
+        );
+        let result = concat.new_local(None, str_ty, "result");
+        let ptr = context.new_cast(None, buf.to_rvalue(), char_ptr_ty);
+        store_str(&block, (ptr_field, len_field), result, ptr, len);
+        block.end_with_return(None, result.to_rvalue());
+
+        // Wraps a C string such as an element of `argv`.
+        let s = context.new_parameter(None, char_ptr_ty, "s");
+        let from_c = context.new_function(
+            None,
+            FunctionType::Internal,
+            str_ty,
+            &[s],
+            "zeta_str_from_c",
+            false,
+        );
+        let block = from_c.new_block("entry");
+        let result = from_c.new_local(None, str_ty, "result");
+        let len = context.new_cast(
+            None,
+            context.new_call(None, strlen, &[s.to_rvalue()]),
+            int_ty,
+        );
+        store_str(&block, (ptr_field, len_field), result, s.to_rvalue(), len);
+        block.end_with_return(None, result.to_rvalue());
+
+        // Reports an out of bounds index the way Rust does, then aborts.
+        let index = context.new_parameter(None, int_ty, "index");
+        let len = context.new_parameter(None, int_ty, "len");
+        let check_index = context.new_function(
+            None,
+            FunctionType::Internal,
+            int_ty,
+            &[index, len],
+            "zeta_check_index",
+            false,
+        );
+        let fd = context.new_parameter(None, c_int_ty, "fd");
+        let format = context.new_parameter(None, char_ptr_ty, "format");
+        let dprintf = context.new_function(
+            None,
+            FunctionType::Extern,
+            c_int_ty,
+            &[fd, format],
+            "dprintf",
+            true,
+        );
+        let abort = extern_fn("abort", context.new_type::<()>(), &[]);
+        let entry = check_index.new_block("entry");
+        let fail = check_index.new_block("fail");
+        let ok = check_index.new_block("ok");
+        let (index, len) = (index.to_rvalue(), len.to_rvalue());
+        let out_of_bounds = context.new_binary_op(
+            None,
+            BinaryOp::LogicalOr,
+            bool_ty,
+            context.new_comparison(
+                None,
+                ComparisonOp::LessThan,
+                index,
+                context.new_rvalue_zero(int_ty),
+            ),
+            context.new_comparison(None, ComparisonOp::GreaterThanEquals, index, len),
+        );
+        entry.end_with_conditional(None, out_of_bounds, fail, ok);
+        fail.add_eval(
+            None,
+            context.new_call(
+                None,
+                dprintf,
+                &[
+                    context.new_rvalue_from_int(c_int_ty, 2),
+                    context.new_string_literal(
+                        "index out of bounds: the len is %ld but the index is %ld\n",
+                    ),
+                    len,
+                    index,
+                ],
+            ),
+        );
+        fail.add_eval(None, context.new_call(None, abort, &[]));
+        fail.end_with_return(None, index);
+        ok.end_with_return(None, index);
+
+        Runtime {
+            str_ty,
+            ptr_field,
+            len_field,
+            eq,
+            concat,
+            from_c,
+            check_index,
+            context,
+            arrays: RefCell::new(Vec::new()),
+            structs: RefCell::new(HashMap::new()),
+            enums: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// The struct type for arrays of `len` `element`s. The same type is
+    /// returned every time, since gccjit treats separately created structs as
+    /// different types.
+    pub fn array_type(&self, element: Type<'a>, len: u64) -> ArrayType<'a> {
+        let mut arrays = self.arrays.borrow_mut();
+        if let Some(array) = arrays.iter().find(|a| a.element == element && a.len == len) {
+            return *array;
+        }
+        let items_ty = self.context.new_array_type(None, element, len as i32);
+        let items = self.context.new_field(None, items_ty, "items");
+        let name = format!("zeta_array{}", arrays.len());
+        let ty = self.context.new_struct_type(None, name, &[items]).as_type();
+        let array = ArrayType {
+            ty,
+            items,
+            element,
+            len,
+        };
+        arrays.push(array);
+        array
+    }
+
+    /// The array type that `ty` is the struct of, if it is one.
+    pub fn as_array(&self, ty: Type<'a>) -> Option<ArrayType<'a>> {
+        self.arrays.borrow().iter().find(|a| a.ty == ty).copied()
+    }
+
+    /// Creates the struct `name` without its fields, so that the types of the
+    /// fields can refer to any struct or enum. See [`Runtime::define_struct`].
+    pub fn declare_struct(&self, name: &str) {
+        let handle = self.context.new_opaque_struct_type(None, name);
+        self.structs.borrow_mut().insert(
+            name.to_string(),
+            StructType {
+                handle,
+                fields: Vec::new(),
+            },
+        );
+    }
+
+    /// Gives the struct `name` its fields, in declaration order.
+    pub fn define_struct(&self, name: &str, fields: &[(&str, Type<'a>)]) {
+        let mut structs = self.structs.borrow_mut();
+        let declared = match structs.get_mut(name) {
+            Some(declared) => declared,
+            None => return,
+        };
+        declared.fields = fields
+            .iter()
+            .map(|&(field, ty)| (field.to_string(), self.context.new_field(None, ty, field)))
+            .collect();
+        let handles: Vec<_> = declared.fields.iter().map(|&(_, field)| field).collect();
+        declared.handle.set_fields(None, &handles);
+    }
+
+    /// Like [`Runtime::declare_struct`], for the enum `name`.
+    pub fn declare_enum(&self, name: &str) {
+        let handle = self.context.new_opaque_struct_type(None, name);
+        self.enums.borrow_mut().insert(
+            name.to_string(),
+            EnumType {
+                handle,
+                tag: None,
+                payload: None,
+                variants: Vec::new(),
+            },
+        );
+    }
+
+    /// Lays out the enum `name` with the payload types of each of its variants.
+    pub fn define_enum(&self, name: &str, variants: &[(&str, Vec<Type<'a>>)]) {
+        let mut enums = self.enums.borrow_mut();
+        let declared = match enums.get_mut(name) {
+            Some(declared) => declared,
+            None => return,
+        };
+        let tag = self
+            .context
+            .new_field(None, self.context.new_type::<i64>(), "tag");
+        let mut members = Vec::new();
+        declared.variants = variants
+            .iter()
+            .map(|(variant, types)| {
+                if types.is_empty() {
+                    return (variant.to_string(), None, Vec::new());
+                }
+                let fields: Vec<_> = types
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &ty)| self.context.new_field(None, ty, format!("f{}", i)))
+                    .collect();
+                let ty = self
+                    .context
+                    .new_struct_type(None, format!("zeta_{}_{}", name, variant), &fields)
+                    .as_type();
+                let member = self.context.new_field(None, ty, *variant);
+                members.push(member);
+                (variant.to_string(), Some(member), fields)
+            })
+            .collect();
+        let mut fields = vec![tag];
+        if !members.is_empty() {
+            let union =
+                self.context
+                    .new_union_type(None, format!("zeta_{}_payload", name), &members);
+            let payload = self.context.new_field(None, union, "payload");
+            fields.push(payload);
+            declared.payload = Some(payload);
+        }
+        declared.tag = Some(tag);
+        declared.handle.set_fields(None, &fields);
+    }
+
+    /// The struct or enum called `name`.
+    pub fn named_type(&self, name: &str) -> Option<Type<'a>> {
+        let structs = self.structs.borrow();
+        match structs.get(name) {
+            Some(s) => Some(s.handle.as_type()),
+            None => self.enums.borrow().get(name).map(|e| e.handle.as_type()),
+        }
+    }
+
+    /// The layout of the variant `name` of `ty`, if `ty` is one of the program's enums.
+    pub fn variant(&self, ty: Type<'a>, name: &str) -> Option<VariantLayout<'a>> {
+        let enums = self.enums.borrow();
+        let declared = enums.values().find(|e| e.handle.as_type() == ty)?;
+        let index = declared.variants.iter().position(|(v, ..)| v == name)?;
+        let (_, member, fields) = &declared.variants[index];
+        Some(VariantLayout {
+            tag: declared.tag?,
+            index: index as i64,
+            payload: declared.payload.zip(*member),
+            fields: fields.clone(),
+        })
+    }
+
+    /// The field `name` of `ty`, if `ty` is one of the program's structs.
+    pub fn field(&self, ty: Type<'a>, name: &str) -> Option<Field<'a>> {
+        let structs = self.structs.borrow();
+        let declared = structs.values().find(|s| s.handle.as_type() == ty)?;
+        declared
+            .fields
+            .iter()
+            .find(|(field, _)| field == name)
+            .map(|&(_, field)| field)
+    }
+
+    /// Builds a string value from a NUL terminated `ptr` and its length.
+    pub fn new_str(
+        &self,
+        fun: Function<'a>,
+        block: &Block<'a>,
+        ptr: RValue<'a>,
+        len: RValue<'a>,
+    ) -> RValue<'a> {
+        // gccjit has no struct constructor expressions, so go through a temporary.
+        let local = fun.new_local(None, self.str_ty, "str");
+        store_str(block, (self.ptr_field, self.len_field), local, ptr, len);
+        local.to_rvalue()
+    }
+}
+
+fn store_str<'a>(
+    block: &Block<'a>,
+    (ptr_field, len_field): (Field<'a>, Field<'a>),
+    local: LValue<'a>,
+    ptr: RValue<'a>,
+    len: RValue<'a>,
+) {
+    block.add_assignment(None, local.access_field(None, ptr_field), ptr);
+    block.add_assignment(None, local.access_field(None, len_field), len);
+}